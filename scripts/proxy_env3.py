@@ -38,7 +38,10 @@ class ProxyEnv3:
         "no_log": False,
         "total_frame":1000,
         # // optional
-        "mech": {}
+        "mech": {},
+        # // optional, reward 各项权重，step 的 info 中返回各项的值
+        # "reward": {"latency": 0.01, "slo_violation": 1.0, "cost": 0.0,
+        #            "cold_start": 0.1, "waste": 0.0, "churn": 0.0, "slo_ms": 1000.0},
     }
 
     env_id=""
@@ -76,12 +79,18 @@ class ProxyEnv3:
             "config":self.config
         })
         # print(f"reset success: {res.json()}")
-        self.env_id = res.json()['kernel']["env_id"]
-        pyid=records_read.conf_str(self.config)
-        rsid=self.env_id
-        # print(f"pyid: {pyid}")
-        # print(f"rsid: {rsid}")
-        assert(pyid==rsid)
+        kernel = res.json()['kernel']
+        if "env_id" not in kernel:
+            # InvalidConfig，errors 为出错字段的列表
+            errors = "\n".join(f"  {e['field']}: {e['msg']}" for e in kernel.get("errors", []))
+            raise ValueError(f"{kernel['msg']}\n{errors}")
+        self.env_id = kernel["env_id"]
+        if records_read.mech_is_legacy(self.config):
+            pyid=records_read.conf_str(self.config)
+            rsid=self.env_id
+            # print(f"pyid: {pyid}")
+            # print(f"rsid: {rsid}")
+            assert(pyid==rsid)
         return res.json()['kernel']
 
     # 用于向模拟环境的API发送执行步骤的请求，并返回API响应中的kernel部分
@@ -95,6 +104,49 @@ class ProxyEnv3:
         res = self.__request("step", {"action": action, "env_id": self.env_id})
         return res.json()['kernel']
 
+    # 一次请求让多个环境并行推进一帧，env 保留在服务端，返回 kernel 中的 states(观测的 json), scores, stops, infos
+    def step_batch(self, env_ids, actions):
+        res = self.__request("step_batch", {"env_ids": env_ids, "actions": actions})
+        return res.json()['kernel']
+
+    def spaces(self):
+        res = self.__request("spaces", {"env_id": self.env_id})
+        return res.json()['kernel']
+
+    def step_typed(self, action):
+        res = self.__request("step_typed", {"env_id": self.env_id, "action": list(action)})
+        return res.json()['kernel']
+
+    def rl_attach(self, points):
+        res = self.__request("rl_attach", {"env_id": self.env_id, "points": list(points)})
+        return res.json()['kernel']
+
+    def rl_poll(self, wait_ms=1000):
+        res = self.__request("rl_poll", {"env_id": self.env_id, "wait_ms": wait_ms})
+        return res.json()['kernel']
+
+    def rl_act(self, decision_id, action):
+        res = self.__request("rl_act", {"env_id": self.env_id, "decision_id": decision_id, "action": list(action)})
+        return res.json()['kernel']
+
+    # 增量查询调度、扩缩容决策日志，下次查询传返回的 next_seq
+    def audit_log(self, from_seq=0, limit=1000, kind="", fnid=-1, reqid=-1):
+        res = self.__request("audit_log", {"env_id": self.env_id, "from_seq": from_seq, "limit": limit,
+                                           "kind": kind, "fnid": fnid, "reqid": reqid})
+        return res.json()['kernel']
+
+    def snapshot(self, path=""):
+        res = self.__request("snapshot", {"env_id": self.env_id, "path": path})
+        return res.json()['kernel']
+
+    def restore(self, path):
+        res = self.__request("restore", {"path": path})
+        return res.json()['kernel']
+
+    def fork(self, mech=None):
+        res = self.__request("fork", {"env_id": self.env_id, "mech": mech})
+        return res.json()['kernel']
+
     def start_async_sim(self):
         def __start_sim():
             res = self.__request("step", {"action": 0, "env_id": self.env_id})
@@ -111,6 +163,6 @@ class ProxyEnv3:
             print("\n\n")
             return
         # 向模拟环境的API发送一个step请求，其中包含action和env_id信息
-        res = self.__request("rl_step", {"action": action})
+        res = self.__request("rl_step", {"action": action, "env_id": self.env_id})
         res = res.json()['kernel']
         return res['state'],res['score'],res['stop'],''
\ No newline at end of file
@@ -1,6 +1,9 @@
 import os
 import re
+import csv
 import json
+import struct
+from decimal import Decimal
 CUR_FPATH = os.path.abspath(__file__)
 CUR_FDIR = os.path.dirname(CUR_FPATH)
 # chdir to the directory of this script
@@ -8,6 +11,39 @@ os.chdir(CUR_FDIR)
 
 import re
 
+# 与 scale/num/down_filter.rs 中 ALGOS 的顺序一致
+FILTER_ALGOS = ['careful_down', 'replicas', 'rate_limit', 'stabilize', 'budget', 'zero_delay']
+
+# mech 的类型化格式 {"type": "pos", "mode": "greedy"} 由 rust 侧转换，python 侧只能算旧格式的 conf_str
+def mech_is_legacy(conf):
+    return all(isinstance(v, dict) and "type" not in v for v in conf['mech'].values())
+
+def to_f32(v):
+    return struct.unpack('f', struct.pack('f', v))[0]
+
+# 按 rust 中 f32 的 Display 格式化：能还原出同一个 f32 的最短十进制，不用科学计数法，整数不带小数点
+def rust_float(v):
+    v = to_f32(float(v))
+    if v != v:
+        return "NaN"
+    if v in (float('inf'), float('-inf')):
+        return "inf" if v > 0 else "-inf"
+    for prec in range(1, 18):
+        digits = "%.*e" % (prec - 1, v)
+        if to_f32(float(digits)) == v:
+            break
+    return format(Decimal(digits).normalize(), 'f')
+
+# 与 tenant.rs 中 tenants_digest 一致，整个租户配置的 FNV-1a 摘要
+def tenants_digest(tenants):
+    canonical = ";".join("{}:{}:{}:{}".format(t['name'], rust_float(t.get('weight', 1.0)), t.get('priority', 0),
+                                              ",".join(str(a) for a in t.get('apps', []))) for t in tenants)
+    h = 0xcbf29ce484222325
+    for b in canonical.encode():
+        h ^= b
+        h = (h * 0x100000001b3) & 0xFFFFFFFFFFFFFFFF
+    return "%016x" % h
+
 def conf_str(conf):
     rand_seed = conf['rand_seed']
     request_freq = conf['request_freq']
@@ -36,13 +72,15 @@ def conf_str(conf):
     sche=mech_part_conf('sche')
 
     # some_filter="!unready!"
-    some_filter=mech_part_conf_multi('filter')
-    some_filter.sort(key=lambda x: x[0])
-    some_filter = "".join([f"({k}.{v})" for k, v in some_filter])
+    # 与 MechConfig::filter_chain 一致，先按 filter_order，其余按 FILTER_ALGOS 的顺序
+    some_filter=dict(mech_part_conf_multi('filter'))
+    filter_order=[k for k in conf['mech'].get('filter_order', []) if k in some_filter]
+    filter_order+=[k for k in FILTER_ALGOS if k in some_filter and k not in filter_order]
+    some_filter = "".join([f"({k}.{some_filter[k]})" for k in filter_order])
 
     instance_cache_policy=mech_part_conf('instance_cache_policy')
 
-    return "sd{}.rf{}.dt{}.cs{}.ft{}.nml{}.mt{}.scl({}.{})({}.{})({}.{})[{}].scd({}.{}).ic({}.{})".\
+    s = "sd{}.rf{}.dt{}.cs{}.ft{}.nml{}.mt{}.scl({}.{})({}.{})({}.{})[{}].scd({}.{}).ic({}.{})".\
         format(rand_seed,request_freq,dag_type,cold_start,fn_type,1 if no_mech_latency else 0,mech_type[0],\
                scale_num[0],scale_num[1],\
                scale_down_exec[0],scale_down_exec[1],\
@@ -50,6 +88,29 @@ def conf_str(conf):
                some_filter,
                sche[0],sche[1],\
                instance_cache_policy[0],instance_cache_policy[1])
+    # 与 Config::str 保持一致，非默认值时才追加
+    if conf.get('event_driven', False):
+        s += ".ed"
+    frame_duration_ms = conf.get('frame_duration_ms', 1.0)
+    if frame_duration_ms != 1.0:
+        s += ".fd{}".format(rust_float(frame_duration_ms))
+    tenants = conf.get('tenants', [])
+    if len(tenants) > 0:
+        s += ".tn{}".format(tenants_digest(tenants))
+    # 缺省值与 AdmissionConfig::default 一致
+    admission = conf.get('admission', {})
+    if admission.get('policy', 'none') != 'none':
+        s += ".ad{}_{}_{}_{}_{}_{}".format(admission['policy'], admission.get('max_inflight', 1000),
+                                           rust_float(admission.get('rate_per_s', 100.0)),
+                                           rust_float(admission.get('burst', 100.0)),
+                                           rust_float(admission.get('max_defer_ms', 1000.0)),
+                                           rust_float(admission.get('penalty_ms', 0.0)))
+    timeout = conf.get('timeout', {})
+    request_ms = timeout.get('request_ms', 0.0)
+    fn_ms = timeout.get('fn_ms', 0.0)
+    if request_ms > 0 or fn_ms > 0:
+        s += ".to{}_{}_{}".format(rust_float(request_ms), rust_float(fn_ms), rust_float(timeout.get('penalty_ms', 0.0)))
+    return s
 
 
 def spec_conf_cnt(conf):
@@ -237,6 +298,54 @@ class Frame:
         return self.frame[self.idxs['FRAME_IDX_FNCONTAINER_COUNT']]
     
 
+# 按列导出的记录，见 serverless_sim/src/columnar.rs
+def columnar_dir(record_name):
+    return f"../serverless_sim/records/columnar/{record_name}"
+
+COLUMN_TYPES = {
+    'uint': int,
+    'float': float,
+    'str': str,
+    'bool': lambda v: v == 'true',
+}
+
+def load_columnar_table(record_name, table):
+    """按 schema.json 读取一张表，返回 {列名: [值]}，空值为 None"""
+    dir = columnar_dir(record_name)
+    with open(f"{dir}/schema.json", 'r') as f:
+        schema = json.load(f)
+    table_schema = [t for t in schema['tables'] if t['name'] == table][0]
+    csv_file = [f for f in table_schema['files'] if f.endswith('.csv')][0]
+    convert = {c['name']: COLUMN_TYPES[c['type']] for c in table_schema['columns']}
+    cols = {name: [] for name in convert}
+    with open(f"{dir}/{csv_file}", 'r', newline='') as f:
+        for row in csv.DictReader(f):
+            for name, v in row.items():
+                cols[name].append(None if v == '' else convert[name](v))
+    return cols
+
+def load_record_from_columnar(record, record_name):
+    frames = load_columnar_table(record_name, 'frames')
+    frame_cnt = frames['frame'][-1]
+    record.cost_per_req = frames['cost_per_req'][-1]
+    record.time_per_req = frames['req_done_time_avg_ms'][-1]
+    record.score = frames['score'][-1]
+    record.rps = sum(frames['done_req_cnt'])/frame_cnt
+    record.coldstart_time_per_req = frames['req_wait_coldstart_time_ms'][-1]
+    record.waitsche_time_per_req = frames['req_wait_sche_time_ms'][-1]
+    record.datarecv_time_per_req = frames['req_data_recv_time_ms'][-1]
+    record.exe_time_per_req = frames['req_exe_time_ms'][-1]
+    record.fn_container_cnt = sum(frames['fn_container_cnt'])/frame_cnt
+    record.undone_req_cnt = frames['running_req_cnt'][-1]
+
+# 请求粒度的日志，见 serverless_sim/src/request_log.rs
+def load_request_log(record_name):
+    """逐行读取 records/request_log/<record_name>.jsonl，时间为帧数"""
+    with open(f"../serverless_sim/records/request_log/{record_name}.jsonl", 'r') as f:
+        for line in f:
+            if line.strip():
+                yield json.loads(line)
+
 def load_record_from_file(filename):
     record = PackedRecord()
     record.filename = filename
@@ -261,6 +370,9 @@ def load_record_from_file(filename):
         record.exe_time_per_req = cacherecord['exe_time_per_req']
         record.fn_container_cnt = cacherecord['fn_container_cnt']
         record.undone_req_cnt = cacherecord['undone_req_cnt']
+    elif filename.endswith(".json") and os.path.exists(columnar_dir(filename[:-len(".json")])):
+        # 有按列导出的记录时不依赖 FRAME_IDX_ 下标
+        load_record_from_columnar(record, filename[:-len(".json")])
     else:
         # seek to filesize - 1000 
         # read lines
@@ -2,8 +2,11 @@ use enum_as_inner::EnumAsInner;
 
 pub type RawAction = u32;
 
-#[derive(EnumAsInner, Clone)]
+/// step 传给扩缩容、调度算法的 action，取值范围由 `spaces::Space` 描述
+#[derive(EnumAsInner, Clone, Debug, PartialEq)]
 pub enum ESActionWrapper {
-    // Float(f32),
+    Float(f32),
     Int(u32),
+    /// MultiDiscrete 或多维 Box
+    Vec(Vec<f32>),
 }
@@ -0,0 +1,269 @@
+//! 准入控制
+//!
+//! 新生成的请求先经过准入控制再进入 requests:
+//! - none: 全部接纳
+//! - reject: 在途请求数达到 max_inflight 时直接拒绝(503)
+//! - token_bucket: 每个函数一个令牌桶，每秒补充 rate_per_s 个、最多攒 burst 个，
+//!   请求要为 DAG 中每个函数各取一个令牌，取不到时限流(429)，类似 Lambda 的并发限制
+//! - defer: 在途请求数达到 max_inflight 时放进等待队列，之后每帧按顺序重试，
+//!   等待超过 max_defer_ms 的丢弃。请求的开始帧仍为生成的帧，延迟包含等待的时间
+//!
+//! 被拒绝、限流和等待超时丢弃的请求计入 `AdmissionStats`，
+//! penalty_ms 大于 0 时 score 把它们当作延迟为 penalty_ms 的完成请求
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{check_one_of, ConfigError},
+    fn_dag::{DagId, EnvFnExt, FnId},
+    request::Request,
+    sim_env::SimEnv,
+};
+
+pub const ADMISSION_POLICIES: &[&str] = &["none", "reject", "token_bucket", "defer"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdmissionConfig {
+    /// none, reject, token_bucket, defer
+    pub policy: String,
+    /// reject、defer 允许的在途请求数
+    pub max_inflight: usize,
+    /// token_bucket 每个函数每秒补充的令牌数
+    pub rate_per_s: f32,
+    /// token_bucket 每个函数最多攒的令牌数，即允许的突发
+    pub burst: f32,
+    /// defer 最多等待的时间(ms)
+    pub max_defer_ms: f32,
+    /// 每个被丢弃的请求在 score 中按这个延迟(ms)计入，为 0 时不计入
+    pub penalty_ms: f32,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            policy: "none".to_owned(),
+            max_inflight: 1000,
+            rate_per_s: 100.0,
+            burst: 100.0,
+            max_defer_ms: 1000.0,
+            penalty_ms: 0.0,
+        }
+    }
+}
+
+impl AdmissionConfig {
+    pub fn enabled(&self) -> bool {
+        self.policy != "none"
+    }
+
+    pub fn check_valid(&self, errs: &mut Vec<ConfigError>) {
+        check_one_of(errs, "admission.policy", &self.policy, ADMISSION_POLICIES);
+        if self.max_inflight == 0 {
+            errs.push(ConfigError::new("admission.max_inflight", "should be greater than 0"));
+        }
+        if !(self.rate_per_s.is_finite() && self.rate_per_s >= 0.0) {
+            errs.push(
+                ConfigError::new("admission.rate_per_s", format!("should be non-negative, got {}", self.rate_per_s))
+            );
+        }
+        if !(self.burst.is_finite() && self.burst >= 1.0) {
+            errs.push(ConfigError::new("admission.burst", format!("should be at least 1, got {}", self.burst)));
+        }
+        for (name, v) in [("max_defer_ms", self.max_defer_ms), ("penalty_ms", self.penalty_ms)] {
+            if !(v.is_finite() && v >= 0.0) {
+                errs.push(ConfigError::new(format!("admission.{}", name), format!("should be non-negative, got {}", v)));
+            }
+        }
+    }
+}
+
+/// 从仿真开始累计的准入结果
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AdmissionStats {
+    pub admitted: usize,
+    pub rejected: usize,
+    pub throttled: usize,
+    /// 等待超时丢弃的
+    pub defer_dropped: usize,
+    /// 当前还在等待队列中的
+    pub deferred: usize,
+}
+
+impl AdmissionStats {
+    /// 被丢弃的请求总数
+    pub fn shed(&self) -> usize {
+        self.rejected + self.throttled + self.defer_dropped
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenBucket {
+    tokens: f32,
+    /// 上次补充令牌的帧
+    frame: usize,
+}
+
+impl TokenBucket {
+    pub fn full(burst: f32, frame: usize) -> Self {
+        Self { tokens: burst, frame }
+    }
+
+    /// 按经过的帧数补充令牌
+    pub fn refill(&mut self, frame: usize, per_frame: f32, burst: f32) {
+        let passed = frame.saturating_sub(self.frame) as f32;
+        self.tokens = (self.tokens + passed * per_frame).min(burst);
+        self.frame = self.frame.max(frame);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AdmissionState {
+    buckets: HashMap<FnId, TokenBucket>,
+    /// 等待准入的 (dag_i, 生成的帧)
+    deferred: VecDeque<(DagId, usize)>,
+    pub stats: AdmissionStats,
+}
+
+impl AdmissionState {
+    /// fns 中每个函数都有令牌时各扣一个，否则不扣
+    pub fn take_tokens(&mut self, conf: &AdmissionConfig, fns: &[FnId], frame: usize, frame_ms: f32) -> bool {
+        let per_frame = conf.rate_per_s * frame_ms / 1000.0;
+        for &fnid in fns {
+            self.buckets
+                .entry(fnid)
+                .or_insert_with(|| TokenBucket::full(conf.burst, frame))
+                .refill(frame, per_frame, conf.burst);
+        }
+        if fns.iter().any(|f| self.buckets[f].tokens < 1.0) {
+            return false;
+        }
+        for f in fns {
+            self.buckets.get_mut(f).unwrap().tokens -= 1.0;
+        }
+        true
+    }
+}
+
+impl SimEnv {
+    fn admission_overloaded(&self) -> bool {
+        self.core.requests().len() >= self.help.config().admission.max_inflight
+    }
+
+    fn admission_accept(&self, dag_i: DagId, begin_frame: usize) {
+        let request = Request::new(self, dag_i, begin_frame);
+        self.core.requests_mut().insert(request.req_id, request);
+        self.help.admission_mut().stats.admitted += 1;
+    }
+
+    /// 新生成的请求经过准入控制，接纳的放进 requests
+    pub fn admission_submit(&self, dag_i: DagId) {
+        let conf = &self.help.config().admission;
+        let frame = self.current_frame();
+        match &*conf.policy {
+            "reject" if self.admission_overloaded() => {
+                self.help.admission_mut().stats.rejected += 1;
+            }
+            "token_bucket" => {
+                let fns = self.dag_fns(dag_i);
+                let ok = self.help
+                    .admission_mut()
+                    .take_tokens(conf, &fns, frame, self.help.config().frame_duration_ms);
+                if ok {
+                    self.admission_accept(dag_i, frame);
+                } else {
+                    self.help.admission_mut().stats.throttled += 1;
+                }
+            }
+            "defer" if self.admission_overloaded() || !self.help.admission().deferred.is_empty() => {
+                // 前面还有在等的请求时也排到后面，保持先来先服务
+                let mut state = self.help.admission_mut();
+                state.deferred.push_back((dag_i, frame));
+                state.stats.deferred = state.deferred.len();
+            }
+            _ => self.admission_accept(dag_i, frame),
+        }
+    }
+
+    /// 生成新请求之前，按顺序重试等待队列中的请求
+    pub fn admission_retry_deferred(&self) {
+        if self.help.admission().deferred.is_empty() {
+            return;
+        }
+        let max_wait = self.help.config().ms_to_frames(self.help.config().admission.max_defer_ms);
+        let frame = self.current_frame();
+        loop {
+            let Some(&(dag_i, gen_frame)) = self.help.admission().deferred.front() else {
+                break;
+            };
+            if frame.saturating_sub(gen_frame) > max_wait {
+                self.help.admission_mut().stats.defer_dropped += 1;
+            } else if self.admission_overloaded() {
+                break;
+            } else {
+                self.admission_accept(dag_i, gen_frame);
+            }
+            self.help.admission_mut().deferred.pop_front();
+        }
+        let mut state = self.help.admission_mut();
+        state.stats.deferred = state.deferred.len();
+    }
+
+    fn dag_fns(&self, dag_i: DagId) -> Vec<FnId> {
+        let dag = self.dag(dag_i);
+        let mut walker = dag.new_dag_walker();
+        let mut fns = vec![];
+        while let Some(fngi) = walker.next(&dag.dag_inner) {
+            fns.push(dag.dag_inner[fngi]);
+        }
+        fns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdmissionConfig, AdmissionState, TokenBucket};
+
+    #[test]
+    fn test_token_bucket() {
+        let mut b = TokenBucket::full(2.0, 0);
+        b.tokens = 0.0;
+        b.refill(3, 0.5, 2.0);
+        assert_eq!(b.tokens, 1.5);
+        b.refill(10, 0.5, 2.0);
+        assert_eq!(b.tokens, 2.0);
+
+        // 1 帧 10ms，每秒 100 个即每帧 1 个
+        let conf = AdmissionConfig {
+            policy: "token_bucket".to_owned(),
+            rate_per_s: 100.0,
+            burst: 1.0,
+            ..Default::default()
+        };
+        let mut state = AdmissionState::default();
+        assert!(state.take_tokens(&conf, &[0, 1], 0, 10.0));
+        assert!(!state.take_tokens(&conf, &[1], 0, 10.0));
+        // 函数 2 的桶还是满的，但函数 1 没有令牌，整个请求都不扣
+        assert!(!state.take_tokens(&conf, &[2, 1], 0, 10.0));
+        assert!(state.take_tokens(&conf, &[2], 0, 10.0));
+        assert!(state.take_tokens(&conf, &[1], 1, 10.0));
+    }
+
+    #[test]
+    fn test_admission_check() {
+        let mut errs = vec![];
+        AdmissionConfig::default().check_valid(&mut errs);
+        assert!(errs.is_empty());
+        let conf = AdmissionConfig {
+            policy: "drop".to_owned(),
+            burst: 0.5,
+            penalty_ms: -1.0,
+            ..Default::default()
+        };
+        conf.check_valid(&mut errs);
+        let fields = errs.into_iter().map(|e| e.field).collect::<Vec<_>>();
+        assert_eq!(fields, vec!["admission.policy", "admission.burst", "admission.penalty_ms"]);
+    }
+}
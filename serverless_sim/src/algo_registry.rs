@@ -0,0 +1,240 @@
+//! 算法注册表
+//!
+//! 调度器、扩缩容算法、扩缩容执行器、过滤器和缓存策略各自在实现文件里声明一个 `AlgoEntry`：
+//! 名字、参数说明、支持的 mech_type 和构造函数，再加到所在目录 mod.rs 的 `ALGOS` 列表中。
+//! 配置检查、类型化配置解析、构造算法实例以及 module_conf_es.json 都从这里读取，
+//! 新增算法不需要再改 mechanism.rs 和 mechanism_conf.rs
+
+use std::fs::File;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    cache::InstanceCachePolicy,
+    fn_dag::FnId,
+    scale::{
+        down_exec::ScaleDownExec,
+        num::{down_filter::ScaleFilter, ScaleNum},
+        up_exec::ScaleUpExec,
+    },
+    sim_run::Scheduler,
+};
+
+/// 算法参数，即类型化配置中除 type 以外的字段。
+/// 解析后转成旧格式的参数字符串保存在 MechConfig 中
+pub trait AlgoParams: DeserializeOwned {
+    fn to_attr(&self) -> String;
+}
+
+/// 没有参数的算法，出现任何字段都报错
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NoParams {}
+
+impl AlgoParams for NoParams {
+    fn to_attr(&self) -> String {
+        String::new()
+    }
+}
+
+/// 参数说明，只用于生成文档
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    /// 如 usize、greedy|random
+    pub ty: &'static str,
+    pub default: &'static str,
+    pub desc: &'static str,
+}
+
+/// 算法的元信息，不含构造函数
+#[derive(Clone, Copy)]
+pub struct AlgoInfo {
+    pub name: &'static str,
+    pub params: &'static [ParamSpec],
+    /// 支持的 mech_type，为空表示全部支持
+    pub mech_types: &'static [&'static str],
+    /// 类型化配置的参数转成旧格式的参数字符串
+    pub parse_params: fn(&Map<String, Value>) -> Result<String, String>,
+    /// 检查旧格式的参数字符串
+    pub check_attr: fn(&str) -> Result<(), String>,
+}
+
+impl AlgoInfo {
+    /// 没有参数、支持给定 mech_type 的算法
+    pub const fn simple(name: &'static str, mech_types: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            params: &[],
+            mech_types,
+            parse_params: parse_params::<NoParams>,
+            check_attr: any_attr,
+        }
+    }
+
+    pub fn supports(&self, mech_type: &str) -> bool {
+        self.mech_types.is_empty() || self.mech_types.contains(&mech_type)
+    }
+}
+
+pub struct AlgoEntry<T: ?Sized> {
+    pub info: AlgoInfo,
+    /// 参数为旧格式的参数字符串，调用前已经通过 check_attr
+    pub new: fn(&str) -> Box<T>,
+}
+
+pub type ScheEntry = AlgoEntry<dyn Scheduler + Send>;
+pub type ScaleNumEntry = AlgoEntry<dyn ScaleNum + Send>;
+pub type ScaleDownExecEntry = AlgoEntry<dyn ScaleDownExec>;
+pub type ScaleUpExecEntry = AlgoEntry<dyn ScaleUpExec>;
+pub type FilterEntry = AlgoEntry<dyn ScaleFilter>;
+pub type CachePolicyEntry = AlgoEntry<dyn InstanceCachePolicy<FnId>>;
+
+pub fn parse_params<P: AlgoParams>(params: &Map<String, Value>) -> Result<String, String> {
+    serde_json::from_value::<P>(Value::Object(params.clone()))
+        .map(|p| p.to_attr())
+        .map_err(|e| e.to_string())
+}
+
+/// 不读取参数字符串的算法
+pub fn any_attr(_attr: &str) -> Result<(), String> {
+    Ok(())
+}
+
+/// 切分 "k1=v1,k2=v2" 形式的旧格式参数，省略的项由调用方取默认值
+pub fn parse_kv_attr<'a>(algo: &str, arg: &'a str) -> Result<Vec<(&'a str, &'a str)>, String> {
+    arg.split(',')
+        .map(str::trim)
+        .filter(|kv| !kv.is_empty())
+        .map(|kv| {
+            kv.split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("{} arg should be key=value, got {:?}", algo, kv))
+        })
+        .collect()
+}
+
+pub fn find<T: ?Sized>(algos: &'static [AlgoEntry<T>], name: &str) -> Option<&'static AlgoEntry<T>> {
+    algos.iter().find(|e| e.info.name == name)
+}
+
+/// MechConfig 中除 mech_type 外的各个阶段
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    ScaleNum,
+    ScaleDownExec,
+    ScaleUpExec,
+    Sche,
+    Filter,
+    InstanceCachePolicy,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::ScaleNum,
+        Stage::ScaleDownExec,
+        Stage::ScaleUpExec,
+        Stage::Sche,
+        Stage::Filter,
+        Stage::InstanceCachePolicy,
+    ];
+
+    /// MechConfig 中的字段名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::ScaleNum => "scale_num",
+            Stage::ScaleDownExec => "scale_down_exec",
+            Stage::ScaleUpExec => "scale_up_exec",
+            Stage::Sche => "sche",
+            Stage::Filter => "filter",
+            Stage::InstanceCachePolicy => "instance_cache_policy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Stage> {
+        Stage::ALL.iter().copied().find(|s| s.name() == name)
+    }
+
+    pub fn algos(&self) -> Vec<AlgoInfo> {
+        fn infos<T: ?Sized>(algos: &[AlgoEntry<T>]) -> Vec<AlgoInfo> {
+            algos.iter().map(|e| e.info).collect()
+        }
+        match self {
+            Stage::ScaleNum => infos(crate::scale::num::ALGOS),
+            Stage::ScaleDownExec => infos(crate::scale::down_exec::ALGOS),
+            Stage::ScaleUpExec => infos(crate::scale::up_exec::ALGOS),
+            Stage::Sche => infos(crate::sche::ALGOS),
+            Stage::Filter => infos(crate::scale::num::down_filter::ALGOS),
+            Stage::InstanceCachePolicy => infos(crate::cache::ALGOS),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.algos()
+            .iter()
+            .map(|a| a.name)
+            .collect()
+    }
+
+    pub fn find(&self, name: &str) -> Option<AlgoInfo> {
+        self.algos().into_iter().find(|a| a.name == name)
+    }
+}
+
+#[derive(Serialize)]
+struct AlgoSchema {
+    name: &'static str,
+    params: &'static [ParamSpec],
+    mech_types: &'static [&'static str],
+}
+
+/// 各阶段可选的算法及参数说明，写到 module_schema_es.json 供前端和脚本查看
+pub fn export_schema_file() {
+    let schema = Stage::ALL
+        .iter()
+        .map(|s| {
+            let algos = s
+                .algos()
+                .iter()
+                .map(|a| AlgoSchema {
+                    name: a.name,
+                    params: a.params,
+                    mech_types: a.mech_types,
+                })
+                .collect::<Vec<_>>();
+            (s.name(), algos)
+        })
+        .collect::<std::collections::BTreeMap<_, _>>();
+    let file = File::create("module_schema_es.json").unwrap();
+    serde_json::to_writer_pretty(file, &schema).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stage;
+    use crate::mechanism::MECH_NAMES;
+
+    #[test]
+    fn test_registry_names() {
+        for stage in Stage::ALL {
+            let mut names = stage.names();
+            assert!(!names.is_empty(), "{:?}", stage);
+            let cnt = names.len();
+            names.sort();
+            names.dedup();
+            assert_eq!(names.len(), cnt, "duplicated algo name in {:?}", stage);
+            for a in stage.algos() {
+                for m in a.mech_types {
+                    assert!(MECH_NAMES.contains(m), "{} of {} unknown mech_type {}", stage.name(), a.name, m);
+                }
+            }
+        }
+        // 每种 mech_type 的每个必选阶段至少有一个算法
+        for m in MECH_NAMES {
+            for stage in [Stage::ScaleNum, Stage::ScaleDownExec, Stage::ScaleUpExec, Stage::Sche] {
+                assert!(stage.algos().iter().any(|a| a.supports(m)), "{} has no {}", m, stage.name());
+            }
+        }
+    }
+}
@@ -81,6 +81,7 @@ pub enum ResetResp{
 },
     InvalidConfig{
        msg:String,
+       errors:Value,
 },
 
 }
@@ -182,10 +183,431 @@ impl RlStepResp {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RlStepReq {
+       pub env_id:String,
        pub action:i32,
 }
 
 
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StepBatchResp{
+    Success{
+       states:Vec<String>,
+       scores:Vec<f64>,
+       stops:Vec<bool>,
+       infos:Vec<String>,
+},
+    EnvNotFound{
+       msg:String,
+},
+    InvalidReq{
+       msg:String,
+},
+
+}
+
+impl StepBatchResp {
+    fn id(&self)->u32 {
+        match self {
+                StepBatchResp::Success{..}=>1,
+    StepBatchResp::EnvNotFound{..}=>2,
+    StepBatchResp::InvalidReq{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepBatchReq {
+       pub env_ids:Vec<String>,
+       pub actions:Vec<i32>,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SnapshotResp{
+    Success{
+       path:String,
+},
+    EnvNotFound{
+       msg:String,
+},
+    Failed{
+       msg:String,
+},
+
+}
+
+impl SnapshotResp {
+    fn id(&self)->u32 {
+        match self {
+                SnapshotResp::Success{..}=>1,
+    SnapshotResp::EnvNotFound{..}=>2,
+    SnapshotResp::Failed{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotReq {
+       pub env_id:String,
+       pub path:String,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RestoreResp{
+    Success{
+       env_id:String,
+},
+    Failed{
+       msg:String,
+},
+
+}
+
+impl RestoreResp {
+    fn id(&self)->u32 {
+        match self {
+                RestoreResp::Success{..}=>1,
+    RestoreResp::Failed{..}=>2,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreReq {
+       pub path:String,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ForkResp{
+    Success{
+       env_id:String,
+},
+    EnvNotFound{
+       msg:String,
+},
+    Failed{
+       msg:String,
+},
+
+}
+
+impl ForkResp {
+    fn id(&self)->u32 {
+        match self {
+                ForkResp::Success{..}=>1,
+    ForkResp::EnvNotFound{..}=>2,
+    ForkResp::Failed{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForkReq {
+       pub env_id:String,
+       pub mech:Value,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpacesResp{
+    Success{
+       version:i32,
+       observation:Value,
+       action:Value,
+},
+    EnvNotFound{
+       msg:String,
+},
+
+}
+
+impl SpacesResp {
+    fn id(&self)->u32 {
+        match self {
+                SpacesResp::Success{..}=>1,
+    SpacesResp::EnvNotFound{..}=>2,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpacesReq {
+       pub env_id:String,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StepTypedResp{
+    Success{
+       obs:Vec<f64>,
+       score:f64,
+       stop:bool,
+       info:String,
+},
+    EnvNotFound{
+       msg:String,
+},
+    InvalidAction{
+       msg:String,
+},
+
+}
+
+impl StepTypedResp {
+    fn id(&self)->u32 {
+        match self {
+                StepTypedResp::Success{..}=>1,
+    StepTypedResp::EnvNotFound{..}=>2,
+    StepTypedResp::InvalidAction{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepTypedReq {
+       pub env_id:String,
+       pub action:Vec<f64>,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RlAttachResp{
+    Success{
+       points:Vec<String>,
+},
+    EnvNotFound{
+       msg:String,
+},
+    InvalidReq{
+       msg:String,
+},
+
+}
+
+impl RlAttachResp {
+    fn id(&self)->u32 {
+        match self {
+                RlAttachResp::Success{..}=>1,
+    RlAttachResp::EnvNotFound{..}=>2,
+    RlAttachResp::InvalidReq{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RlAttachReq {
+       pub env_id:String,
+       pub points:Vec<String>,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RlPollResp{
+    Pending{
+       decision_id:i32,
+       point:String,
+       fnid:i32,
+       reqid:i32,
+       default_choice:i32,
+       obs:Vec<f64>,
+       action_space:Value,
+},
+    NoPending{
+       msg:String,
+},
+    EnvNotFound{
+       msg:String,
+},
+
+}
+
+impl RlPollResp {
+    fn id(&self)->u32 {
+        match self {
+                RlPollResp::Pending{..}=>1,
+    RlPollResp::NoPending{..}=>2,
+    RlPollResp::EnvNotFound{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RlPollReq {
+       pub env_id:String,
+       pub wait_ms:i32,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RlActResp{
+    Success{
+       decision_id:i32,
+},
+    InvalidAction{
+       msg:String,
+},
+    EnvNotFound{
+       msg:String,
+},
+
+}
+
+impl RlActResp {
+    fn id(&self)->u32 {
+        match self {
+                RlActResp::Success{..}=>1,
+    RlActResp::InvalidAction{..}=>2,
+    RlActResp::EnvNotFound{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RlActReq {
+       pub env_id:String,
+       pub decision_id:i32,
+       pub action:Vec<f64>,
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuditLogResp{
+    Success{
+       records:Value,
+       next_seq:i32,
+},
+    EnvNotFound{
+       msg:String,
+},
+    InvalidReq{
+       msg:String,
+},
+
+}
+
+impl AuditLogResp {
+    fn id(&self)->u32 {
+        match self {
+                AuditLogResp::Success{..}=>1,
+    AuditLogResp::EnvNotFound{..}=>2,
+    AuditLogResp::InvalidReq{..}=>3,
+
+        }
+    }
+    pub fn serialize(&self)->Value {
+        json!({
+            "id": self.id(),
+            "kernel": serde_json::to_value(self).unwrap(),
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogReq {
+       pub env_id:String,
+       pub from_seq:i32,
+       pub limit:i32,
+       pub kind:String,
+       pub fnid:i32,
+       pub reqid:i32,
+}
+
+
 #[async_trait]
 pub trait ApiHandler {
     
@@ -199,6 +621,26 @@ pub trait ApiHandler {
             
     async fn handle_rl_step(&self, req:RlStepReq)->RlStepResp;
             
+    async fn handle_step_batch(&self, req:StepBatchReq)->StepBatchResp;
+            
+    async fn handle_snapshot(&self, req:SnapshotReq)->SnapshotResp;
+            
+    async fn handle_restore(&self, req:RestoreReq)->RestoreResp;
+            
+    async fn handle_fork(&self, req:ForkReq)->ForkResp;
+            
+    async fn handle_spaces(&self, req:SpacesReq)->SpacesResp;
+            
+    async fn handle_step_typed(&self, req:StepTypedReq)->StepTypedResp;
+            
+    async fn handle_rl_attach(&self, req:RlAttachReq)->RlAttachResp;
+            
+    async fn handle_rl_poll(&self, req:RlPollReq)->RlPollResp;
+            
+    async fn handle_rl_act(&self, req:RlActReq)->RlActResp;
+            
+    async fn handle_audit_log(&self, req:AuditLogReq)->AuditLogResp;
+            
 }
 
 
@@ -235,6 +677,66 @@ pub fn add_routers(mut router:Router)->Router
     router=router
         .route("/rl_step", post(rl_step));
                              
+    async fn step_batch(Json(req):Json<StepBatchReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_step_batch(req).await.serialize()))
+    }
+    router=router
+        .route("/step_batch", post(step_batch));
+                             
+    async fn snapshot(Json(req):Json<SnapshotReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_snapshot(req).await.serialize()))
+    }
+    router=router
+        .route("/snapshot", post(snapshot));
+                             
+    async fn restore(Json(req):Json<RestoreReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_restore(req).await.serialize()))
+    }
+    router=router
+        .route("/restore", post(restore));
+                             
+    async fn fork(Json(req):Json<ForkReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_fork(req).await.serialize()))
+    }
+    router=router
+        .route("/fork", post(fork));
+                             
+    async fn spaces(Json(req):Json<SpacesReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_spaces(req).await.serialize()))
+    }
+    router=router
+        .route("/spaces", post(spaces));
+                             
+    async fn step_typed(Json(req):Json<StepTypedReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_step_typed(req).await.serialize()))
+    }
+    router=router
+        .route("/step_typed", post(step_typed));
+                             
+    async fn rl_attach(Json(req):Json<RlAttachReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_rl_attach(req).await.serialize()))
+    }
+    router=router
+        .route("/rl_attach", post(rl_attach));
+                             
+    async fn rl_poll(Json(req):Json<RlPollReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_rl_poll(req).await.serialize()))
+    }
+    router=router
+        .route("/rl_poll", post(rl_poll));
+                             
+    async fn rl_act(Json(req):Json<RlActReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_rl_act(req).await.serialize()))
+    }
+    router=router
+        .route("/rl_act", post(rl_act));
+                             
+    async fn audit_log(Json(req):Json<AuditLogReq>)-> (StatusCode, Json<Value>){
+        (StatusCode::OK, Json(ApiHandlerImpl.handle_audit_log(req).await.serialize()))
+    }
+    router=router
+        .route("/audit_log", post(audit_log));
+                             
     
     router
 }
@@ -0,0 +1,248 @@
+//! 调度、扩缩容指令的决策日志
+//!
+//! 算法线程下发的 ScheCmd/UpCmd/DownCmd/PreemptCmd 在仿真侧应用时记一条 `DecisionRecord`:
+//! 哪个算法下发、在哪一帧应用、候选节点及打分、是否真正生效(例如内存不够时扩容被丢弃)。
+//! 算法可以在下发指令前通过 `AuditHub::note_candidates` 附上候选节点，
+//! 应用指令时按 (kind, reqid, fnid) 取出。
+//! 日志只保留最近 `AUDIT_LOG_CAP` 条，通过 http `audit_log` 按 seq 增量查询
+
+use std::collections::{HashMap, VecDeque};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::{config::Config, fn_dag::FnId, node::NodeId, request::ReqId};
+
+/// 最多保留的决策条数，超过后丢掉最旧的
+pub const AUDIT_LOG_CAP: usize = 100_000;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionKind {
+    Sche,
+    ScaleUp,
+    ScaleDown,
+    /// 撤回已调度的任务
+    Preempt,
+}
+
+impl DecisionKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sche" => Some(Self::Sche),
+            "scale_up" => Some(Self::ScaleUp),
+            "scale_down" => Some(Self::ScaleDown),
+            "preempt" => Some(Self::Preempt),
+            _ => None,
+        }
+    }
+}
+
+/// 候选节点，score 的含义由算法决定(如 pos 为 1/(任务数+1)，bp_balance 为饥饿程度)
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CandidateScore {
+    pub nid: NodeId,
+    pub score: Option<f32>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    Applied,
+    Dropped { reason: String },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DecisionRecord {
+    pub seq: u64,
+    /// 指令被应用时的帧
+    pub frame: usize,
+    pub kind: DecisionKind,
+    /// 下发指令的算法，agent 在调度控制点替换了节点时为 rl
+    pub algo: String,
+    pub reqid: Option<ReqId>,
+    pub fnid: FnId,
+    pub nid: NodeId,
+    /// 算法没有附上候选节点时为空
+    pub candidates: Vec<CandidateScore>,
+    pub outcome: DecisionOutcome,
+}
+
+#[derive(Default)]
+struct DecisionNote {
+    algo: Option<String>,
+    candidates: Vec<CandidateScore>,
+}
+
+type NoteKey = (DecisionKind, Option<ReqId>, FnId);
+
+#[derive(Default)]
+struct AuditInner {
+    next_seq: u64,
+    records: VecDeque<DecisionRecord>,
+    notes: HashMap<NoteKey, DecisionNote>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    /// 只返回 seq >= from_seq 的记录
+    pub from_seq: u64,
+    pub limit: usize,
+    pub kind: Option<DecisionKind>,
+    pub fnid: Option<FnId>,
+    pub reqid: Option<ReqId>,
+}
+
+/// 和机制线程共享，机制线程写 note，仿真线程写记录
+pub struct AuditHub {
+    sche_algo: String,
+    scale_algo: String,
+    inner: Mutex<AuditInner>,
+}
+
+// 配置中选中的算法，没有选中时为 none
+fn selected(conf: &HashMap<String, Option<String>>) -> &str {
+    conf.iter()
+        .find(|(_, v)| v.is_some())
+        .map(|(k, _)| k.as_str())
+        .unwrap_or("none")
+}
+
+impl AuditHub {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            sche_algo: selected(&config.mech.sche).to_owned(),
+            scale_algo: format!(
+                "{}+{}",
+                selected(&config.mech.scale_num),
+                selected(&config.mech.scale_up_exec)
+            ),
+            inner: Mutex::new(AuditInner::default()),
+        }
+    }
+
+    /// 算法下发指令前附上候选节点
+    pub fn note_candidates(
+        &self,
+        kind: DecisionKind,
+        reqid: Option<ReqId>,
+        fnid: FnId,
+        candidates: Vec<CandidateScore>,
+    ) {
+        self.inner.lock().notes.entry((kind, reqid, fnid)).or_default().candidates = candidates;
+    }
+
+    /// 覆盖指令的来源算法
+    pub fn note_algo(&self, kind: DecisionKind, reqid: Option<ReqId>, fnid: FnId, algo: &str) {
+        self.inner.lock().notes.entry((kind, reqid, fnid)).or_default().algo = Some(algo.to_owned());
+    }
+
+    /// 一轮算法结束，丢掉没有对应指令的 note
+    pub fn clear_notes(&self) {
+        self.inner.lock().notes.clear();
+    }
+
+    pub fn record(
+        &self,
+        frame: usize,
+        kind: DecisionKind,
+        reqid: Option<ReqId>,
+        fnid: FnId,
+        nid: NodeId,
+        outcome: DecisionOutcome,
+    ) {
+        let mut inner = self.inner.lock();
+        let note = inner.notes.remove(&(kind, reqid, fnid)).unwrap_or_default();
+        let algo = note.algo.unwrap_or_else(|| match kind {
+            DecisionKind::Sche | DecisionKind::Preempt => self.sche_algo.clone(),
+            DecisionKind::ScaleUp | DecisionKind::ScaleDown => self.scale_algo.clone(),
+        });
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        if inner.records.len() >= AUDIT_LOG_CAP {
+            inner.records.pop_front();
+        }
+        inner.records.push_back(DecisionRecord {
+            seq,
+            frame,
+            kind,
+            algo,
+            reqid,
+            fnid,
+            nid,
+            candidates: note.candidates,
+            outcome,
+        });
+    }
+
+    /// 返回符合条件的记录和下次查询的 from_seq
+    pub fn query(&self, q: &AuditQuery) -> (Vec<DecisionRecord>, u64) {
+        let inner = self.inner.lock();
+        let mut next_seq = q.from_seq.max(inner.records.front().map_or(inner.next_seq, |r| r.seq));
+        let mut res = vec![];
+        for r in inner.records.iter().filter(|r| r.seq >= q.from_seq) {
+            if res.len() >= q.limit {
+                break;
+            }
+            next_seq = r.seq + 1;
+            if q.kind.is_some_and( |k| k != r.kind)
+                || q.fnid.is_some_and( |f| f != r.fnid)
+                || q.reqid.is_some_and( |id| Some(id) != r.reqid)
+            {
+                continue;
+            }
+            res.push(r.clone());
+        }
+        if res.len() < q.limit {
+            next_seq = next_seq.max(inner.next_seq);
+        }
+        (res, next_seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditHub, AuditQuery, CandidateScore, DecisionKind, DecisionOutcome};
+    use crate::config::Config;
+
+    #[test]
+    fn test_audit_notes_and_query() {
+        let hub = AuditHub::new(&Config::new_test());
+        hub.note_candidates(
+            DecisionKind::Sche,
+            Some(1),
+            2,
+            vec![CandidateScore { nid: 0, score: Some(0.5) }, CandidateScore { nid: 3, score: None }],
+        );
+        hub.note_algo(DecisionKind::Sche, Some(1), 2, "rl");
+        hub.record(5, DecisionKind::Sche, Some(1), 2, 3, DecisionOutcome::Applied);
+        hub.record(6, DecisionKind::ScaleUp, None, 2, 0, DecisionOutcome::Dropped {
+            reason: "insufficient memory".to_owned(),
+        });
+        hub.record(6, DecisionKind::Sche, Some(4), 7, 1, DecisionOutcome::Applied);
+
+        let (all, next) = hub.query(&AuditQuery { limit: 10, ..Default::default() });
+        assert_eq!(all.len(), 3);
+        assert_eq!(next, 3);
+        assert_eq!(all[0].algo, "rl");
+        assert_eq!(all[0].candidates.len(), 2);
+        // note 用过后不再附到之后的指令上
+        assert!(all[2].candidates.is_empty());
+        assert_ne!(all[2].algo, "rl");
+
+        let (sche, _) = hub.query(&AuditQuery {
+            limit: 10,
+            kind: Some(DecisionKind::Sche),
+            fnid: Some(7),
+            ..Default::default()
+        });
+        assert_eq!(sche.len(), 1);
+        assert_eq!(sche[0].reqid, Some(4));
+
+        // limit 截断时 next_seq 指向下一条未返回的记录
+        let (first, next) = hub.query(&AuditQuery { limit: 1, ..Default::default() });
+        assert_eq!((first.len(), next), (1, 1));
+        let (rest, next) = hub.query(&AuditQuery { from_seq: next, limit: 10, ..Default::default() });
+        assert_eq!((rest.len(), next), (2, 3));
+    }
+}
@@ -0,0 +1,406 @@
+//! 不经过 http 的批量实验
+//!
+//! 从 yaml 读取实验矩阵 seeds × request_freq × dag_type × mech(scale_num, sche ...)，
+//! 在线程池中逐个组合运行仿真，records 照常写入 records/ 目录，
+//! 结束后输出一张汇总表 records/batch_summary.<time>.csv
+//!
+//! 配置格式沿用 scripts/batch_run.yml，额外增加 seeds、total_frame、threads
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{
+    admission::AdmissionConfig,
+    config::{config_errors_str, Config},
+    mechanism_conf::{MechConfig, ModuleMechConf},
+    reward::RewardConfig,
+    score::EnvMetricExt,
+    sim_env::SimEnv,
+    tenant::TenantConfig,
+    timeout::TimeoutConfig,
+};
+
+/// 单个模块的选择，形如 `- pos: greedy`，值为空表示无参数
+pub type NamedArg = BTreeMap<String, Option<Value>>;
+
+#[derive(Deserialize)]
+pub struct BatchConf {
+    /// 线程数，默认为 cpu 核数
+    #[serde(default)]
+    pub threads: Option<usize>,
+    #[serde(default = "default_total_frame")]
+    pub total_frame: usize,
+    /// 按真实时间给出总时长，覆盖 total_frame
+    #[serde(default)]
+    pub total_ms: Option<f32>,
+    pub seeds: Vec<String>,
+    pub params: BatchParams,
+    /// mech_type -> 各模块的候选
+    pub mech_scale_sche: BTreeMap<String, BatchMechConf>,
+    #[serde(default)]
+    pub mech_other: BatchMechOther,
+}
+
+#[derive(Deserialize)]
+pub struct BatchParams {
+    pub request_freq: Vec<NamedArg>,
+    pub dag_type: Vec<NamedArg>,
+    #[serde(default = "default_cold_start")]
+    pub cold_start: Vec<NamedArg>,
+    #[serde(default = "default_fn_type")]
+    pub fn_type: Vec<NamedArg>,
+    #[serde(default = "default_no_mech_latency")]
+    pub no_mech_latency: Vec<BTreeMap<bool, Option<Value>>>,
+    #[serde(default)]
+    pub event_driven: bool,
+    #[serde(default = "default_frame_duration_ms")]
+    pub frame_duration_ms: f32,
+    #[serde(default)]
+    pub export_columnar: bool,
+    #[serde(default)]
+    pub request_log: bool,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    #[serde(default)]
+    pub admission: AdmissionConfig,
+    #[serde(default)]
+    pub timeout: TimeoutConfig,
+}
+
+#[derive(Deserialize)]
+pub struct BatchMechConf {
+    pub scale_num: Vec<NamedArg>,
+    pub scale_down_exec: Vec<NamedArg>,
+    pub scale_up_exec: Vec<NamedArg>,
+    pub sche: Vec<NamedArg>,
+    /// 每一项是一组同时生效的 filter
+    #[serde(default = "default_filter")]
+    pub filter: Vec<Vec<NamedArg>>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchMechOther {
+    pub instance_cache_policy: Vec<NamedArg>,
+}
+
+impl Default for BatchMechOther {
+    fn default() -> Self {
+        Self {
+            instance_cache_policy: vec![named_arg("no_evict", None)],
+        }
+    }
+}
+
+fn default_total_frame() -> usize {
+    1000
+}
+fn default_cold_start() -> Vec<NamedArg> {
+    vec![named_arg("high", None)]
+}
+fn default_fn_type() -> Vec<NamedArg> {
+    vec![named_arg("cpu", None)]
+}
+fn default_no_mech_latency() -> Vec<BTreeMap<bool, Option<Value>>> {
+    vec![[(true, None)].into_iter().collect()]
+}
+fn default_frame_duration_ms() -> f32 {
+    1.0
+}
+fn default_filter() -> Vec<Vec<NamedArg>> {
+    vec![vec![]]
+}
+
+fn named_arg(name: &str, arg: Option<Value>) -> NamedArg {
+    [(name.to_owned(), arg)].into_iter().collect()
+}
+
+// 和 batch_run.py 的 apply_mech 一致，空值当作 ""
+fn arg_str(arg: &Option<Value>) -> String {
+    match arg {
+        None | Some(Value::Null) => "".to_owned(),
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => serde_yaml::to_string(v).unwrap().trim().to_owned(),
+    }
+}
+
+fn apply_named(sub: &mut std::collections::HashMap<String, Option<String>>, arg: &NamedArg) {
+    for (name, v) in arg {
+        sub.insert(name.clone(), Some(arg_str(v)));
+    }
+}
+
+fn names(args: &[NamedArg]) -> Vec<String> {
+    args.iter().flat_map(|a| a.keys().cloned()).collect()
+}
+
+/// 汇总表中的一行
+#[derive(Serialize)]
+pub struct BatchSummaryRow {
+    pub config: String,
+    pub seed: String,
+    pub request_freq: String,
+    pub dag_type: String,
+    pub mech_type: String,
+    pub scale_num: String,
+    pub sche: String,
+    pub done_req_cnt: usize,
+    pub req_done_time_avg_ms: f32,
+    pub req_done_time_std_ms: f32,
+    pub cost_per_req: f32,
+    pub quality_price_ratio: f32,
+    pub run_ms: u128,
+    /// 仿真 panic 时记录 panic 信息，此时各指标为 NaN
+    pub error: String,
+}
+
+impl BatchConf {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("read {} failed: {}", path, e))?;
+        serde_yaml::from_str(&content).map_err(|e| format!("parse {} failed: {}", path, e))
+    }
+
+    /// 展开所有参数组合
+    pub fn compositions(&self) -> Vec<Config> {
+        let mut confs = vec![];
+        let p = &self.params;
+        for seed in &self.seeds {
+            for request_freq in names(&p.request_freq) {
+                for dag_type in names(&p.dag_type) {
+                    for cold_start in names(&p.cold_start) {
+                        for fn_type in names(&p.fn_type) {
+                            for no_mech_latency in p.no_mech_latency.iter().flat_map(|v| v.keys()) {
+                                let base = Config {
+                                    rand_seed: seed.clone(),
+                                    total_frame: self.total_frame,
+                                    total_ms: self.total_ms,
+                                    request_freq: request_freq.clone(),
+                                    dag_type: dag_type.clone(),
+                                    cold_start: cold_start.clone(),
+                                    fn_type: fn_type.clone(),
+                                    no_mech_latency: *no_mech_latency,
+                                    mech: ModuleMechConf::new().0,
+                                    no_log: false,
+                                    event_driven: p.event_driven,
+                                    frame_duration_ms: p.frame_duration_ms,
+                                    reward: RewardConfig::default(),
+                                    export_columnar: p.export_columnar,
+                                    request_log: p.request_log,
+                                    tenants: p.tenants.clone(),
+                                    admission: p.admission.clone(),
+                                    timeout: p.timeout.clone(),
+                                };
+                                self.mech_compositions(&base, &mut confs);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        confs
+    }
+
+    fn mech_compositions(&self, base: &Config, confs: &mut Vec<Config>) {
+        for (mech_type, m) in &self.mech_scale_sche {
+            for scale_num in &m.scale_num {
+                for scale_down_exec in &m.scale_down_exec {
+                    for scale_up_exec in &m.scale_up_exec {
+                        for sche in &m.sche {
+                            for filters in &m.filter {
+                                for cache in &self.mech_other.instance_cache_policy {
+                                    let mut conf = base.clone();
+                                    let mech: &mut MechConfig = &mut conf.mech;
+                                    mech.mech_type.insert(mech_type.clone(), Some("".to_owned()));
+                                    apply_named(&mut mech.scale_num, scale_num);
+                                    apply_named(&mut mech.scale_down_exec, scale_down_exec);
+                                    apply_named(&mut mech.scale_up_exec, scale_up_exec);
+                                    apply_named(&mut mech.sche, sche);
+                                    for f in filters {
+                                        apply_named(&mut mech.filter, f);
+                                        mech.filter_order.extend(f.keys().cloned());
+                                    }
+                                    apply_named(&mut mech.instance_cache_policy, cache);
+                                    confs.push(conf);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 运行单个组合直到仿真结束，records 在仿真结束时写入
+pub fn run_one(config: Config) -> BatchSummaryRow {
+    let begin = std::time::Instant::now();
+    let mut env = SimEnv::new(config.clone());
+    env.step(1);
+    let done_req_cnt = env.core.done_requests().len();
+    BatchSummaryRow {
+        config: config.str(),
+        seed: config.rand_seed.clone(),
+        request_freq: config.request_freq.clone(),
+        dag_type: config.dag_type.clone(),
+        mech_type: config.mech.mech_type().0,
+        scale_num: config.mech.scale_num_conf().0,
+        sche: config.mech.sche_conf().0,
+        done_req_cnt,
+        req_done_time_avg_ms: env.req_done_time_avg(),
+        req_done_time_std_ms: env.req_done_time_std(),
+        cost_per_req: env.cost_each_req(),
+        quality_price_ratio: env.quality_price_ratio(),
+        run_ms: begin.elapsed().as_millis(),
+        error: String::new(),
+    }
+}
+
+/// 运行一个组合，panic 时返回一行失败记录，不影响其它组合
+fn run_catch(config: Config, run: impl FnOnce(Config) -> BatchSummaryRow) -> BatchSummaryRow {
+    let begin = std::time::Instant::now();
+    let res = panic::catch_unwind(AssertUnwindSafe(|| run(config.clone())));
+    res.unwrap_or_else(|payload| {
+        let error = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic".to_owned()
+        };
+        log::warn!("batch run {} panicked: {}", config.str(), error);
+        BatchSummaryRow {
+            config: config.str(),
+            seed: config.rand_seed.clone(),
+            request_freq: config.request_freq.clone(),
+            dag_type: config.dag_type.clone(),
+            mech_type: config.mech.mech_type().0,
+            scale_num: config.mech.scale_num_conf().0,
+            sche: config.mech.sche_conf().0,
+            done_req_cnt: 0,
+            req_done_time_avg_ms: f32::NAN,
+            req_done_time_std_ms: f32::NAN,
+            cost_per_req: f32::NAN,
+            quality_price_ratio: f32::NAN,
+            run_ms: begin.elapsed().as_millis(),
+            error,
+        }
+    })
+}
+
+/// 在线程池中运行所有组合，返回按配置排序的汇总
+pub fn run_batch(conf: &BatchConf) -> Vec<BatchSummaryRow> {
+    let confs = conf.compositions();
+    let total = confs.len();
+    let threads = conf
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |v| v.get()))
+        .max(1)
+        .min(total.max(1));
+    log::warn!("batch run {} compositions with {} threads", total, threads);
+
+    let todo = Arc::new(Mutex::new(confs.into_iter().collect::<VecDeque<_>>()));
+    let rows = Arc::new(Mutex::new(vec![]));
+    let workers = (0..threads)
+        .map(|_| {
+            let todo = todo.clone();
+            let rows = rows.clone();
+            thread::spawn(move || loop {
+                let Some(config) = todo.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let key = config.str();
+                if let Err(errs) = config.check_valid() {
+                    log::warn!("skip invalid mech composition: {}, {}", key, config_errors_str(&errs));
+                    continue;
+                }
+                let row = run_catch(config, run_one);
+                let mut rows = rows.lock().unwrap();
+                rows.push(row);
+                log::warn!("batch done {}/{}: {}", rows.len(), total, key);
+            })
+        })
+        .collect::<Vec<_>>();
+    for w in workers {
+        w.join().unwrap();
+    }
+
+    let mut rows = Arc::try_unwrap(rows).ok().unwrap().into_inner().unwrap();
+    rows.sort_by(|a, b| a.config.cmp(&b.config));
+    rows
+}
+
+/// 汇总表写到 records/batch_summary.<time>.csv，返回文件路径
+pub fn write_summary(rows: &Vec<BatchSummaryRow>) -> String {
+    let _ = fs::create_dir("records");
+    let path = format!(
+        "records/batch_summary.{}.csv",
+        chrono::offset::Utc::now().format("UTC_%Y_%m_%d_%H_%M_%S")
+    );
+    let mut writer = csv::Writer::from_path(&path).unwrap();
+    for row in rows {
+        writer.serialize(row).unwrap();
+    }
+    writer.flush().unwrap();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_catch, run_one, BatchConf};
+    use crate::config::Config;
+
+    #[test]
+    fn test_batch_compositions() {
+        let conf: BatchConf = serde_yaml::from_str(
+            r#"
+seeds: [a, b]
+params:
+  request_freq:
+  - low:
+  - high:
+  dag_type:
+  - single:
+mech_scale_sche:
+  scale_sche_separated:
+    scale_num:
+    - hpa:
+    - lass:
+    scale_down_exec:
+    - default:
+    scale_up_exec:
+    - least_task:
+    sche:
+    - pos: greedy
+    filter:
+    - []
+    - [{'careful_down':''}]
+"#,
+        )
+        .unwrap();
+        let confs = conf.compositions();
+        assert_eq!(confs.len(), 2 * 2 * 2 * 2);
+        assert_eq!(confs[0].mech.sche_conf(), ("pos".to_owned(), "greedy".to_owned()));
+        assert_eq!(confs[0].mech.filter.values().filter(|v| v.is_some()).count(), 0);
+        assert_eq!(confs[1].mech.filter.values().filter(|v| v.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn test_run_catch_panic() {
+        let row = run_catch(Config::new_test(), |_| panic!("boom"));
+        assert_eq!(row.error, "boom");
+        assert_eq!(row.done_req_cnt, 0);
+        assert!(row.req_done_time_avg_ms.is_nan());
+
+        let mut config = Config::new_test();
+        config.total_frame = 10;
+        let row = run_catch(config, run_one);
+        assert!(row.error.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use clap::Parser;
+use env_logger::Builder;
+use log::LevelFilter;
+use serverless_sim::batch::{ run_batch, write_summary, BatchConf };
+
+/// 不经过 http 的批量实验
+#[derive(Parser)]
+struct Args {
+    /// 实验矩阵配置
+    #[arg(short, long, default_value = "batch_conf.yml")]
+    conf: String,
+    /// 覆盖配置中的线程数
+    #[arg(short, long)]
+    threads: Option<usize>,
+}
+
+fn main() {
+    // 每帧的 info 日志太多，批量运行时默认只输出 warn
+    Builder::new().filter(None, LevelFilter::Warn).parse_default_env().init();
+
+    let args = Args::parse();
+    let mut conf = BatchConf::from_file(&args.conf).unwrap_or_else(|e| panic!("{}", e));
+    if args.threads.is_some() {
+        conf.threads = args.threads;
+    }
+    let rows = run_batch(&conf);
+    let path = write_summary(&rows);
+    println!("{} compositions done, summary: {}", rows.len(), path);
+    for row in &rows {
+        println!(
+            "{}  done:{}  latency:{:.3}ms  cost:{:.5}",
+            row.config,
+            row.done_req_cnt,
+            row.req_done_time_avg_ms,
+            row.cost_per_req
+        );
+    }
+}
@@ -0,0 +1,125 @@
+pub mod fifo;
+pub mod lru;
+pub mod no_evict;
+
+use std::{cell::RefCell, cmp::Eq, fmt::Debug, hash::Hash, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    algo_registry::{parse_params, AlgoEntry, AlgoInfo, AlgoParams, CachePolicyEntry, ParamSpec},
+    fn_dag::FnId,
+};
+
+// 双向链表节点
+pub struct ListNode<Payload> {
+    key: Option<Payload>, // None when dummy
+    // value: Option<FnContainer>,
+    prev: Option<Rc<RefCell<ListNode<Payload>>>>,
+    next: Option<Rc<RefCell<ListNode<Payload>>>>,
+}
+
+unsafe impl<Payload> Send for ListNode<Payload> {}
+unsafe impl<Payload> Sync for ListNode<Payload> {}
+
+impl<Payload> ListNode<Payload> {
+    fn new(key: Option<Payload>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(ListNode {
+            key,
+            prev: None,
+            next: None,
+        }))
+    }
+}
+
+// 从尾部(最旧)向头部(最新)收集链表中的 key
+fn list_keys_oldest_first<Payload: Clone>(tail: &Rc<RefCell<ListNode<Payload>>>) -> Vec<Payload> {
+    let mut keys = vec![];
+    let mut cur = tail.borrow().prev.clone();
+    while let Some(n) = cur {
+        let Some(key) = n.borrow().key.clone() else {
+            break;
+        };
+        keys.push(key);
+        cur = n.borrow().prev.clone();
+    }
+    keys
+}
+pub trait InstanceCachePolicy<Payload: Eq + Hash + Clone + Debug>: Send {
+    fn get(&mut self, key: Payload) -> Option<Payload>;
+
+    /// can_be_evict: check if the payload is pinned
+    /// first return: return Some(payload) if one is evcited
+    /// second return: return true if put success
+    fn put(
+        &mut self,
+        key: Payload,
+        can_be_evict: Box<dyn FnMut(&Payload) -> bool>,
+    ) -> (Option<Payload>, bool);
+    fn remove_all(&mut self, key: &Payload) -> bool;
+    /// 当前缓存中的 key，从最早淘汰到最晚淘汰，按此顺序 put 可以恢复缓存状态
+    fn keys(&self) -> Vec<Payload>;
+}
+
+fn default_cache_limit() -> usize {
+    10
+}
+
+/// {"type": "lru", "limit": 10}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheLimitParams {
+    #[serde(default = "default_cache_limit")]
+    pub limit: usize,
+}
+
+impl AlgoParams for CacheLimitParams {
+    fn to_attr(&self) -> String {
+        self.limit.to_string()
+    }
+}
+
+/// lru、fifo 的参数为缓存的容器数上限
+pub fn parse_cache_limit(attr: &str) -> Result<usize, String> {
+    match attr.parse::<usize>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => Err(format!("cache limit should be a positive integer, got {:?}", attr)),
+    }
+}
+
+const LIMIT_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "limit",
+        ty: "usize",
+        default: "10",
+        desc: "每个节点缓存的容器数上限",
+    },
+];
+
+pub const ALGOS: &[CachePolicyEntry] = &[
+    AlgoEntry {
+        info: AlgoInfo::simple("no_evict", &[]),
+        new: |_| Box::new(no_evict::NoEvict::new()),
+    },
+    AlgoEntry {
+        info: AlgoInfo {
+            name: "lru",
+            params: LIMIT_PARAMS,
+            mech_types: &[],
+            parse_params: parse_params::<CacheLimitParams>,
+            check_attr: |attr| parse_cache_limit(attr).map(|_| ()),
+        },
+        new: |attr| Box::new(lru::LRUCache::<FnId>::new(parse_cache_limit(attr).unwrap())),
+    },
+    // 沿用之前的实现，fifo 也按 lru 淘汰
+    AlgoEntry {
+        info: AlgoInfo {
+            name: "fifo",
+            params: LIMIT_PARAMS,
+            mech_types: &[],
+            parse_params: parse_params::<CacheLimitParams>,
+            check_attr: |attr| parse_cache_limit(attr).map(|_| ()),
+        },
+        new: |attr| Box::new(lru::LRUCache::<FnId>::new(parse_cache_limit(attr).unwrap())),
+    },
+];
@@ -0,0 +1,522 @@
+//! 按列导出的仿真记录
+//!
+//! `records/<name>.json` 的每帧是按 `FRAME_IDX_*` 下标排列的数组，下标变化时分析脚本就会出错。
+//! 这里按有名字和类型的列导出几张表，每张表同时写成 CSV 和 Arrow IPC 文件，
+//! 表和列的描述写在 schema.json 中:
+//!
+//! ```text
+//! records/columnar/<record_name>/
+//!     schema.json
+//!     frames.csv        frames.arrow        每帧的汇总指标
+//!     frame_nodes.csv   frame_nodes.arrow   每帧每个节点的 cpu、mem
+//!     containers.csv    containers.arrow    每帧每个容器的状态
+//!     requests.csv      requests.arrow      每个请求，未完成的请求时间列为空
+//! ```
+//!
+//! 通过 `Config::export_columnar` 打开。列的组成或含义改变时增加 `COLUMNAR_SCHEMA_VERSION`
+
+use std::{fs, sync::Arc};
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use serde::Serialize;
+
+use crate::{fn_dag::FnContainerState, sim_env::SimEnv};
+
+pub const COLUMNAR_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    UInt,
+    Float,
+    Str,
+    Bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Column {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub ty: ColumnType,
+    pub doc: &'static str,
+}
+
+impl ColumnType {
+    /// Null 可以出现在任何列
+    fn accepts(self, cell: &Cell) -> bool {
+        matches!(
+            (self, cell),
+            (_, Cell::Null) |
+                (ColumnType::UInt, Cell::UInt(_)) |
+                (ColumnType::Float, Cell::Float(_)) |
+                (ColumnType::Str, Cell::Str(_)) |
+                (ColumnType::Bool, Cell::Bool(_))
+        )
+    }
+}
+
+const fn col(name: &'static str, ty: ColumnType, doc: &'static str) -> Column {
+    Column { name, ty, doc }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Null,
+}
+
+impl From<usize> for Cell {
+    fn from(v: usize) -> Self {
+        Cell::UInt(v as u64)
+    }
+}
+impl From<f32> for Cell {
+    fn from(v: f32) -> Self {
+        Cell::Float(v as f64)
+    }
+}
+impl From<f64> for Cell {
+    fn from(v: f64) -> Self {
+        Cell::Float(v)
+    }
+}
+impl From<bool> for Cell {
+    fn from(v: bool) -> Self {
+        Cell::Bool(v)
+    }
+}
+impl From<&str> for Cell {
+    fn from(v: &str) -> Self {
+        Cell::Str(v.to_owned())
+    }
+}
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Cell::Null, |v| v.into())
+    }
+}
+
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [Column],
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(name: &'static str, columns: &'static [Column]) -> Self {
+        Self { name, columns, rows: vec![] }
+    }
+
+    /// 行的长度和每个单元格的类型都要和列一致
+    pub fn push(&mut self, row: Vec<Cell>) -> Result<(), String> {
+        if row.len() != self.columns.len() {
+            return Err(format!(
+                "row len {} of table {} mismatch, expect {}",
+                row.len(),
+                self.name,
+                self.columns.len()
+            ));
+        }
+        for (cell, column) in row.iter().zip(self.columns) {
+            self.check_cell(cell, column)?;
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    fn check_cell(&self, cell: &Cell, column: &Column) -> Result<(), String> {
+        if !column.ty.accepts(cell) {
+            return Err(format!("cell {:?} doesn't match column {}.{}", cell, self.name, column.name));
+        }
+        Ok(())
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<(), String> {
+        let mut writer = csv::Writer::from_path(path).map_err(|e| format!("create {} failed: {}", path, e))?;
+        writer
+            .write_record(self.columns.iter().map(|c| c.name))
+            .map_err(|e| format!("write {} failed: {}", path, e))?;
+        for row in &self.rows {
+            for (cell, column) in row.iter().zip(self.columns) {
+                self.check_cell(cell, column)?;
+            }
+            writer
+                .write_record(row.iter().map(|c| match c {
+                    Cell::UInt(v) => v.to_string(),
+                    Cell::Float(v) => v.to_string(),
+                    Cell::Str(v) => v.clone(),
+                    Cell::Bool(v) => v.to_string(),
+                    Cell::Null => String::new(),
+                }))
+                .map_err(|e| format!("write {} failed: {}", path, e))?;
+        }
+        writer.flush().map_err(|e| format!("write {} failed: {}", path, e))
+    }
+
+    fn arrow_schema(&self) -> Schema {
+        Schema::new(
+            self.columns
+                .iter()
+                .map(|c| {
+                    let ty = match c.ty {
+                        ColumnType::UInt => DataType::UInt64,
+                        ColumnType::Float => DataType::Float64,
+                        ColumnType::Str => DataType::Utf8,
+                        ColumnType::Bool => DataType::Boolean,
+                    };
+                    Field::new(c.name, ty, true)
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// rows 可以被直接修改，这里再检查一次类型
+    fn arrow_column(&self, i: usize) -> Result<ArrayRef, String> {
+        let column = &self.columns[i];
+        for row in &self.rows {
+            self.check_cell(&row[i], column)?;
+        }
+        let cells = self.rows.iter().map(|r| &r[i]);
+        Ok(match column.ty {
+            ColumnType::UInt => Arc::new(UInt64Array::from(
+                cells
+                    .map(|c| match c {
+                        Cell::UInt(v) => Some(*v),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            ColumnType::Float => Arc::new(Float64Array::from(
+                cells
+                    .map(|c| match c {
+                        Cell::Float(v) => Some(*v),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            ColumnType::Str => Arc::new(StringArray::from(
+                cells
+                    .map(|c| match c {
+                        Cell::Str(v) => Some(v.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            ColumnType::Bool => Arc::new(BooleanArray::from(
+                cells
+                    .map(|c| match c {
+                        Cell::Bool(v) => Some(*v),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+        })
+    }
+
+    pub fn write_arrow(&self, path: &str) -> Result<(), String> {
+        let schema = Arc::new(self.arrow_schema());
+        let columns = (0..self.columns.len())
+            .map(|i| self.arrow_column(i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = RecordBatch::try_new(schema.clone(), columns)
+            .map_err(|e| format!("build arrow batch {} failed: {}", self.name, e))?;
+        let file = fs::File::create(path).map_err(|e| format!("create {} failed: {}", path, e))?;
+        let mut writer = FileWriter::try_new(file, &schema)
+            .map_err(|e| format!("write {} failed: {}", path, e))?;
+        writer.write(&batch).map_err(|e| format!("write {} failed: {}", path, e))?;
+        writer.finish().map_err(|e| format!("write {} failed: {}", path, e))
+    }
+}
+
+use ColumnType::{Bool, Float, Str, UInt};
+
+const FRAME_COLUMNS: &[Column] = &[
+    col("frame", UInt, "帧数"),
+    col("running_req_cnt", UInt, "未完成的请求数"),
+    col("new_req_cnt", UInt, "这一帧到达的请求数"),
+    col("done_req_cnt", UInt, "这一帧完成的请求数"),
+    col("req_done_time_avg_ms", Float, "已完成请求的平均延迟"),
+    col("req_done_time_std_ms", Float, "已完成请求延迟的标准差"),
+    col("req_done_time_avg_90p_ms", Float, "已完成请求中较快的 90% 的平均延迟"),
+    col("cost_per_req", Float, "已完成请求的平均成本"),
+    col("score", Float, "得分"),
+    col("req_wait_sche_time_ms", Float, "平均等待调度时间"),
+    col("req_wait_coldstart_time_ms", Float, "平均等待冷启动时间"),
+    col("req_data_recv_time_ms", Float, "平均数据接收时间"),
+    col("req_exe_time_ms", Float, "平均执行时间"),
+    col("algo_exe_time_ms", Float, "平均算法执行时间"),
+    col("fn_container_cnt", UInt, "容器总数"),
+];
+
+const FRAME_NODE_COLUMNS: &[Column] = &[
+    col("frame", UInt, "帧数"),
+    col("node_id", UInt, "节点 id"),
+    col("cpu", Float, "这一帧使用的计算量"),
+    col("mem", Float, "这一帧使用的内存"),
+];
+
+const CONTAINER_COLUMNS: &[Column] = &[
+    col("frame", UInt, "帧数"),
+    col("node_id", UInt, "节点 id"),
+    col("fn_id", UInt, "函数 id"),
+    col("state", Str, "starting 或 running"),
+    col("born_frame", UInt, "容器创建的帧"),
+    col("task_cnt", UInt, "容器上的任务数"),
+    col("used_times", UInt, "被使用过的帧数"),
+    col("cpu_used", Float, "这一帧使用的计算量"),
+    col("mem", Float, "这一帧使用的内存"),
+];
+
+const REQUEST_COLUMNS: &[Column] = &[
+    col("req_id", UInt, "请求 id"),
+    col("dag_id", UInt, "dag id"),
+    col("begin_frame", UInt, "到达的帧"),
+    col("end_frame", UInt, "完成的帧，未完成为空"),
+    col("done", Bool, "是否完成"),
+    col("timed_out", Bool, "是否超时取消"),
+    col("done_time_ms", Float, "延迟，未完成为空"),
+    col("wait_sche_time_ms", Float, "关键路径上的等待调度时间，未完成为空"),
+    col("wait_coldstart_time_ms", Float, "关键路径上的等待冷启动时间，未完成为空"),
+    col("data_recv_time_ms", Float, "关键路径上的数据接收时间，未完成为空"),
+    col("exe_time_ms", Float, "关键路径上的执行时间，未完成为空"),
+];
+
+/// 一帧的汇总指标，json 记录和按列导出共用
+#[derive(Serialize, Clone, Debug)]
+pub struct FrameStats {
+    pub frame: usize,
+    pub running_req_cnt: usize,
+    pub new_req_cnt: usize,
+    pub done_req_cnt: usize,
+    pub req_done_time_avg_ms: f32,
+    pub req_done_time_std_ms: f32,
+    pub req_done_time_avg_90p_ms: f32,
+    pub cost_per_req: f32,
+    pub score: f32,
+    pub req_wait_sche_time_ms: f32,
+    pub req_wait_coldstart_time_ms: f32,
+    pub req_data_recv_time_ms: f32,
+    pub req_exe_time_ms: f32,
+    pub algo_exe_time_ms: f64,
+    pub fn_container_cnt: usize,
+}
+
+impl FrameStats {
+    fn row(&self) -> Vec<Cell> {
+        vec![
+            self.frame.into(),
+            self.running_req_cnt.into(),
+            self.new_req_cnt.into(),
+            self.done_req_cnt.into(),
+            self.req_done_time_avg_ms.into(),
+            self.req_done_time_std_ms.into(),
+            self.req_done_time_avg_90p_ms.into(),
+            self.cost_per_req.into(),
+            self.score.into(),
+            self.req_wait_sche_time_ms.into(),
+            self.req_wait_coldstart_time_ms.into(),
+            self.req_data_recv_time_ms.into(),
+            self.req_exe_time_ms.into(),
+            self.algo_exe_time_ms.into(),
+            self.fn_container_cnt.into(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct TableSchema {
+    name: &'static str,
+    files: Vec<String>,
+    columns: &'static [Column],
+}
+
+#[derive(Serialize)]
+struct ColumnarSchema<'a> {
+    version: u32,
+    record_name: &'a str,
+    config: String,
+    tables: Vec<TableSchema>,
+}
+
+pub struct ColumnarRecorder {
+    record_name: String,
+    frames: Table,
+    frame_nodes: Table,
+    containers: Table,
+}
+
+impl ColumnarRecorder {
+    pub fn new(record_name: String) -> Self {
+        Self {
+            record_name,
+            frames: Table::new("frames", FRAME_COLUMNS),
+            frame_nodes: Table::new("frame_nodes", FRAME_NODE_COLUMNS),
+            containers: Table::new("containers", CONTAINER_COLUMNS),
+        }
+    }
+
+    pub fn dir(&self) -> String {
+        format!("records/columnar/{}", self.record_name)
+    }
+
+    pub fn add_frame(&mut self, env: &SimEnv, stats: &FrameStats) -> Result<(), String> {
+        self.frames.push(stats.row())?;
+        for node in env.core.nodes().iter() {
+            self.frame_nodes.push(vec![
+                stats.frame.into(),
+                node.node_id().into(),
+                node.cpu.into(),
+                node.unready_mem().into(),
+            ])?;
+            let mut containers = node.fn_containers.borrow().values().map(|c| {
+                vec![
+                    stats.frame.into(),
+                    c.node_id.into(),
+                    c.fn_id.into(),
+                    (match c.state() {
+                        FnContainerState::Starting { .. } => "starting",
+                        FnContainerState::Running => "running",
+                    }).into(),
+                    c.born_frame.into(),
+                    c.req_fn_state.len().into(),
+                    c.used_times.into(),
+                    c.last_frame_cpu_used.into(),
+                    c.mem_use.into(),
+                ]
+            }).collect::<Vec<_>>();
+            // HashMap 的顺序不固定，按函数 id 排序
+            containers.sort_by_key(|r| match r[2] {
+                Cell::UInt(v) => v,
+                _ => unreachable!(),
+            });
+            for row in containers {
+                self.containers.push(row)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn requests(env: &SimEnv) -> Result<Table, String> {
+        let mut table = Table::new("requests", REQUEST_COLUMNS);
+        let config = env.help.config();
+        for req in env.core.done_requests_mut().iter_mut() {
+            if req.timed_out() {
+                table.push(vec![
+                    req.req_id.into(),
+                    req.dag_i.into(),
+                    req.begin_frame.into(),
+                    req.end_frame.into(),
+                    false.into(),
+                    true.into(),
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Null,
+                ])?;
+                continue;
+            }
+            table.push(vec![
+                req.req_id.into(),
+                req.dag_i.into(),
+                req.begin_frame.into(),
+                req.end_frame.into(),
+                true.into(),
+                false.into(),
+                config.frames_to_ms((req.end_frame - req.begin_frame) as f32).into(),
+                config.frames_to_ms(req.wait_sche_time(env) as f32).into(),
+                config.frames_to_ms(req.wait_cold_start_time(env) as f32).into(),
+                config.frames_to_ms(req.data_recv_time(env) as f32).into(),
+                config.frames_to_ms(req.exe_time(env) as f32).into(),
+            ])?;
+        }
+        for (_, req) in env.core.requests().iter() {
+            table.push(vec![
+                req.req_id.into(),
+                req.dag_i.into(),
+                req.begin_frame.into(),
+                Cell::Null,
+                false.into(),
+                false.into(),
+                Cell::Null,
+                Cell::Null,
+                Cell::Null,
+                Cell::Null,
+                Cell::Null,
+            ])?;
+        }
+        Ok(table)
+    }
+
+    /// 重写所有表，返回输出目录
+    pub fn write(&self, env: &SimEnv) -> Result<String, String> {
+        let dir = self.dir();
+        fs::create_dir_all(&dir).map_err(|e| format!("create dir {} failed: {}", dir, e))?;
+        let requests = Self::requests(env)?;
+        let tables = [&self.frames, &self.frame_nodes, &self.containers, &requests];
+
+        let mut schema = ColumnarSchema {
+            version: COLUMNAR_SCHEMA_VERSION,
+            record_name: &self.record_name,
+            config: env.help.config().str(),
+            tables: vec![],
+        };
+        for table in tables {
+            let csv = format!("{}.csv", table.name);
+            let arrow = format!("{}.arrow", table.name);
+            table.write_csv(&format!("{}/{}", dir, csv))?;
+            table.write_arrow(&format!("{}/{}", dir, arrow))?;
+            schema.tables.push(TableSchema {
+                name: table.name,
+                files: vec![csv, arrow],
+                columns: table.columns,
+            });
+        }
+        let schema_path = format!("{}/schema.json", dir);
+        let file = fs::File::create(&schema_path).map_err(|e| format!("create {} failed: {}", schema_path, e))?;
+        serde_json::to_writer_pretty(file, &schema).map_err(|e| format!("write {} failed: {}", schema_path, e))?;
+        Ok(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{col, Cell, ColumnType, Column, Table};
+
+    const COLUMNS: &[Column] = &[
+        col("id", ColumnType::UInt, ""),
+        col("v", ColumnType::Float, ""),
+        col("name", ColumnType::Str, ""),
+    ];
+
+    #[test]
+    fn test_table_write() {
+        let mut table = Table::new("test", COLUMNS);
+        table.push(vec![1usize.into(), 0.5f32.into(), "a".into()]).unwrap();
+        table.push(vec![2usize.into(), Cell::Null, Option::<&str>::None.into()]).unwrap();
+        // 类型或长度不对的行被拒绝，不会写入
+        assert!(table.push(vec![3usize.into(), "x".into(), "b".into()]).is_err());
+        assert!(table.push(vec![3usize.into()]).is_err());
+
+        let dir = std::env::temp_dir().join("serverless_sim_columnar_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv_path = dir.join("test.csv").to_str().unwrap().to_owned();
+        table.write_csv(&csv_path).unwrap();
+        assert_eq!(std::fs::read_to_string(&csv_path).unwrap(), "id,v,name\n1,0.5,a\n2,,\n");
+
+        let arrow_path = dir.join("test.arrow").to_str().unwrap().to_owned();
+        table.write_arrow(&arrow_path).unwrap();
+        let reader = arrow_ipc::reader::FileReader::try_new(std::fs::File::open(&arrow_path).unwrap(), None).unwrap();
+        let rows: usize = reader.map(|b| b.unwrap().num_rows()).sum();
+        assert_eq!(rows, 2);
+
+        table.rows.push(vec![Cell::Bool(true), Cell::Null, Cell::Null]);
+        assert!(table.write_csv(&csv_path).is_err());
+        assert!(table.write_arrow(&arrow_path).is_err());
+    }
+}
@@ -1,6 +1,12 @@
+use std::fmt;
+
 use serde::{ Deserialize, Serialize };
 
+use crate::admission::AdmissionConfig;
 use crate::mechanism_conf::MechConfig;
+use crate::reward::RewardConfig;
+use crate::tenant::{ check_tenants, tenants_digest, TenantConfig };
+use crate::timeout::TimeoutConfig;
 
 // 存储应用配置信息
 #[derive(Serialize, Deserialize, Clone)]
@@ -30,7 +36,11 @@ pub struct APPConfig {
 pub struct Config {
     /// for the different algos, should use the same seed
     pub rand_seed: String,
+    /// 仿真的总帧数，单位是帧而不是 ms，对应的真实时间为 total_frame * frame_duration_ms
     pub total_frame: usize,
+    /// 仿真的总时长(ms)，设置后按 frame_duration_ms 换算成帧并覆盖 total_frame，见 total_frames
+    #[serde(default)]
+    pub total_ms: Option<f32>,
     /// low middle high
     pub request_freq: String,
     /// dag type: single, chain, dag, mix
@@ -45,12 +55,85 @@ pub struct Config {
     pub mech: MechConfig,
     /// whether to log the resultz
     pub no_log: bool,
+    /// 事件驱动推进，跳过没有状态变化的帧
+    #[serde(default)]
+    pub event_driven: bool,
+    /// 一帧对应的真实时间(ms)
+    /// - 冷启动时间、带宽、请求频率等输入按真实时间给出，再换算到每帧
+    /// - 请求延迟等输出换算回 ms
+    /// - total_frame 和 start_timer 按帧计，要按真实时间给出用 total_ms 和 start_timer_ms
+    #[serde(default = "default_frame_duration_ms")]
+    pub frame_duration_ms: f32,
+    /// RL 奖励各项的权重，不影响仿真过程
+    #[serde(default)]
+    pub reward: RewardConfig,
+    /// 额外把记录按列导出为 CSV 和 Arrow，见 columnar
+    #[serde(default)]
+    pub export_columnar: bool,
+    /// 每个完成的请求写一行到 records/request_log，见 request_log
+    #[serde(default)]
+    pub request_log: bool,
+    /// 租户及其应用、权重和优先级，为空时所有应用属于同一个租户，见 tenant
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    /// 新请求的准入控制，默认全部接纳，见 admission
+    #[serde(default)]
+    pub admission: AdmissionConfig,
+    /// 请求和函数的超时时间，默认不限制，见 timeout
+    #[serde(default)]
+    pub timeout: TimeoutConfig,
+}
+
+fn default_frame_duration_ms() -> f32 {
+    1.0
+}
+
+/// 配置中的一处错误，field 为出错字段的路径，如 mech.sche.pos
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    pub field: String,
+    pub msg: String,
+}
+
+impl ConfigError {
+    pub fn new(field: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.msg)
+    }
+}
+
+/// 多处错误拼成一行，用于日志和只能返回字符串的地方
+pub fn config_errors_str(errs: &[ConfigError]) -> String {
+    errs.iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn check_one_of(errs: &mut Vec<ConfigError>, field: &str, value: &str, allow: &[&str]) {
+    if !allow.contains(&value) {
+        errs.push(
+            ConfigError::new(
+                field,
+                format!("unknown value {:?}, should be one of: {}", value, allow.join(", "))
+            )
+        );
+    }
 }
 
 impl Config {
     pub fn new_test() -> Config {
         Config {
             total_frame: 100,
+            total_ms: None,
             rand_seed: "test".to_string(),
             request_freq: "low".to_string(),
             dag_type: "single".to_string(),
@@ -59,9 +142,35 @@ impl Config {
             mech: MechConfig::new_test(),
             no_mech_latency: true,
             no_log: true,
+            event_driven: false,
+            frame_duration_ms: default_frame_duration_ms(),
+            reward: RewardConfig::default(),
+            export_columnar: false,
+            request_log: false,
+            tenants: vec![],
+            admission: AdmissionConfig::default(),
+            timeout: TimeoutConfig::default(),
+        }
+    }
+
+    /// 真实时间(ms)换算成帧数，不足一帧的部分按一帧算
+    pub fn ms_to_frames(&self, ms: f32) -> usize {
+        (ms / self.frame_duration_ms).ceil() as usize
+    }
+
+    /// 仿真的总帧数，设置了 total_ms 时由它换算
+    pub fn total_frames(&self) -> usize {
+        match self.total_ms {
+            Some(ms) => self.ms_to_frames(ms),
+            None => self.total_frame,
         }
     }
 
+    /// 帧数换算成真实时间(ms)
+    pub fn frames_to_ms(&self, frames: f32) -> f32 {
+        frames * self.frame_duration_ms
+    }
+
     pub fn request_freq_low(&self) -> bool {
         if &*self.request_freq == "low" {
             return true;
@@ -116,49 +225,40 @@ impl Config {
         false
     }
 
-    // pub fn check_valid(&self) {
-    //     match &*self.request_freq {
-    //         "low" | "middle" | "high" => {}
-    //         _ => panic!("request_freq should be low, middle or high"),
-    //     }
-    //     match &*self.dag_type {
-    //         "single" | "chain" | "dag" | "mix" => {}
-    //         _ => panic!("dag_type should be single, chain, dag or mix"),
-    //     }
-    //     match &*self.cold_start {
-    //         "high" | "low" | "mix" => {}
-    //         _ => panic!("cold_start should be high, low or mix"),
-    //     }
-    //     match &*self.fn_type {
-    //         "cpu" | "data" | "mix" => {}
-    //         _ => panic!("fn_type should be cpu, data or mix"),
-    //     }
-    //     match &*self.es.up {
-    //         // "ai","lass","fnsche","hpa","faasflow"
-    //         "lass" | "ai" | "fnsche" | "hpa" | "faasflow" => {}
-    //         _ => panic!("ef.up should be lass, ai, fnsche, hpa or faasflow"),
-    //     }
-    //     match &*self.es.down {
-    //         // "ai","lass","fnsche","hpa","faasflow"
-    //         "lass" | "ai" | "fnsche" | "hpa" | "faasflow" => {}
-    //         _ => panic!("ef.down should be lass, ai, fnsche, hpa or faasflow"),
-    //     }
-    //     match &*self.es.sche {
-    //         "rule" | "ai" | "faasflow" | "fnsche" | "rule_prewarm_succ" | "random"
-    //         | "round_robin" | "load_least" | "gofs" | "pass" => {}
-    //         _ => panic!("ef.sche should be rule, ai, faasflow or fnsche"),
-    //     }
-    //     match &*self.es.down_smooth {
-    //         "direct" | "smooth_30" | "smooth_100" => {}
-    //         _ => panic!("ef.down_smooth should be direct, smooth_30 or smooth_100"),
-    //     }
-    //     if self.es.sche_ai() {
-    //         match &**self.es.ai_type.as_ref().unwrap() {
-    //             "sac" | "ppo" | "mat" => {}
-    //             _ => panic!("ef.ai_type should be sac, ppo or mat"),
-    //         }
-    //     }
-    // }
+    /// 检查所有字段，返回全部出错的字段而不是遇到第一个就停
+    pub fn check_valid(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errs = vec![];
+        check_one_of(&mut errs, "request_freq", &self.request_freq, &["low", "middle", "high"]);
+        check_one_of(&mut errs, "dag_type", &self.dag_type, &["single", "dag", "mix"]);
+        check_one_of(&mut errs, "cold_start", &self.cold_start, &["high", "low", "mix"]);
+        check_one_of(&mut errs, "fn_type", &self.fn_type, &["cpu", "data"]);
+        if self.total_frame == 0 {
+            errs.push(ConfigError::new("total_frame", "should be greater than 0"));
+        }
+        if let Some(ms) = self.total_ms {
+            if !(ms.is_finite() && ms > 0.0) {
+                errs.push(ConfigError::new("total_ms", format!("should be a positive number, got {}", ms)));
+            }
+        }
+        if !(self.frame_duration_ms.is_finite() && self.frame_duration_ms > 0.0) {
+            errs.push(
+                ConfigError::new(
+                    "frame_duration_ms",
+                    format!("should be a positive number, got {}", self.frame_duration_ms)
+                )
+            );
+        }
+        check_tenants(&self.tenants, &mut errs);
+        self.admission.check_valid(&mut errs);
+        self.timeout.check_valid(&mut errs);
+        self.reward.check_valid(&mut errs);
+        self.mech.check_valid(&mut errs);
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
     pub fn no_mech_str(&self) -> String {
         format!(
             "sd{}.rf{}.dt{}.cs{}.ft{}",
@@ -175,18 +275,14 @@ impl Config {
         let scup = self.mech.scale_up_exec_conf();
         let sche = self.mech.sche_conf();
         let ins_cache = self.mech.instance_cache_policy_conf();
-        let mut some_filter = self.mech.filter
-            .iter()
-            .filter(|v| v.1.is_some())
-            .map(|v| (v.0, v.1.clone().unwrap()))
-            .collect::<Vec<_>>();
-        some_filter.sort();
-        let some_filter = some_filter
+        // filter 按作用顺序
+        let some_filter = self.mech
+            .filter_chain()
             .iter()
             .map(|v| format!("({}.{})", v.0, v.1))
             .collect::<String>();
         // .join(",");
-        format!(
+        let mut s = format!(
             "sd{}.rf{}.dt{}.cs{}.ft{}.nml{}.mt{}.scl({}.{})({}.{})({}.{})[{}].scd({}.{}).ic({}.{})",
             self.rand_seed,
             self.request_freq,
@@ -210,6 +306,103 @@ impl Config {
             sche.1,
             ins_cache.0,
             ins_cache.1
-        )
+        );
+        if self.event_driven {
+            s.push_str(".ed");
+        }
+        if self.frame_duration_ms != default_frame_duration_ms() {
+            s.push_str(&format!(".fd{}", self.frame_duration_ms));
+        }
+        if !self.tenants.is_empty() {
+            s.push_str(&format!(".tn{}", tenants_digest(&self.tenants)));
+        }
+        if self.admission.enabled() {
+            let ad = &self.admission;
+            s.push_str(&format!(
+                ".ad{}_{}_{}_{}_{}_{}",
+                ad.policy,
+                ad.max_inflight,
+                ad.rate_per_s,
+                ad.burst,
+                ad.max_defer_ms,
+                ad.penalty_ms
+            ));
+        }
+        if self.timeout.enabled() {
+            s.push_str(&format!(
+                ".to{}_{}_{}",
+                self.timeout.request_ms,
+                self.timeout.fn_ms,
+                self.timeout.penalty_ms
+            ));
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    fn select(conf: &mut std::collections::HashMap<String, Option<String>>, name: &str, attr: &str) {
+        for (k, v) in conf.iter_mut() {
+            *v = if k == name { Some(attr.to_owned()) } else { None };
+        }
+    }
+
+    #[test]
+    fn test_check_valid() {
+        assert!(Config::new_test().check_valid().is_ok());
+
+        let mut config = Config::new_test();
+        config.request_freq = "huge".to_owned();
+        config.frame_duration_ms = 0.0;
+        config.mech.sche.insert("unknown_sche".to_owned(), None);
+        let fields = config
+            .check_valid()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["request_freq", "frame_duration_ms", "mech.sche.unknown_sche"]);
+
+        // pos 只能搭配 scale_sche_joint，参数也要合法
+        let mut config = Config::new_test();
+        select(&mut config.mech.sche, "pos", "");
+        select(&mut config.mech.instance_cache_policy, "lru", "0");
+        let fields = config
+            .check_valid()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["mech.sche.pos", "mech.sche.pos", "mech.instance_cache_policy.lru"]);
+
+        let mut config = Config::new_test();
+        select(&mut config.mech.mech_type, "scale_sche_joint", "");
+        select(&mut config.mech.sche, "pos", "greedy");
+        assert!(config.check_valid().is_ok());
+
+        config.mech.scale_num.values_mut().for_each(|v| *v = None);
+        let errs = config.check_valid().unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].field, "mech.scale_num");
+    }
+
+    #[test]
+    fn test_total_ms() {
+        let mut config = Config::new_test();
+        assert_eq!(config.total_frames(), config.total_frame);
+
+        // 设置 total_ms 后仿真时长不随帧长变化
+        config.total_ms = Some(100.0);
+        for (fd, frames) in [(1.0, 100), (2.0, 50), (0.5, 200), (3.0, 34)] {
+            config.frame_duration_ms = fd;
+            assert_eq!(config.total_frames(), frames);
+        }
+
+        config.total_ms = Some(0.0);
+        let errs = config.check_valid().unwrap_err();
+        assert_eq!(errs[0].field, "total_ms");
     }
 }
@@ -4,9 +4,6 @@ use std::io;
 use std::fs::File;
 use csv::ReaderBuilder;
 
-
-use crate::sim_env::SimEnv;
-
 use crate::sim_env::{self, SimEnv};
 
 #[derive(Debug)]
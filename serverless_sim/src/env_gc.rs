@@ -1,6 +1,6 @@
 use std::{ thread, time::{ Duration, SystemTime, UNIX_EPOCH } };
 
-use crate::network::SIM_ENVS;
+use crate::network::{ remove_sim_envs, SIM_ENVS };
 
 /// 函数启动一个线程，该线程以大约每 30 毫秒的间隔检查 SIM_ENVS 中的模拟环境。
 /// 如果某个环境在最近 60 秒内未被使用，它将被移除，并清理相关资源。同时，会记录警告日志和度量数据。
@@ -15,23 +15,27 @@ pub fn start_gc() {
                 let start = SystemTime::now();
                 let now = start.duration_since(UNIX_EPOCH).unwrap();
 
-                // 获取 SIM_ENVS 中的所有模拟环境
-                let mut sim_envs = SIM_ENVS.write();
-                // 用于存储待移除的模拟环境的键
+                // 只在读锁下找出待移除的模拟环境，正在 step 的环境跳过
                 let mut to_remove = vec![];
-
-                for e in sim_envs.iter() {
-                    let env = e.1.lock();
+                for (key, env) in SIM_ENVS.read().iter() {
+                    let Some(env) = env.try_lock() else {
+                        continue;
+                    };
                     // 检查当前时间是否已经超过环境最近使用时间加上 60 秒
                     if now > Duration::from_secs(60) + env.recent_use_time {
-                        let key = env.help.config().str();
-                        log::warn!("gc env {}", key);
-                        env.help.metric_record().as_ref().unwrap().flush(&env);
-                        to_remove.push(key);
+                        to_remove.push(key.clone());
                     }
                 }
-                for key in to_remove {
-                    sim_envs.remove(&key);
+                if to_remove.is_empty() {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+
+                // 写锁只用于从表中摘除，flush 在锁外进行
+                for env in remove_sim_envs(&to_remove) {
+                    let env = env.lock();
+                    log::warn!("gc env {}", env.help.config().str());
+                    env.help.metric_record().as_ref().unwrap().flush(&env);
                 }
             }
             thread::sleep(Duration::from_millis(10));
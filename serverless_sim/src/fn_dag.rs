@@ -1,7 +1,8 @@
-use std::{ cell::{ Ref, RefMut }, collections::{ HashMap, HashSet, VecDeque }, io };
+use std::{ cell::{ Ref, RefMut }, collections::{ BTreeMap, HashMap, HashSet, VecDeque }, io };
 
 use daggy::{ petgraph::visit::{ Topo, Visitable }, Dag, NodeIndex, Walker };
 use enum_as_inner::EnumAsInner;
+use serde::{ Deserialize, Serialize };
 
 use crate::{
     config::APPConfig, dag_parsers::csv_parser::parse_dag_csv, mechanism::SimEnvObserve, node::{ EnvNodeExt, NodeId }, request::{ ReqId, Request }, sim_env::SimEnv, CONTAINER_BASIC_MEM
@@ -173,7 +174,7 @@ pub struct Func {
     // 当前函数有实例的节点
     pub nodes: HashSet<usize>,
 
-    // frame count of cold start
+    // frame count of cold start, converted from ms by frame_duration_ms
     pub cold_start_time: usize,
 
     pub cold_start_container_mem_use: f32,
@@ -210,7 +211,7 @@ impl Func {
     }
 }
 
-#[derive(EnumAsInner, Clone)]
+#[derive(EnumAsInner, Clone, Serialize, Deserialize)]
 pub enum FnContainerState {
     // 创建
     Starting {
@@ -220,11 +221,12 @@ pub enum FnContainerState {
     Running,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FnContainer {
     pub node_id: NodeId,
     pub fn_id: FnId,
-    pub req_fn_state: HashMap<ReqId, RunningTask>,
+    /// 按请求 id 排序，内存不够时按这个顺序决定哪些任务先运行，保证结果可复现
+    pub req_fn_state: BTreeMap<ReqId, RunningTask>,
     pub born_frame: usize,
     pub used_times: usize,
     pub this_frame_used: bool,
@@ -314,7 +316,7 @@ impl FnContainer {
         Self {
             node_id,
             fn_id,
-            req_fn_state: HashMap::default(),
+            req_fn_state: BTreeMap::new(),
             born_frame: sim_env.current_frame(),
             used_times: 0,
             this_frame_used: false,
@@ -409,7 +411,7 @@ impl FnContainer {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RunningTask {
     /// nodeid - (need,recv)
     pub data_recv: HashMap<NodeId, (f32, f32)>,
@@ -457,8 +459,14 @@ impl SimEnv {
             out_put_size,
             nodes: HashSet::new(),
             cold_start_container_mem_use: 100.0,
-            cold_start_container_cpu_use: self.env_rand_f(0.1, 1.0),
-            cold_start_time: self.env_rand_i(50, 100),
+            cold_start_container_cpu_use: self.env_rand_f(0.1, 1.0)
+                * self.help.config().frame_duration_ms,
+            // 冷启动 50~100 ms
+            cold_start_time: self
+                .help
+                .config()
+                .ms_to_frames(self.env_rand_i(50, 100) as f32)
+                .max(1),
             dag_id: 0,
             graph_i: (0).into(),
         });
@@ -0,0 +1,66 @@
+pub mod actions;
+pub mod admission;
+pub mod algo_registry;
+pub mod algos;
+pub mod apis;
+pub mod audit;
+pub mod batch;
+pub mod cache;
+pub mod columnar;
+pub mod config;
+pub mod env_gc;
+pub mod fn_dag;
+pub mod mechanism;
+pub mod mechanism_conf;
+pub mod mechanism_thread;
+pub mod metric;
+pub mod network;
+pub mod node;
+pub mod output;
+pub mod request;
+pub mod request_log;
+pub mod reward;
+pub mod scale;
+pub mod sche;
+pub mod score;
+pub mod sim_env;
+pub mod sim_event_loop;
+pub mod sim_events;
+pub mod sim_loop;
+pub mod sim_run;
+pub mod sim_timer;
+pub mod snapshot;
+pub mod spaces;
+pub mod state;
+pub mod telemetry;
+pub mod tenant;
+pub mod timeout;
+pub mod util;
+pub mod with_env_sub;
+pub mod rl_target;
+pub mod dag_parsers;
+
+#[macro_use]
+extern crate lazy_static;
+
+/* 
+每1帧生成
+节点数量修改为了30个
+mix模式生成的应用为单函数、dag各5个
+请求数量没变。
+node的cpu资源从1000到200
+*/
+
+const REQUEST_GEN_FRAME_INTERVAL: usize = 1;
+// const REQUEST_GEN_FRAME_INTERVAL: usize = 10;
+
+const NODE_SCORE_CPU_WEIGHT: f32 = 0.5;
+
+const NODE_SCORE_MEM_WEIGHT: f32 = 0.5;
+
+// const NODE_CNT: usize = 10;
+const NODE_CNT: usize = 30;
+
+const CONTAINER_BASIC_MEM: f32 = 199.0;
+
+const NODE_LEFT_MEM_THRESHOLD: f32 = 2500.0;
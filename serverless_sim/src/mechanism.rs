@@ -1,23 +1,27 @@
-use std::{ cell::{ RefCell, RefMut }, collections::HashMap };
+use std::{ cell::{ RefCell, RefMut }, collections::{ BTreeMap, HashMap } };
 
 use enum_as_inner::EnumAsInner;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
 
 use crate::{
     actions::ESActionWrapper,
-    config::Config,
+    algo_registry,
+    config::{ Config, ConfigError },
     fn_dag::{ EnvFnExt, FnId },
-    mechanism_conf::{ MechConfig, ModuleMechConf },
     mechanism_thread::MechCmdDistributor,
     node::NodeId,
     request::ReqId,
+    rl_target::ControlPoint,
     scale::{
         down_exec::{ new_scale_down_exec, ScaleDownExec },
-        num::{ down_filter::{ CarefulScaleDownFilter, ScaleFilter }, new_scale_num, ScaleNum },
+        num::{ down_filter::{ self, ScaleFilter }, new_scale_num, ScaleNum },
         up_exec::{ new_scale_up_exec, ScaleUpExec },
     },
     sche::prepare_spec_scheduler,
     sim_env::{ SimEnvCoreState, SimEnvHelperState },
     sim_run::Scheduler,
+    spaces::{ self, Space },
     with_env_sub::{ WithEnvCore, WithEnvHelp },
     util,
 };
@@ -41,6 +45,13 @@ pub struct ScheCmd {
     pub memlimit: Option<f32>,
 }
 
+/// 把节点上已调度还没完成的任务撤回，任务回到待调度状态
+pub struct PreemptCmd {
+    pub nid: NodeId,
+    pub reqid: ReqId,
+    pub fnid: FnId,
+}
+
 pub trait SameTarget: Sized {
     fn same_target(&self, other: &Self) -> bool;
 }
@@ -78,36 +89,7 @@ impl<S: SameTarget> CheckDup for Vec<S> {
     }
 }
 
-pub const SCHE_NAMES: [&'static str; 12] = [
-    "rotate",
-    "hash",
-    "bp_balance",
-    "faasflow",
-    "pass",
-    "pos",
-    "fnsche",
-    "random",
-    "greedy",
-    "consistenthash", // "gofs",
-    "ensure_scheduler",
-    "load_least",
-    // "load_least",
-    // "random",
-];
-pub const SCALE_NUM_NAMES: [&'static str; 7] = [
-    "no",
-    "hpa",
-    "lass",
-    "temp_scaler",
-    "full_placement",
-    "rela",
-    "ensure_scaler",
-];
-pub const SCALE_DOWN_EXEC_NAMES: [&'static str; 1] = ["default"];
-pub const SCALE_UP_EXEC_NAMES: [&'static str; 2] = ["least_task", "no"];
 pub const MECH_NAMES: [&'static str; 3] = ["no_scale", "scale_sche_separated", "scale_sche_joint"];
-pub const FILTER_NAMES: [&'static str; 1] = ["careful_down"];
-pub const INSTANCE_LIVE_NAMES: [&'static str; 3] = ["no_evict", "lru", "fifo"];
 
 pub trait Mechanism: Send {
     fn step(
@@ -119,156 +101,38 @@ pub trait Mechanism: Send {
 }
 
 pub trait ConfigNewMec {
-    fn new_mec(&self) -> Option<MechanismImpl>;
+    fn new_mec(&self) -> Result<MechanismImpl, Vec<ConfigError>>;
 }
 
 impl ConfigNewMec for Config {
-    // return none if failed
-    fn new_mec(&self) -> Option<MechanismImpl> {
-        // read mechanism config
-        let module_es = ModuleMechConf::new();
-        if !module_es.check_conf_by_module(&self.mech) {
-            return None;
-        }
-
-        fn check_config(
-            conf: &MechConfig,
-            allow_sche: &Vec<&'static str>,
-            allow_scale_num: &Vec<&'static str>,
-            allow_scale_down_exec: &Vec<&'static str>,
-            allow_scale_up_exec: &Vec<&'static str>
-        ) -> bool {
-            if !allow_sche.contains(&&*conf.sche_conf().0) {
-                log::warn!(
-                    "mech_type {} not support sche {}",
-                    conf.mech_type().0,
-                    conf.sche_conf().0
-                );
-                return false;
-            }
-            if !allow_scale_num.contains(&&*conf.scale_num_conf().0) {
-                log::warn!(
-                    "mech_type {} not support scale_num {}",
-                    conf.mech_type().0,
-                    conf.scale_num_conf().0
-                );
-                return false;
-            }
-            if !allow_scale_down_exec.contains(&&*conf.scale_down_exec_conf().0) {
-                log::warn!(
-                    "mech_type {} not support scale_down_exec {}",
-                    conf.mech_type().0,
-                    conf.scale_down_exec_conf().0
-                );
-                return false;
-            }
-            if !allow_scale_up_exec.contains(&&*conf.scale_up_exec_conf().0) {
-                log::warn!(
-                    "mech_type {} no_scale not support scale_up_exec {}",
-                    conf.mech_type().0,
-                    conf.scale_up_exec_conf().0
-                );
-                return false;
-            }
-            true
-        }
-
-        // check conf relation
-        match &*self.mech.mech_type().0 {
-            "no_scale" => {
-                let allow_sche = vec![
-                    "faasflow",
-                    "pass",
-                    "fnsche",
-                    "random",
-                    "greedy",
-                    "consistenthash",
-                    "hash",
-                    "rotate",
-                    "load_least",
-                ];
-                let allow_scale_num = vec!["no"];
-                let allow_scale_down_exec = vec!["default"];
-                let allow_scale_up_exec = vec!["no"];
-
-                if
-                    !check_config(
-                        &self.mech,
-                        &allow_sche,
-                        &allow_scale_num,
-                        &allow_scale_down_exec,
-                        &allow_scale_up_exec
-                    )
-                {
-                    return None;
-                }
-            }
-            "scale_sche_separated" => {
-                let allow_sche = vec!["random", "greedy", "hash", "rotate","load_least","pass"];
-                let allow_scale_num = vec!["hpa", "lass", "temp_scaler", "full_placement", "rela"];
-                let allow_scale_down_exec = vec!["default"];
-                let allow_scale_up_exec = vec!["least_task"];
-
-                if
-                    !check_config(
-                        &self.mech,
-                        &allow_sche,
-                        &allow_scale_num,
-                        &allow_scale_down_exec,
-                        &allow_scale_up_exec
-                    )
-                {
-                    return None;
-                }
-            }
-            "scale_sche_joint" => {
-                let allow_sche = vec!["pos", "bp_balance", "ensure_scheduler"];
-                let allow_scale_num = vec!["hpa", "lass", "temp_scaler", "full_placement", "rela", "ensure_scaler"];
-                let allow_scale_down_exec = vec!["default"];
-                let allow_scale_up_exec = vec!["least_task"];
-                if
-                    !check_config(
-                        &self.mech,
-                        &allow_sche,
-                        &allow_scale_num,
-                        &allow_scale_down_exec,
-                        &allow_scale_up_exec
-                    )
-                {
-                    return None;
-                }
-            }
-            _ => {
-                panic!("mech_type not supported {}", self.mech.mech_type().0);
-            }
-        }
+    // 配置不合法时返回所有出错的字段
+    fn new_mec(&self) -> Result<MechanismImpl, Vec<ConfigError>> {
+        self.check_valid()?;
 
+        let not_supported = |field: &str, name: String| {
+            vec![ConfigError::new(format!("mech.{}.{}", field, name), "algorithm not implemented")]
+        };
         let Some(sche) = prepare_spec_scheduler(self) else {
-            return None;
+            return Err(not_supported("sche", self.mech.sche_conf().0));
         };
         let Some(scale_num) = new_scale_num(self) else {
-            return None;
+            return Err(not_supported("scale_num", self.mech.scale_num_conf().0));
         };
         let Some(scale_down_exec) = new_scale_down_exec(self) else {
-            return None;
+            return Err(not_supported("scale_down_exec", self.mech.scale_down_exec_conf().0));
         };
         let Some(scale_up_exec) = new_scale_up_exec(self) else {
-            return None;
+            return Err(not_supported("scale_up_exec", self.mech.scale_up_exec_conf().0));
         };
-        let filters = FILTER_NAMES.iter()
-            .filter(|v| self.mech.filter.get(**v).unwrap().is_some())
-            .map(|filters| {
-                let filter = match *filters {
-                    "careful_down" => CarefulScaleDownFilter::new(),
-                    _ => {
-                        panic!("filter not supported {}", filters);
-                    }
-                };
-                let filter: Box<dyn ScaleFilter> = Box::new(filter);
-                RefCell::new(filter)
+        let filters = self.mech
+            .filter_chain()
+            .into_iter()
+            .filter_map(|(name, attr)| {
+                let e = algo_registry::find(down_filter::ALGOS, &name)?;
+                Some(RefCell::new((e.new)(&attr)))
             })
             .collect();
-        Some(MechanismImpl {
+        Ok(MechanismImpl {
             sche: RefCell::new(sche),
             scale_num: RefCell::new(scale_num),
             scale_down_exec: RefCell::new(scale_down_exec),
@@ -277,6 +141,7 @@ impl ConfigNewMec for Config {
             fn_scale_num: RefCell::new(HashMap::new()),
             config: self.clone(),
             step_begin: RefCell::new(0),
+            sim_overhead_ms: RefCell::new(0.0),
         })
     }
 }
@@ -290,6 +155,61 @@ pub struct MechanismImpl {
     filters: Vec<RefCell<Box<dyn ScaleFilter>>>,
     fn_scale_num: RefCell<HashMap<FnId, usize>>,
     pub step_begin: RefCell<u64>,
+    // 算法模拟出来的耗时(如探测节点的网络往返)，不占用真实时间，计入算法耗时
+    sim_overhead_ms: RefCell<f32>,
+}
+
+/// 快照中各算法跨帧保留的状态，见 ScaleNum::save_state 等
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MechState {
+    pub scale_num: Value,
+    /// 按 filter_chain 的顺序
+    pub filters: Vec<Value>,
+    pub sche: Value,
+    pub fn_scale_num: BTreeMap<FnId, usize>,
+}
+
+impl MechanismImpl {
+    pub fn save_state(&self) -> Result<MechState, String> {
+        Ok(MechState {
+            scale_num: self.scale_num.borrow().save_state()?,
+            filters: self.filters
+                .iter()
+                .map(|f| f.borrow().save_state())
+                .collect::<Result<_, _>>()?,
+            sche: self.sche.borrow().save_state()?,
+            fn_scale_num: self.fn_scale_num.borrow().clone().into_iter().collect(),
+        })
+    }
+
+    /// 状态需要来自相同 mech 配置的 save_state
+    pub fn load_state(&self, state: MechState) -> Result<(), String> {
+        if state.filters.len() != self.filters.len() {
+            return Err(
+                format!("mech state has {} filters, expect {}", state.filters.len(), self.filters.len())
+            );
+        }
+        self.scale_num.borrow_mut().load_state(state.scale_num)?;
+        for (filter, state) in self.filters.iter().zip(state.filters) {
+            filter.borrow_mut().load_state(state)?;
+        }
+        self.sche.borrow_mut().load_state(state.sche)?;
+        *self.fn_scale_num.borrow_mut() = state.fn_scale_num.into_iter().collect();
+        Ok(())
+    }
+
+    pub fn action_space(&self, fn_cnt: usize) -> Space {
+        self.scale_num.borrow().action_space(fn_cnt)
+    }
+
+    pub fn add_sim_overhead_ms(&self, ms: f32) {
+        *self.sim_overhead_ms.borrow_mut() += ms;
+    }
+
+    /// 取出并清零本轮模拟出来的耗时
+    pub fn take_sim_overhead_ms(&self) -> u64 {
+        std::mem::take(&mut *self.sim_overhead_ms.borrow_mut()).round() as u64
+    }
 }
 
 pub struct SimEnvObserve {
@@ -376,8 +296,25 @@ impl MechanismImpl {
 
     fn update_scale_num(&self, env: &SimEnvObserve, fnid: FnId, action: &ESActionWrapper) {
         let mut target = self.scale_num.borrow_mut().scale_for_fn(env, fnid, action);
+        // 扩缩容控制点，agent 给出的容器数替换算法的结果
+        let rl_agent = env.help().rl_agent();
+        if rl_agent.attached(ControlPoint::ScaleNum) {
+            let space = Space::Discrete { n: env.core().nodes().len() + 1 };
+            if
+                let Some(ESActionWrapper::Int(n)) = rl_agent.decide(
+                    ControlPoint::ScaleNum,
+                    fnid,
+                    None,
+                    target,
+                    spaces::observe(env),
+                    space
+                )
+            {
+                target = n as usize;
+            }
+        }
         for filter in self.filters.iter() {
-            target = filter.borrow_mut().filter_desired(fnid, target, env.fn_container_cnt(fnid));
+            target = filter.borrow_mut().filter_desired(env, fnid, target, env.fn_container_cnt(fnid));
         }
         self.fn_scale_num.borrow_mut().insert(fnid, target);
     }
@@ -1,17 +1,12 @@
 use serde::{ Deserialize, Serialize };
+use serde_json::{ Map, Value };
 
 use crate::{
-    cache::InstanceCachePolicy,
+    algo_registry::{ self, parse_params, NoParams, Stage },
+    cache::{ self, InstanceCachePolicy },
+    config::ConfigError,
     fn_dag::FnId,
-    mechanism::{
-        FILTER_NAMES,
-        INSTANCE_LIVE_NAMES,
-        MECH_NAMES,
-        SCALE_DOWN_EXEC_NAMES,
-        SCALE_NUM_NAMES,
-        SCALE_UP_EXEC_NAMES,
-        SCHE_NAMES,
-    },
+    mechanism::MECH_NAMES,
 };
 use std::{ collections::HashMap, fs::File };
 
@@ -19,30 +14,21 @@ pub struct ModuleMechConf(pub MechConfig);
 
 impl ModuleMechConf {
     pub fn new() -> Self {
-        ModuleMechConf(MechConfig {
-            scale_num: {
-                SCALE_NUM_NAMES.iter()
-                    .map(|v| (v.to_string(), None))
-                    .collect()
-            },
-            scale_down_exec: SCALE_DOWN_EXEC_NAMES.iter()
-                .map(|v| (v.to_string(), None))
-                .collect(),
-            scale_up_exec: SCALE_UP_EXEC_NAMES.iter()
-                .map(|v| (v.to_string(), None))
-                .collect(),
-            sche: SCHE_NAMES.iter()
-                .map(|v| (v.to_string(), None))
-                .collect(),
-            mech_type: MECH_NAMES.iter()
-                .map(|v| (v.to_string(), None))
-                .collect(),
-            filter: FILTER_NAMES.iter()
-                .map(|v| (v.to_string(), None))
-                .collect(),
-            instance_cache_policy: INSTANCE_LIVE_NAMES.iter()
+        let none_selected = |stage: &str| -> StageMap {
+            stage_names(stage)
+                .iter()
                 .map(|v| (v.to_string(), None))
-                .collect(),
+                .collect()
+        };
+        ModuleMechConf(MechConfig {
+            scale_num: none_selected("scale_num"),
+            scale_down_exec: none_selected("scale_down_exec"),
+            scale_up_exec: none_selected("scale_up_exec"),
+            sche: none_selected("sche"),
+            mech_type: none_selected("mech_type"),
+            filter: none_selected("filter"),
+            filter_order: vec![],
+            instance_cache_policy: none_selected("instance_cache_policy"),
         })
     }
     ///将结构体中的配置数据导出为一个JSON文件
@@ -101,7 +87,175 @@ impl ModuleMechConf {
     }
 }
 
+/// 阶段可选的算法名，mech_type 不在注册表中
+fn stage_names(stage: &str) -> Vec<&'static str> {
+    match Stage::from_name(stage) {
+        Some(s) => s.names(),
+        None => MECH_NAMES.to_vec(),
+    }
+}
+
+/// 类型化的单个算法配置，如 {"type": "pos", "mode": "greedy"}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlgoConf {
+    #[serde(rename = "type")]
+    pub name: String,
+    #[serde(flatten)]
+    pub params: Map<String, Value>,
+}
+
+impl AlgoConf {
+    /// 按算法解析参数，返回旧格式的参数字符串
+    pub fn to_attr(&self, stage: &str) -> Result<String, String> {
+        match Stage::from_name(stage).and_then(|s| s.find(&self.name)) {
+            Some(info) => (info.parse_params)(&self.params),
+            // mech_type 没有参数
+            None => parse_params::<NoParams>(&self.params),
+        }
+    }
+}
+
+/// 反序列化时每个阶段可以是旧格式 {"pos": "greedy", "random": null, ...}，
+/// 也可以是类型化的 {"type": "pos", "mode": "greedy"}；filter 的类型化格式为数组，数组的顺序即作用的顺序。
+/// 除 mech_type、sche 外都可以省略，取默认值
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MechConfigRepr {
+    mech_type: Value,
+    #[serde(default)]
+    scale_num: Option<Value>,
+    #[serde(default)]
+    scale_down_exec: Option<Value>,
+    #[serde(default)]
+    scale_up_exec: Option<Value>,
+    sche: Value,
+    #[serde(default)]
+    filter: Option<Value>,
+    #[serde(default)]
+    filter_order: Vec<String>,
+    #[serde(default)]
+    instance_cache_policy: Option<Value>,
+}
+
+type StageMap = HashMap<String, Option<String>>;
+
+// 只选中 name 的旧格式
+fn stage_select(names: &[&str], name: &str, attr: String) -> StageMap {
+    let mut map = names
+        .iter()
+        .map(|n| (n.to_string(), None))
+        .collect::<StageMap>();
+    map.insert(name.to_owned(), Some(attr));
+    map
+}
+
+fn typed_stage_attr(stage: &str, conf: &AlgoConf) -> Result<String, String> {
+    let names = stage_names(stage);
+    if !names.contains(&&*conf.name) {
+        return Err(
+            format!(
+                "mech.{}: unknown type {:?}, should be one of: {}",
+                stage,
+                conf.name,
+                names.join(", ")
+            )
+        );
+    }
+    conf.to_attr(stage).map_err(|e| format!("mech.{}.{}: {}", stage, conf.name, e))
+}
+
+fn stage_from_value(stage: &str, v: Value) -> Result<StageMap, String> {
+    let typed = v.as_object().is_some_and(|m| m.contains_key("type"));
+    if !typed {
+        return serde_json::from_value::<StageMap>(v).map_err(|e| format!("mech.{}: {}", stage, e));
+    }
+    let conf = serde_json::from_value::<AlgoConf>(v).map_err(|e| format!("mech.{}: {}", stage, e))?;
+    let attr = typed_stage_attr(stage, &conf)?;
+    Ok(stage_select(&stage_names(stage), &conf.name, attr))
+}
+
+// 返回选中的 filter 和数组给出的顺序
+fn filter_from_value(v: Value) -> Result<(StageMap, Vec<String>), String> {
+    let Value::Array(list) = v else {
+        return stage_from_value("filter", v).map(|map| (map, vec![]));
+    };
+    let mut map = stage_names("filter")
+        .iter()
+        .map(|n| (n.to_string(), None))
+        .collect::<StageMap>();
+    let mut order = vec![];
+    for conf in list {
+        let conf = serde_json::from_value::<AlgoConf>(conf).map_err(|e| format!("mech.filter: {}", e))?;
+        let attr = typed_stage_attr("filter", &conf)?;
+        if order.contains(&conf.name) {
+            return Err(format!("mech.filter: {} selected more than once", conf.name));
+        }
+        order.push(conf.name.clone());
+        map.insert(conf.name, Some(attr));
+    }
+    Ok((map, order))
+}
+
+impl TryFrom<MechConfigRepr> for MechConfig {
+    type Error = String;
+
+    fn try_from(repr: MechConfigRepr) -> Result<Self, String> {
+        let mech_type = stage_from_value("mech_type", repr.mech_type)?;
+        let no_scale = mech_type.get("no_scale").is_some_and(|v| v.is_some());
+        let stage = |stage: &str, v: Option<Value>, default: Option<&str>| {
+            match (v, default) {
+                (Some(v), _) => stage_from_value(stage, v),
+                (None, Some(default)) =>
+                    Ok(stage_select(&stage_names(stage), default, String::new())),
+                (None, None) => Err(format!("mech.{}: missing", stage)),
+            }
+        };
+        let (filter, filter_order) = match repr.filter {
+            Some(v) => filter_from_value(v)?,
+            None =>
+                (
+                    stage_names("filter")
+                        .iter()
+                        .map(|n| (n.to_string(), None))
+                        .collect(),
+                    vec![],
+                ),
+        };
+        if !filter_order.is_empty() && !repr.filter_order.is_empty() {
+            return Err("mech.filter_order: should not be set when mech.filter is an array".to_owned());
+        }
+        Ok(MechConfig {
+            scale_num: stage(
+                "scale_num",
+                repr.scale_num,
+                no_scale.then_some("no")
+            )?,
+            scale_down_exec: stage(
+                "scale_down_exec",
+                repr.scale_down_exec,
+                Some("default")
+            )?,
+            scale_up_exec: stage(
+                "scale_up_exec",
+                repr.scale_up_exec,
+                Some(if no_scale { "no" } else { "least_task" })
+            )?,
+            sche: stage_from_value("sche", repr.sche)?,
+            filter,
+            filter_order: if filter_order.is_empty() { repr.filter_order } else { filter_order },
+            instance_cache_policy: stage(
+                "instance_cache_policy",
+                repr.instance_cache_policy,
+                Some("no_evict")
+            )?,
+            mech_type,
+        })
+    }
+}
+
+/// 序列化时仍输出旧格式，和 Config::str、records 及 python 脚本保持一致
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "MechConfigRepr")]
 pub struct MechConfig {
     pub mech_type: HashMap<String, Option<String>>,
     pub scale_num: HashMap<String, Option<String>>,
@@ -109,57 +263,26 @@ pub struct MechConfig {
     pub scale_up_exec: HashMap<String, Option<String>>,
     pub sche: HashMap<String, Option<String>>,
     pub filter: HashMap<String, Option<String>>,
+    /// filter 作用的顺序，没有列出的选中 filter 按注册顺序排在后面
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter_order: Vec<String>,
     pub instance_cache_policy: HashMap<String, Option<String>>,
 }
 
 impl MechConfig {
     pub fn new_test() -> Self {
+        let select = |stage: &str, name: &str, attr: &str| {
+            stage_select(&stage_names(stage), name, attr.to_owned())
+        };
         MechConfig {
-            scale_num: {
-                SCALE_NUM_NAMES.iter()
-                    .map(|v| {
-                        (v.to_string(), if *v == "hpa" { Some("".to_string()) } else { None })
-                    })
-                    .collect()
-            },
-            scale_down_exec: SCALE_DOWN_EXEC_NAMES.iter()
-                .map(|v| {
-                    (v.to_string(), if *v == "default" { Some("".to_string()) } else { None })
-                })
-                .collect(),
-            scale_up_exec: SCALE_UP_EXEC_NAMES.iter()
-                .enumerate()
-                .map(|(_i, v)| {
-                    (v.to_string(), if *v == "least_task" { Some("".to_string()) } else { None })
-                })
-                .collect(),
-            sche: SCHE_NAMES.iter()
-                .enumerate()
-                .map(|(_i, v)| {
-                    (v.to_string(), if *v == "random" { Some("".to_string()) } else { None })
-                })
-                .collect(),
-            mech_type: MECH_NAMES.iter()
-                .enumerate()
-                .map(|(_i, v)| {
-                    (
-                        v.to_string(),
-                        if *v == "scale_sche_separated" { Some("".to_string()) } else { None },
-                    )
-                })
-                .collect(),
-            filter: FILTER_NAMES.iter()
-                .enumerate()
-                .map(|(_i, v)| {
-                    (v.to_string(), if *v == "careful_down" { Some("".to_string()) } else { None })
-                })
-                .collect(),
-            instance_cache_policy: INSTANCE_LIVE_NAMES.iter()
-                .enumerate()
-                .map(|(_i, v)| {
-                    (v.to_string(), if *v == "lru" { Some("10".to_string()) } else { None })
-                })
-                .collect(),
+            scale_num: select("scale_num", "hpa", ""),
+            scale_down_exec: select("scale_down_exec", "default", ""),
+            scale_up_exec: select("scale_up_exec", "least_task", ""),
+            sche: select("sche", "random", ""),
+            mech_type: select("mech_type", "scale_sche_separated", ""),
+            filter: select("filter", "careful_down", ""),
+            filter_order: vec![],
+            instance_cache_policy: select("instance_cache_policy", "lru", "10"),
         }
     }
     pub fn mech_type(&self) -> (String, String) {
@@ -171,19 +294,120 @@ impl MechConfig {
             .unwrap()
     }
 
+    /// 选中的 filter 按作用顺序排列的 (name, attr)
+    pub fn filter_chain(&self) -> Vec<(String, String)> {
+        let selected = |name: &str| self.filter.get(name).and_then(|v| v.clone());
+        let mut chain = self.filter_order
+            .iter()
+            .filter_map(|name| selected(name).map(|attr| (name.clone(), attr)))
+            .collect::<Vec<_>>();
+        for name in stage_names("filter") {
+            if !self.filter_order.iter().any(|n| n == name) {
+                if let Some(attr) = selected(name) {
+                    chain.push((name.to_owned(), attr));
+                }
+            }
+        }
+        chain
+    }
+
     pub fn new_instance_cache_policy(&self) -> Box<dyn InstanceCachePolicy<FnId>> {
         let (policy, arg) = self.instance_cache_policy_conf();
-        match &*policy {
-            "lru" => {
-                let limit = arg.parse::<usize>().expect("Please offer lru cache policy arg");
-                Box::new(crate::cache::lru::LRUCache::new(limit))
+        let entry = algo_registry
+            ::find(cache::ALGOS, &policy)
+            .unwrap_or_else(|| panic!("new_instance_cache_policy {}", policy));
+        (entry.new)(&arg)
+    }
+
+    /// 检查各阶段的选择、mech_type 的搭配和各算法的参数，错误追加到 errs
+    pub fn check_valid(&self, errs: &mut Vec<ConfigError>) {
+        let before = errs.len();
+        let stages: [(&str, &StageMap, bool); 7] = [
+            ("mech_type", &self.mech_type, true),
+            ("scale_num", &self.scale_num, true),
+            ("scale_down_exec", &self.scale_down_exec, true),
+            ("scale_up_exec", &self.scale_up_exec, true),
+            ("sche", &self.sche, true),
+            ("filter", &self.filter, false),
+            ("instance_cache_policy", &self.instance_cache_policy, true),
+        ];
+        for (stage, conf, must_one_some) in stages {
+            let names = stage_names(stage);
+            let mut unknown = conf
+                .keys()
+                .filter(|k| !names.contains(&k.as_str()))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            for k in unknown {
+                errs.push(
+                    ConfigError::new(
+                        format!("mech.{}.{}", stage, k),
+                        format!("unknown algorithm, should be one of: {}", names.join(", "))
+                    )
+                );
             }
-            "fifo" => {
-                let limit = arg.parse::<usize>().expect("Please offer fifo cache policy arg");
-                Box::new(crate::cache::lru::LRUCache::new(limit))
+            let mut selected = conf
+                .iter()
+                .filter(|(_k, v)| v.is_some())
+                .map(|(k, _v)| k.as_str())
+                .collect::<Vec<_>>();
+            selected.sort();
+            if must_one_some && selected.len() != 1 {
+                errs.push(
+                    ConfigError::new(
+                        format!("mech.{}", stage),
+                        format!(
+                            "exactly one algorithm should be selected, got [{}]",
+                            selected.join(", ")
+                        )
+                    )
+                );
+            }
+        }
+        for (i, name) in self.filter_order.iter().enumerate() {
+            if self.filter_order[..i].contains(name) {
+                errs.push(ConfigError::new("mech.filter_order", format!("{} listed more than once", name)));
+            } else if !self.filter.get(name).is_some_and(|v| v.is_some()) {
+                errs.push(ConfigError::new("mech.filter_order", format!("{} is not selected in mech.filter", name)));
+            }
+        }
+        // 下面用到各阶段选中的算法
+        if errs.len() > before {
+            return;
+        }
+
+        let mech_type = self.mech_type().0;
+        for (stage, conf, _) in &stages[1..] {
+            let stage = Stage::from_name(stage).unwrap();
+            let mut selected = conf
+                .iter()
+                .filter_map(|(k, v)| v.as_ref().map(|attr| (k, attr)))
+                .collect::<Vec<_>>();
+            selected.sort();
+            for (name, attr) in selected {
+                let info = stage.find(name).unwrap();
+                if !info.supports(&mech_type) {
+                    let allow = stage
+                        .algos()
+                        .into_iter()
+                        .filter(|a| a.supports(&mech_type))
+                        .map(|a| a.name)
+                        .collect::<Vec<_>>();
+                    errs.push(
+                        ConfigError::new(
+                            format!("mech.{}.{}", stage.name(), name),
+                            format!(
+                                "not supported by mech_type {}, should be one of: {}",
+                                mech_type,
+                                allow.join(", ")
+                            )
+                        )
+                    );
+                }
+                if let Err(msg) = (info.check_attr)(attr) {
+                    errs.push(ConfigError::new(format!("mech.{}.{}", stage.name(), name), msg));
+                }
             }
-            "no_evict" => Box::new(crate::cache::no_evict::NoEvict::new()),
-            _ => panic!("new_instance_cache_policy"),
         }
     }
 
@@ -350,3 +574,70 @@ impl MechConfig {
     //     false
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MechConfig;
+
+    #[test]
+    fn test_typed_mech_config() {
+        let typed = serde_json::from_value::<MechConfig>(
+            serde_json::json!({
+                "mech_type": {"type": "scale_sche_joint"},
+                "scale_num": {"type": "hpa"},
+                "sche": {"type": "pos", "mode": "random"},
+                "filter": [{"type": "careful_down"}],
+                "instance_cache_policy": {"type": "lru", "limit": 20},
+            })
+        ).unwrap();
+        assert_eq!(typed.sche_conf(), ("pos".to_owned(), "random".to_owned()));
+        assert_eq!(typed.scale_up_exec_conf(), ("least_task".to_owned(), "".to_owned()));
+        assert_eq!(typed.instance_cache_policy_conf(), ("lru".to_owned(), "20".to_owned()));
+        assert_eq!(typed.filter.values().filter(|v| v.is_some()).count(), 1);
+
+        // filter 按数组的顺序作用，序列化后保留顺序
+        let chained = serde_json::from_value::<MechConfig>(
+            serde_json::json!({
+                "mech_type": {"type": "scale_sche_separated"},
+                "scale_num": {"type": "hpa"},
+                "sche": {"type": "random"},
+                "filter": [{"type": "zero_delay", "delay_ms": 20}, {"type": "replicas", "max": 5}, {"type": "careful_down"}],
+            })
+        ).unwrap();
+        let names = |c: &MechConfig| c.filter_chain().into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        assert_eq!(names(&chained), vec!["zero_delay", "replicas", "careful_down"]);
+        let back = serde_json::from_value::<MechConfig>(serde_json::to_value(&chained).unwrap()).unwrap();
+        assert_eq!(names(&back), names(&chained));
+        assert!(
+            serde_json::from_value::<MechConfig>(
+                serde_json::json!({"mech_type": {"type": "no_scale"}, "sche": {"type": "hash"},
+                    "filter": [{"type": "replicas"}, {"type": "replicas"}]})
+            ).is_err()
+        );
+
+        // 旧格式照常解析，序列化仍为旧格式
+        let legacy = serde_json::to_value(&typed).unwrap();
+        assert!(legacy["sche"]["pos"].is_string());
+        let back = serde_json::from_value::<MechConfig>(legacy).unwrap();
+        assert_eq!(back.sche, typed.sche);
+        assert_eq!(back.instance_cache_policy, typed.instance_cache_policy);
+
+        let no_scale = serde_json::from_value::<MechConfig>(
+            serde_json::json!({"mech_type": {"type": "no_scale"}, "sche": {"type": "hash"}})
+        ).unwrap();
+        assert_eq!(no_scale.scale_num_conf().0, "no");
+        assert_eq!(no_scale.scale_up_exec_conf().0, "no");
+        assert_eq!(no_scale.instance_cache_policy_conf().0, "no_evict");
+
+        // 拼错的参数和算法名都报错
+        for bad in [
+            serde_json::json!({"mech_type": {"type": "no_scale"}, "sche": {"type": "hash", "mode": "x"}}),
+            serde_json::json!({"mech_type": {"type": "no_scale"}, "sche": {"type": "hsah"}}),
+            serde_json::json!({"mech_type": {"type": "scale_sche_joint"}, "scale_num": {"type": "hpa"},
+                "sche": {"type": "pos", "mod": "greedy"}}),
+            serde_json::json!({"mech_type": {"type": "no_scale"}, "sche": {"type": "hash"}, "shce": {}}),
+        ] {
+            assert!(serde_json::from_value::<MechConfig>(bad).is_err());
+        }
+    }
+}
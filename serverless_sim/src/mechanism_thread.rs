@@ -1,4 +1,6 @@
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
+
+use parking_lot::Mutex;
 
 use enum_as_inner::EnumAsInner;
 #[cfg(target_os = "windows")]
@@ -7,10 +9,13 @@ use thread_priority::{set_current_thread_priority, ThreadPriority, WinAPIThreadP
 use windows::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY};
 
 use crate::actions::ESActionWrapper;
-use crate::mechanism::{DownCmd, Mechanism, MechanismImpl, ScheCmd, SimEnvObserve, UpCmd};
+use crate::audit::DecisionKind;
+use crate::mechanism::{DownCmd, Mechanism, MechanismImpl, PreemptCmd, ScheCmd, SimEnvObserve, UpCmd};
 
+use crate::rl_target::ControlPoint;
+use crate::spaces::{self, Space};
 use crate::util;
-use crate::with_env_sub::WithEnvHelp;
+use crate::with_env_sub::{WithEnvCore, WithEnvHelp};
 
 pub type MechCmdDistributor = mpsc::Sender<MechScheduleOnceRes>;
 
@@ -25,6 +30,7 @@ pub enum MechScheduleOnceRes {
     ScheCmd(ScheCmd),
     ScaleUpCmd(UpCmd),
     ScaleDownCmd(DownCmd),
+    PreemptCmd(PreemptCmd),
     Cmds {
         sche_cmds: Vec<ScheCmd>,
         scale_up_cmds: Vec<UpCmd>,
@@ -35,7 +41,22 @@ pub enum MechScheduleOnceRes {
     },
 }
 
-pub fn spawn(mech: MechanismImpl) -> mpsc::Sender<MechScheduleOnce> {
+// 调度控制点，agent 给出的节点替换调度算法的选择
+fn sche_control_point(env: &SimEnvObserve, sche: &mut ScheCmd) {
+    let space = Space::Discrete { n: env.core().nodes().len() };
+    if
+        let Some(ESActionWrapper::Int(nid)) = env
+            .help()
+            .rl_agent()
+            .decide(ControlPoint::Sche, sche.fnid, Some(sche.reqid), sche.nid, spaces::observe(env), space)
+    {
+        sche.nid = nid as usize;
+        env.help().audit().note_algo(DecisionKind::Sche, Some(sche.reqid), sche.fnid, "rl");
+    }
+}
+
+/// mech 和 SimEnv 共享，每次决策时加锁，快照在两次决策之间读写算法状态
+pub fn spawn(mech: Arc<Mutex<MechanismImpl>>) -> mpsc::Sender<MechScheduleOnce> {
     let (tx, rx) = mpsc::channel();
     std::thread::spawn(move || {
         // 尝试设置当前线程的优先级
@@ -56,7 +77,7 @@ pub fn spawn(mech: MechanismImpl) -> mpsc::Sender<MechScheduleOnce> {
     tx
 }
 
-fn mechanism_loop(rx: mpsc::Receiver<MechScheduleOnce>, mech: MechanismImpl) {
+fn mechanism_loop(rx: mpsc::Receiver<MechScheduleOnce>, mech: Arc<Mutex<MechanismImpl>>) {
     loop {
         let res = match rx.recv() {
             Ok(res) => res,
@@ -67,18 +88,41 @@ fn mechanism_loop(rx: mpsc::Receiver<MechScheduleOnce>, mech: MechanismImpl) {
         };
 
         std::thread::sleep(std::time::Duration::from_millis(10));
+        let mech = mech.lock();
 
         let begin_ms = util::now_ms();
         // let measure = util::MeasureThreadTime::new();
         // let begin_cpu = cpu_time::ThreadTime::now();
-        mech.step(&res.sim_env, res.action, &res.responser);
+        if res.sim_env.help().rl_agent().attached(ControlPoint::Sche) {
+            // 先收集本次的指令，逐条经过调度控制点后再发给仿真
+            let (tx, rx) = mpsc::channel();
+            mech.step(&res.sim_env, res.action, &tx);
+            drop(tx);
+            for mut cmd in rx {
+                match &mut cmd {
+                    MechScheduleOnceRes::ScheCmd(sche) => sche_control_point(&res.sim_env, sche),
+                    MechScheduleOnceRes::Cmds { sche_cmds, .. } => {
+                        for sche in sche_cmds.iter_mut() {
+                            sche_control_point(&res.sim_env, sche);
+                        }
+                    }
+                    _ => {}
+                }
+                res.responser.send(cmd).unwrap();
+            }
+        } else {
+            mech.step(&res.sim_env, res.action, &res.responser);
+        }
         // let passed_ms = measure.passed_100ns();
-        let end_ms = util::now_ms();
+        // 等待 agent 的时间不算作算法耗时
+        let end_ms = util::now_ms() - res.sim_env.help().rl_agent().take_wait_ms();
+        // 算法模拟的耗时加在真实耗时上
+        let sim_overhead_ms = mech.take_sim_overhead_ms();
         // log::info!("master mech run cpu:{:?}, total:{} ms", begin_cpu.elapsed(), end_ms - begin_ms);
         let mech_latency = if mech.config.no_mech_latency {
             0
         } else {
-            end_ms - begin_ms
+            end_ms - begin_ms + sim_overhead_ms
         };
         res.responser
             .send(MechScheduleOnceRes::End {
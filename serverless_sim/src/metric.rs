@@ -1,12 +1,17 @@
 use crate::fn_dag::EnvFnExt;
 use crate::node::EnvNodeExt;
-use crate::request::ReqId;
+use crate::request::{ ReqId, Request };
+use crate::request_log::RequestLogger;
 use crate::score::EnvMetricExt;
 use crate::{
+    admission::AdmissionConfig,
+    columnar::{ ColumnarRecorder, FrameStats },
     config::Config,
     fn_dag::FnId,
     mechanism_conf::ModuleMechConf,
+    reward::RewardConfig,
     sim_env::SimEnv,
+    timeout::TimeoutConfig,
     util::Window,
 };
 use chrono;
@@ -49,7 +54,7 @@ use std::{ collections::{ BTreeMap, HashMap }, fs::{ self, File }, io::{ Read, W
 //     cost: f32,
 //     score:f32,
 // }
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MechMetric {
     // 函数-窗口，窗口中记录了该函数在 窗口长度 中被请求但还未被调度的次数
     fn_recent_req_cnt_window: HashMap<FnId, Window>,
@@ -180,7 +185,7 @@ impl MechMetric {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OneFrameMetric {
     // pub frame: usize,
     done_request_count: usize,
@@ -231,6 +236,10 @@ pub struct Recorder {
     // 6 cost
     // pub frames: Vec<Vec<serde_json::Value>>,
     file: RefCell<File>,
+    /// config.export_columnar 打开时按列导出
+    columnar: Option<ColumnarRecorder>,
+    /// config.request_log 打开时记录每个完成的请求
+    request_log: Option<RefCell<RequestLogger>>,
 }
 
 impl Drop for Recorder {
@@ -269,12 +278,16 @@ const FRAME_IDX_REQ_DATA_RECV_TIME: usize = 11; // 数据接收时间
 const FRAME_IDX_REQ_EXE_TIME: usize = 12; // 请求的执行时间
 const FRAME_IDX_ALGO_EXE_TIME: usize = 13; // 算法执行时间
 const FRAME_IDX_FNCONTAINER_COUNT: usize = 14; // 总的容器数量
+const FRAME_IDX_TENANTS: usize = 15; // 各租户的请求延迟和资源份额
+const FRAME_IDX_ADMISSION: usize = 16; // 准入控制接纳、拒绝、限流的请求数
+const FRAME_IDX_TIMED_OUT_REQ_COUNT: usize = 17; // 累计超时取消的请求数
 
 // the last + 1
-const FRAME_LEN: usize = 15;
+const FRAME_LEN: usize = 18;
 
 impl Recorder {
-    pub fn new(mut key: String) -> Self {
+    pub fn new(config: &Config) -> Self {
+        let key = config.str();
         // let args = parse_arg::get_arg();
         // key = key.replace(":", "_");
         // key = key.replace(",", ".");
@@ -301,7 +314,22 @@ impl Recorder {
 ", record_name).as_bytes()
         ).unwrap();
 
+        let request_log = if config.request_log {
+            RequestLogger::new(&record_name)
+                .map_err(|e| log::warn!("request log disabled: {}", e))
+                .ok()
+                .map(RefCell::new)
+        } else {
+            None
+        };
+
         Self {
+            columnar: if config.export_columnar {
+                Some(ColumnarRecorder::new(record_name.clone()))
+            } else {
+                None
+            },
+            request_log,
             record_name,
             // frames: Vec::new(),
             file: file.into(),
@@ -310,8 +338,39 @@ impl Recorder {
 
     // 将模拟环境中的一帧数据添加到记录中
     pub fn add_frame(&mut self, sim_env: &SimEnv) {
+        let stats = FrameStats {
+            frame: sim_env.current_frame(),
+            running_req_cnt: sim_env.core.requests().len(),
+            new_req_cnt: sim_env.core
+                .requests()
+                .values()
+                .filter(|req| req.begin_frame == sim_env.current_frame())
+                .count(),
+            done_req_cnt: sim_env.help.metric().done_request_count,
+            req_done_time_avg_ms: sim_env.req_done_time_avg(),
+            req_done_time_std_ms: sim_env.req_done_time_std(),
+            req_done_time_avg_90p_ms: sim_env.req_done_time_avg_90p(),
+            cost_per_req: sim_env.cost_each_req(),
+            score: sim_env.score(),
+            req_wait_sche_time_ms: sim_env.req_wait_sche_time_avg(),
+            req_wait_coldstart_time_ms: sim_env.req_wait_coldstart_time_avg(),
+            req_data_recv_time_ms: sim_env.req_data_recv_time_avg(),
+            req_exe_time_ms: sim_env.req_exe_time_avg(),
+            algo_exe_time_ms: sim_env.help.avg_algo_exc_time(),
+            fn_container_cnt: sim_env.core
+                .nodes()
+                .iter()
+                .map(|n| n.fn_containers.borrow().len())
+                .sum::<usize>(),
+        };
+        if let Some(columnar) = self.columnar.as_mut() {
+            if let Err(e) = columnar.add_frame(sim_env, &stats) {
+                log::warn!("columnar add frame failed: {}", e);
+            }
+        }
+
         let mut frame = vec![Value::Null; FRAME_LEN];
-        frame[FRAME_IDX_FRAME] = sim_env.core.current_frame().into();
+        frame[FRAME_IDX_FRAME] = stats.frame.into();
         frame[FRAME_IDX_RUNNING_REQS] = sim_env.core
             .requests()
             .iter()
@@ -336,23 +395,21 @@ impl Recorder {
             })
             .collect::<Vec<_>>()
             .into();
-        frame[FRAME_IDX_REQ_DONE_TIME_AVG] = sim_env.req_done_time_avg().into();
-        frame[FRAME_IDX_REQ_DONE_TIME_STD] = sim_env.req_done_time_std().into();
-        frame[FRAME_IDX_REQ_DONE_TIME_AVG_90P] = sim_env.req_done_time_avg_90p().into();
-        frame[FRAME_IDX_COST] = sim_env.cost_each_req().into();
-        frame[FRAME_IDX_SCORE] = sim_env.score().into();
-        frame[FRAME_IDX_DONE_REQ_COUNT] = sim_env.help.metric().done_request_count.into();
-        frame[FRAME_IDX_REQ_WAIT_SCHE_TIME] = sim_env.req_wait_sche_time_avg().into();
-        frame[FRAME_IDX_REQ_WAIT_COLDSTART_TIME] = sim_env.req_wait_coldstart_time_avg().into();
-        frame[FRAME_IDX_REQ_DATA_RECV_TIME] = sim_env.req_data_recv_time_avg().into();
-        frame[FRAME_IDX_REQ_EXE_TIME] = sim_env.req_exe_time_avg().into();
-        frame[FRAME_IDX_ALGO_EXE_TIME] = sim_env.help.avg_algo_exc_time().into();
-        frame[FRAME_IDX_FNCONTAINER_COUNT] = sim_env.core
-            .nodes()
-            .iter()
-            .map(|n| n.fn_containers.borrow().len())
-            .sum::<usize>()
-            .into();
+        frame[FRAME_IDX_REQ_DONE_TIME_AVG] = stats.req_done_time_avg_ms.into();
+        frame[FRAME_IDX_REQ_DONE_TIME_STD] = stats.req_done_time_std_ms.into();
+        frame[FRAME_IDX_REQ_DONE_TIME_AVG_90P] = stats.req_done_time_avg_90p_ms.into();
+        frame[FRAME_IDX_COST] = stats.cost_per_req.into();
+        frame[FRAME_IDX_SCORE] = stats.score.into();
+        frame[FRAME_IDX_DONE_REQ_COUNT] = stats.done_req_cnt.into();
+        frame[FRAME_IDX_REQ_WAIT_SCHE_TIME] = stats.req_wait_sche_time_ms.into();
+        frame[FRAME_IDX_REQ_WAIT_COLDSTART_TIME] = stats.req_wait_coldstart_time_ms.into();
+        frame[FRAME_IDX_REQ_DATA_RECV_TIME] = stats.req_data_recv_time_ms.into();
+        frame[FRAME_IDX_REQ_EXE_TIME] = stats.req_exe_time_ms.into();
+        frame[FRAME_IDX_ALGO_EXE_TIME] = stats.algo_exe_time_ms.into();
+        frame[FRAME_IDX_FNCONTAINER_COUNT] = stats.fn_container_cnt.into();
+        frame[FRAME_IDX_TENANTS] = serde_json::to_value(sim_env.tenant_metrics()).unwrap();
+        frame[FRAME_IDX_ADMISSION] = serde_json::to_value(&sim_env.help.admission().stats).unwrap();
+        frame[FRAME_IDX_TIMED_OUT_REQ_COUNT] = sim_env.timed_out_req_cnt().into();
 
         self.file
             .borrow_mut()
@@ -363,7 +420,17 @@ impl Recorder {
         // self.frames.push(frame);
     }
 
+    pub fn log_done_request(&self, req: &Request) {
+        if let Some(request_log) = &self.request_log {
+            request_log.borrow_mut().log(req);
+        }
+    }
+
     pub fn flush(&self, env: &SimEnv) {
+        if let Some(request_log) = &self.request_log {
+            request_log.borrow_mut().flush();
+        }
+
         // seek back 2 bytes ",\n"
         let flen = self.file.borrow_mut().metadata().unwrap().len();
         if flen < 100 {
@@ -375,6 +442,13 @@ impl Recorder {
             .unwrap();
 
         self.file.borrow_mut().write_all("\n]}".as_bytes()).unwrap();
+
+        if let Some(columnar) = &self.columnar {
+            match columnar.write(env) {
+                Ok(dir) => log::info!("columnar records written to {}", dir),
+                Err(e) => log::warn!("columnar export failed: {}", e),
+            }
+        }
         // self.file.flush().unwrap();
         // if env.help.config().no_log {
         //     log::info!("no metric record, skip flush");
@@ -428,6 +502,15 @@ impl RecordFile {
 
             mech: ModuleMechConf::new().0,
             total_frame: 1000,
+            total_ms: None,
+            event_driven: false,
+            frame_duration_ms: 1.0,
+            reward: RewardConfig::default(),
+            export_columnar: false,
+            request_log: false,
+            tenants: vec![],
+            admission: AdmissionConfig::default(),
+            timeout: TimeoutConfig::default(),
         };
 
         Some(Self {
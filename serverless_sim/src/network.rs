@@ -9,19 +9,60 @@ use crate::apis::{
     StepResp,
     RlStepReq,
     RlStepResp,
+    StepBatchReq,
+    StepBatchResp,
+    SnapshotReq,
+    SnapshotResp,
+    RestoreReq,
+    RestoreResp,
+    ForkReq,
+    ForkResp,
+    SpacesReq,
+    SpacesResp,
+    RlAttachReq,
+    RlAttachResp,
+    RlPollReq,
+    RlPollResp,
+    RlActReq,
+    RlActResp,
+    StepTypedReq,
+    StepTypedResp,
+    AuditLogReq,
+    AuditLogResp,
 };
+use crate::actions::ESActionWrapper;
+use crate::audit::{ AuditHub, AuditQuery, DecisionKind };
 use crate::node::EnvNodeExt;
-use crate::rl_target::RL_TARGET;
-use crate::{ apis, config::Config, metric::{ self, Records }, sim_env::SimEnv };
+use crate::rl_target::{ ControlPoint, RlAgentHub };
+use crate::mechanism_conf::MechConfig;
+use crate::snapshot::{ snapshot_path, SimEnvSnapshot };
+use crate::spaces::{ self, SPACES_VERSION };
+use crate::telemetry::TelemetryHub;
+use crate::{ apis, config::{ config_errors_str, Config, ConfigError }, metric::{ self, Records }, sim_env::SimEnv };
 use async_trait::async_trait;
-use axum::{ http::StatusCode, routing::post, Json, Router };
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::sse::{ Event, KeepAlive, Sse },
+    routing::{ get, post },
+    Json,
+    Router,
+};
+use futures::Stream;
 use moka::sync::Cache;
 use parking_lot::{ Mutex, RwLock };
 use serde::{ Deserialize, Serialize };
 use serde_json::Value;
 
-use std::time::{ Instant, Duration };
-use std::{ cmp::min, collections::HashMap, fs::{ self, File }, io::Read, sync::Arc };
+use std::{
+    cmp::min,
+    convert::Infallible,
+    collections::{ HashMap, HashSet },
+    fs::{ self, File },
+    io::Read,
+    sync::{ atomic::{ AtomicUsize, Ordering }, Arc },
+    time::Duration,
+};
 
 pub async fn start() {
     // build our application with a route
@@ -31,7 +72,8 @@ pub async fn start() {
         .route("/get_seeds_metrics", post(get_seeds_metrics))
         .route("/history_list", post(history_list))
         .route("/history", post(history))
-        .route("/meteic", post(metric));
+        .route("/meteic", post(metric))
+        .route("/telemetry", get(telemetry));
     app = apis::add_routers(app);
     // run our app with hyper, listening globally on port 3000
     // run it with hyper on localhost:3000
@@ -50,22 +92,111 @@ pub async fn start() {
 
 // 用于定义全局静态变量，这些变量在首次访问时才进行初始化，且初始化过程只执行一次。
 lazy_static! {
-    /// This is an example for using doc comment attributes
-    pub static ref SIM_ENVS: RwLock<HashMap<String,Mutex<SimEnv>>> = RwLock::new(HashMap::new());
+    /// 全局表只在查找、插入、删除时短暂加锁，
+    /// 每个 env 单独加锁，不同 env 可以在不同线程上并行 step
+    pub static ref SIM_ENVS: RwLock<HashMap<String,Arc<Mutex<SimEnv>>>> = RwLock::new(HashMap::new());
+    /// 和 SIM_ENVS 同步增删，step 期间 env 一直被锁住，agent 通过这里找到 env 的 RlAgentHub
+    static ref RL_AGENTS: RwLock<HashMap<String,Arc<RlAgentHub>>> = RwLock::new(HashMap::new());
+    /// 同上，订阅遥测时不需要等 env 的锁
+    static ref TELEMETRY: RwLock<HashMap<String,Arc<TelemetryHub>>> = RwLock::new(HashMap::new());
+    /// 同上，查询决策日志时不需要等 env 的锁
+    static ref AUDITS: RwLock<HashMap<String,Arc<AuditHub>>> = RwLock::new(HashMap::new());
     static ref HISTORY_CACHE: Cache<String,Arc<Records>> = Cache::new(100);
     static ref COLLECT_SEED_METRICS_LOCK :tokio::sync::Mutex<()>= tokio::sync::Mutex::new(());
 }
 
 pub struct ApiHandlerImpl;
 
+/// restore 和 fork 出来的 env 的编号，避免和 reset 的 env_id 冲突
+static DERIVED_ENV_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+fn insert_derived_env(sim_env: SimEnv, tag: &str) -> String {
+    let key = format!(
+        "{}.{}{}",
+        sim_env.help.config().str(),
+        tag,
+        DERIVED_ENV_SEQ.fetch_add(1, Ordering::SeqCst)
+    );
+    insert_sim_env(key.clone(), sim_env);
+    key
+}
+
+fn insert_sim_env(key: String, sim_env: SimEnv) {
+    RL_AGENTS.write().insert(key.clone(), sim_env.help.rl_agent().clone());
+    TELEMETRY.write().insert(key.clone(), sim_env.help.telemetry().clone());
+    AUDITS.write().insert(key.clone(), sim_env.help.audit().clone());
+    SIM_ENVS.write().insert(key, Arc::new(Mutex::new(sim_env)));
+}
+
+/// 从表中摘除，返回被摘除的 env
+pub fn remove_sim_envs(keys: &[String]) -> Vec<Arc<Mutex<SimEnv>>> {
+    let mut rl_agents = RL_AGENTS.write();
+    let mut telemetry = TELEMETRY.write();
+    let mut audits = AUDITS.write();
+    let mut sim_envs = SIM_ENVS.write();
+    keys.iter()
+        .filter_map(|key| {
+            rl_agents.remove(key);
+            telemetry.remove(key);
+            audits.remove(key);
+            sim_envs.remove(key)
+        })
+        .collect()
+}
+
+/// 取出 env 后立即释放全局读锁
+fn get_sim_env(key: &str) -> Option<Arc<Mutex<SimEnv>>> {
+    SIM_ENVS.read().get(key).cloned()
+}
+
+fn get_rl_agent(key: &str) -> Option<Arc<RlAgentHub>> {
+    RL_AGENTS.read().get(key).cloned()
+}
+
+/// 在阻塞线程池中 step 一个 env，返回 (score, state, stop, info)
+///
+/// info 为这次 step 的奖励分解(reward::RewardBreakdown)的 json
+async fn step_sim_env(
+    sim_env: Arc<Mutex<SimEnv>>,
+    action: ESActionWrapper
+) -> (f64, String, bool, String) {
+    tokio::task
+        ::spawn_blocking(move || {
+            let mut sim_env = sim_env.lock();
+            let (score, state) = sim_env.step_action(action);
+            sim_env.help.metric_record().as_ref().unwrap().flush(&sim_env);
+            let info = serde_json::to_string(&sim_env.help.reward().last).unwrap();
+            let stop = sim_env.current_frame() > sim_env.help.config().total_frames();
+            (score as f64, state, stop, info)
+        }).await
+        .unwrap()
+}
+
+/// 在阻塞线程池中让一个 env 只推进一帧，env 保留在表中，返回 (score, state, stop, info)
+///
+/// state 为这一帧之后的观测(spaces::observe)的 json
+async fn step_frame_sim_env(
+    sim_env: Arc<Mutex<SimEnv>>,
+    action: ESActionWrapper
+) -> (f64, String, bool, String) {
+    tokio::task
+        ::spawn_blocking(move || {
+            let mut sim_env = sim_env.lock();
+            let (score, stop) = sim_env.step_frame(action);
+            let state = serde_json::to_string(&spaces::observe(&*sim_env)).unwrap();
+            let info = serde_json::to_string(&sim_env.help.reward().last).unwrap();
+            (score as f64, state, stop, info)
+        }).await
+        .unwrap()
+}
+
 #[async_trait]
 impl ApiHandler for ApiHandlerImpl {
     async fn handle_get_network_topo(&self, _req: GetNetworkTopoReq) -> GetNetworkTopoResp {
         let env_ids_response = self.handle_get_env_id().await;
         if let GetEnvIdResp::Exist { env_id } = env_ids_response {
             if let Some(first_env_id) = env_id.first() {
-                let sim_envs = SIM_ENVS.read();
-                return match sim_envs.get(first_env_id) {
+                return match get_sim_env(first_env_id) {
                     Some(env_mutex) => {
                         let env = env_mutex.lock();
                         let node_count = env.node_cnt();
@@ -111,36 +242,39 @@ impl ApiHandler for ApiHandlerImpl {
     async fn handle_reset(&self, req: ResetReq) -> ResetResp {
         log::info!("Reset sim env");
         // 将req.config反序列化为Config类型的数据，并使用match表达式处理反序列化结果
-        match serde_json::from_value::<Config>(req.config.clone()) {
+        let config = serde_json::from_value::<Config>(req.config.clone()).map_err(|e| {
+            vec![ConfigError::new("config", e.to_string())]
+        });
+        match config.and_then(|config| config.check_valid().map(|_| config)) {
             Ok(config) => {
                 // 获取配置的标识键，并尝试获取或创建该SimEnv实例
                 let key = config.str();
-                {
-                    // 获取全局SIM_ENVS的写入锁
-                    let sim_envs = SIM_ENVS.read();
-
+                tokio::task::block_in_place(|| {
                     // 如果找到了已有的模拟环境实例，则获取其独占锁，以便更新模拟环境
-                    if let Some(sim_env) = sim_envs.get(&key) {
+                    if let Some(sim_env) = get_sim_env(&key) {
                         let mut sim_env = sim_env.lock();
                         // 调用模拟环境的帮助方法来记录指标，并刷新记录
                         sim_env.help.metric_record().as_ref().unwrap().flush(&sim_env);
-                        // 用新的配置创建一个新的模拟环境实例
+                        // 用新的配置创建一个新的模拟环境实例，遥测的订阅者继续收到新 env 的帧
+                        let telemetry = sim_env.help.telemetry().clone();
                         *sim_env = SimEnv::new(config);
+                        sim_env.help.set_telemetry(telemetry);
+                        RL_AGENTS.write().insert(key.clone(), sim_env.help.rl_agent().clone());
+                        AUDITS.write().insert(key.clone(), sim_env.help.audit().clone());
                     } else {
-                        // 释放读锁
-                        drop(sim_envs);
-                        // 获取写锁
-                        let mut sim_envs = SIM_ENVS.write();
+                        // 创建比较耗时，在全局写锁之外完成
                         // 向模拟环境映射中插入一个新的模拟环境实例
-                        sim_envs.insert(key.clone(), SimEnv::new(config).into());
+                        insert_sim_env(key.clone(), SimEnv::new(config));
                     }
-                }
+                });
                 ResetResp::Success { env_id: key }
             }
-            Err(e) => {
-                log::info!("Invalid config: {:?}", e);
+            Err(errors) => {
+                let msg = format!("Invalid config: {}", config_errors_str(&errors));
+                log::info!("{}", msg);
                 ResetResp::InvalidConfig {
-                    msg: format!("Invalid config: {}", e),
+                    msg,
+                    errors: serde_json::to_value(&errors).unwrap(),
                 }
             }
         }
@@ -150,54 +284,338 @@ impl ApiHandler for ApiHandlerImpl {
         let key = env_id;
         // log::info!("Step sim env");
 
-        // 获取全局SIM_ENVS的读取锁
-        let mut sim_envs = SIM_ENVS.try_write_until(
-            Instant::now() + Duration::from_secs(1)
-        ).unwrap();
-
         // 尝试获取指定env_id对应的SimEnv实例
-        let res = if let Some(sim_env) = sim_envs.get(&key) {
-            // 尝试获取该SimEnv实例的独占锁
-            let mut sim_env = sim_env.lock();
+        let Some(sim_env) = get_sim_env(&key) else {
+            let msg = format!("Sim env {key} not found, create new one by reset");
+            log::warn!("{}", msg);
+            return StepResp::EnvNotFound { msg };
+        };
 
-            // 调用SimEnv实例的step方法
-            let (score, state) = tokio::task::block_in_place(|| {
-                let res = sim_env.step(action as u32);
-                sim_env.help.metric_record().as_ref().unwrap().flush(&sim_env);
-                res
-            });
+        // 调用SimEnv实例的step方法
+        let (score, state, stop, info) = step_sim_env(
+            sim_env,
+            ESActionWrapper::Int(action as u32)
+        ).await;
+        remove_sim_envs(&[key]);
 
-            // insert your application logic here
-            // 根据步进操作的结果，返回StepResp::Success，其中包含得分、状态和停止标志，停止标志基于当前帧是否大于1000
-            StepResp::Success {
-                score: score as f64,
-                state,
-                stop: sim_env.current_frame() > 1000,
-                info: "".to_owned(),
+        // 根据步进操作的结果，返回StepResp::Success，其中包含得分、状态和停止标志
+        StepResp::Success {
+            score,
+            state,
+            stop,
+            info,
+        }
+    }
+
+    async fn handle_step_batch(
+        &self,
+        StepBatchReq { env_ids, actions }: StepBatchReq
+    ) -> StepBatchResp {
+        if env_ids.len() != actions.len() {
+            return StepBatchResp::InvalidReq {
+                msg: format!("{} env ids but {} actions", env_ids.len(), actions.len()),
+            };
+        }
+        let mut unique = env_ids.clone();
+        unique.sort();
+        unique.dedup();
+        if unique.len() != env_ids.len() {
+            return StepBatchResp::InvalidReq { msg: "duplicated env id".to_owned() };
+        }
+
+        let mut sim_envs = vec![];
+        let mut not_found = vec![];
+        for key in &env_ids {
+            match get_sim_env(key) {
+                Some(sim_env) => sim_envs.push(sim_env),
+                None => not_found.push(key.clone()),
+            }
+        }
+        if !not_found.is_empty() {
+            let msg = format!("Sim envs {:?} not found, create new one by reset", not_found);
+            log::warn!("{}", msg);
+            return StepBatchResp::EnvNotFound { msg };
+        }
+
+        // 每个 env 在各自的阻塞线程上并行推进一帧，env 保留在表中供后续 step
+        let results = futures::future::join_all(
+            sim_envs
+                .into_iter()
+                .zip(actions)
+                .map(|(sim_env, action)| {
+                    step_frame_sim_env(sim_env, ESActionWrapper::Int(action as u32))
+                })
+        ).await;
+
+        let mut resp = (vec![], vec![], vec![], vec![]);
+        for (score, state, stop, info) in results {
+            resp.0.push(state);
+            resp.1.push(score);
+            resp.2.push(stop);
+            resp.3.push(info);
+        }
+        StepBatchResp::Success {
+            states: resp.0,
+            scores: resp.1,
+            stops: resp.2,
+            infos: resp.3,
+        }
+    }
+
+    async fn handle_snapshot(&self, SnapshotReq { env_id, path }: SnapshotReq) -> SnapshotResp {
+        let Some(sim_env) = get_sim_env(&env_id) else {
+            return SnapshotResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        tokio::task::block_in_place(|| {
+            let sim_env = sim_env.lock();
+            // 返回相对 SNAPSHOT_DIR 的路径，可以直接传给 restore
+            let path = if path.is_empty() {
+                format!("{}.{}.json", env_id, sim_env.current_frame())
+            } else {
+                path
+            };
+            let res = snapshot_path(&path).and_then(|file| sim_env.snapshot()?.save(&file));
+            match res {
+                Ok(()) => SnapshotResp::Success { path },
+                Err(msg) => {
+                    log::warn!("snapshot {} failed: {}", env_id, msg);
+                    SnapshotResp::Failed { msg }
+                }
             }
+        })
+    }
+
+    async fn handle_restore(&self, RestoreReq { path }: RestoreReq) -> RestoreResp {
+        let res = tokio::task::block_in_place(|| {
+            let snap = SimEnvSnapshot::load(&snapshot_path(&path)?)?;
+            SimEnv::from_snapshot(snap)
+        });
+        match res {
+            Ok(sim_env) => RestoreResp::Success { env_id: insert_derived_env(sim_env, "restore") },
+            Err(msg) => {
+                log::warn!("restore {} failed: {}", path, msg);
+                RestoreResp::Failed { msg }
+            }
+        }
+    }
+
+    async fn handle_fork(&self, ForkReq { env_id, mech }: ForkReq) -> ForkResp {
+        let Some(sim_env) = get_sim_env(&env_id) else {
+            return ForkResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        let mech = if mech.is_null() {
+            None
         } else {
-            let msg = format!("Sim env {key} not found, create new one by reset");
+            match serde_json::from_value::<MechConfig>(mech) {
+                Ok(mech) => Some(mech),
+                Err(e) => {
+                    return ForkResp::Failed { msg: format!("Invalid mech config: {}", e) };
+                }
+            }
+        };
+        let res = tokio::task::block_in_place(|| {
+            let sim_env = sim_env.lock();
+            let config = mech.map(|mech| {
+                let mut config = sim_env.help.config().clone();
+                config.mech = mech;
+                config
+            });
+            sim_env.fork(config)
+        });
+        match res {
+            Ok(forked) => ForkResp::Success { env_id: insert_derived_env(forked, "fork") },
+            Err(msg) => {
+                log::warn!("fork {} failed: {}", env_id, msg);
+                ForkResp::Failed { msg }
+            }
+        }
+    }
+
+    async fn handle_spaces(&self, SpacesReq { env_id }: SpacesReq) -> SpacesResp {
+        let Some(sim_env) = get_sim_env(&env_id) else {
+            return SpacesResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        tokio::task
+            ::spawn_blocking(move || {
+                let sim_env = sim_env.lock();
+                SpacesResp::Success {
+                    version: SPACES_VERSION as i32,
+                    observation: serde_json::to_value(spaces::observation_space(&*sim_env)).unwrap(),
+                    action: serde_json::to_value(&sim_env.action_space).unwrap(),
+                }
+            }).await
+            .unwrap()
+    }
+
+    async fn handle_step_typed(
+        &self,
+        StepTypedReq { env_id, action }: StepTypedReq
+    ) -> StepTypedResp {
+        let Some(sim_env) = get_sim_env(&env_id) else {
+            let msg = format!("Sim env {env_id} not found, create new one by reset");
             log::warn!("{}", msg);
-            StepResp::EnvNotFound { msg }
+            return StepTypedResp::EnvNotFound { msg };
         };
-        sim_envs.remove(&key).unwrap();
-        res
+        let action = action
+            .iter()
+            .map(|v| *v as f32)
+            .collect::<Vec<_>>();
+        // 只推进一帧，env 保留在表中供后续 step
+        tokio::task
+            ::spawn_blocking(move || {
+                let mut sim_env = sim_env.lock();
+                let action = match sim_env.action_space.decode_action(&action) {
+                    Ok(action) => action,
+                    Err(msg) => {
+                        return StepTypedResp::InvalidAction { msg };
+                    }
+                };
+                let (score, stop) = sim_env.step_frame(action);
+                let info = serde_json::to_string(&sim_env.help.reward().last).unwrap();
+                StepTypedResp::Success {
+                    obs: spaces
+                        ::observe(&*sim_env)
+                        .into_iter()
+                        .map(|v| v as f64)
+                        .collect(),
+                    score: score as f64,
+                    stop,
+                    info,
+                }
+            }).await
+            .unwrap()
     }
 
     async fn handle_rl_step(&self, req: RlStepReq) -> RlStepResp {
         if req.action < 0 {
             return RlStepResp::Failed { msg: "action is invalid".to_owned() };
         }
-        let Some(rl) = RL_TARGET.as_ref() else {
+        let Some(rl_agent) = get_rl_agent(&req.env_id) else {
+            return RlStepResp::Failed { msg: format!("Sim env {} not found", req.env_id) };
+        };
+        let Some((state, score, stop)) = rl_agent.target_step(req.action as usize) else {
             return RlStepResp::Failed { msg: "rl target is not inited".to_owned() };
         };
-        let (state, score, stop) = rl.step(req.action as usize);
         RlStepResp::Success {
             state,
             score: score as f64,
             stop,
         }
     }
+
+    async fn handle_rl_attach(&self, RlAttachReq { env_id, points }: RlAttachReq) -> RlAttachResp {
+        let Some(rl_agent) = get_rl_agent(&env_id) else {
+            return RlAttachResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        let mut attach = HashSet::new();
+        for p in &points {
+            let Some(point) = ControlPoint::from_name(p) else {
+                return RlAttachResp::InvalidReq { msg: format!("unknown control point {}", p) };
+            };
+            attach.insert(point);
+        }
+        rl_agent.attach(attach);
+        RlAttachResp::Success { points }
+    }
+
+    async fn handle_rl_poll(&self, RlPollReq { env_id, wait_ms }: RlPollReq) -> RlPollResp {
+        let Some(rl_agent) = get_rl_agent(&env_id) else {
+            return RlPollResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        let wait = Duration::from_millis(wait_ms.max(0) as u64);
+        let Some(pending) = tokio::task::block_in_place(|| rl_agent.poll(wait)) else {
+            return RlPollResp::NoPending { msg: "no pending decision".to_owned() };
+        };
+        RlPollResp::Pending {
+            decision_id: pending.id as i32,
+            point: serde_json::to_value(pending.point).unwrap().as_str().unwrap().to_owned(),
+            fnid: pending.fnid as i32,
+            reqid: pending.reqid.map_or(-1, |v| v as i32),
+            default_choice: pending.default_choice as i32,
+            obs: pending.obs
+                .into_iter()
+                .map(|v| v as f64)
+                .collect(),
+            action_space: serde_json::to_value(&pending.action_space).unwrap(),
+        }
+    }
+
+    async fn handle_rl_act(
+        &self,
+        RlActReq { env_id, decision_id, action }: RlActReq
+    ) -> RlActResp {
+        let Some(rl_agent) = get_rl_agent(&env_id) else {
+            return RlActResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        let action = action
+            .iter()
+            .map(|v| *v as f32)
+            .collect::<Vec<_>>();
+        match rl_agent.act(decision_id as u64, &action) {
+            Ok(()) => RlActResp::Success { decision_id },
+            Err(msg) => RlActResp::InvalidAction { msg },
+        }
+    }
+
+    async fn handle_audit_log(
+        &self,
+        AuditLogReq { env_id, from_seq, limit, kind, fnid, reqid }: AuditLogReq
+    ) -> AuditLogResp {
+        let Some(audit) = AUDITS.read().get(&env_id).cloned() else {
+            return AuditLogResp::EnvNotFound { msg: format!("Sim env {env_id} not found") };
+        };
+        let kind = if kind.is_empty() {
+            None
+        } else {
+            let Some(kind) = DecisionKind::from_name(&kind) else {
+                return AuditLogResp::InvalidReq { msg: format!("unknown decision kind {}", kind) };
+            };
+            Some(kind)
+        };
+        let (records, next_seq) = audit.query(
+            &(AuditQuery {
+                from_seq: from_seq.max(0) as u64,
+                limit: limit.max(0) as usize,
+                kind,
+                fnid: (fnid >= 0).then_some(fnid as usize),
+                reqid: (reqid >= 0).then_some(reqid as usize),
+            })
+        );
+        AuditLogResp::Success {
+            records: serde_json::to_value(&records).unwrap(),
+            next_seq: next_seq as i32,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TelemetryQuery {
+    env_id: String,
+}
+
+/// SSE 推送 env 的逐帧遥测，每帧一条 frame 事件，数据为 telemetry::FrameTelemetry 的 json；
+/// env 被回收时结束
+async fn telemetry(
+    Query(TelemetryQuery { env_id }): Query<TelemetryQuery>
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let Some(hub) = TELEMETRY.read().get(&env_id).cloned() else {
+        return Err((StatusCode::NOT_FOUND, format!("Sim env {env_id} not found")));
+    };
+    let rx = hub.subscribe();
+    // 不持有 hub，env 被回收后发送端释放，流随之结束
+    drop(hub);
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        use tokio::sync::broadcast::error::RecvError;
+        let event = match rx.recv().await {
+            Ok(frame) => Event::default().event("frame").json_data(&*frame).unwrap(),
+            Err(RecvError::Lagged(n)) => Event::default().event("lagged").data(n.to_string()),
+            Err(RecvError::Closed) => {
+                return None;
+            }
+        };
+        Some((Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
 // async fn history() -> (StatusCode, Json<()>) {
@@ -321,7 +739,12 @@ async fn history_list() -> (StatusCode, Json<HistoryListResp>) {
 
     if let Ok(paths) = fs::read_dir("./records") {
         for path in paths {
-            resp.list.push(path.unwrap().file_name().into_string().unwrap());
+            let path = path.unwrap();
+            // records/columnar 等目录不是记录
+            if path.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            resp.list.push(path.file_name().into_string().unwrap());
         }
     }
 
@@ -419,3 +842,37 @@ async fn collect_seed_metrics() -> (StatusCode, Json<()>) {
 //     // with a status code of `201 Created`
 //     (StatusCode::OK, Json(resp))
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::{ get_sim_env, insert_sim_env, remove_sim_envs, ApiHandlerImpl };
+    use crate::apis::{ ApiHandler, StepBatchReq, StepBatchResp };
+    use crate::{ config::Config, sim_env::SimEnv };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_step_batch_twice() {
+        let env_ids = vec!["test_step_batch.a".to_owned(), "test_step_batch.b".to_owned()];
+        for key in &env_ids {
+            let mut config = Config::new_test();
+            config.total_frame = 1;
+            insert_sim_env(key.clone(), SimEnv::new(config));
+        }
+        let step = || ApiHandlerImpl.handle_step_batch(StepBatchReq {
+            env_ids: env_ids.clone(),
+            actions: vec![0, 0],
+        });
+
+        // 每次只推进一帧，env 一直留在表中
+        for (frame, stop) in [(1, false), (2, true)] {
+            let StepBatchResp::Success { stops, states, .. } = step().await else {
+                panic!("step_batch failed");
+            };
+            assert_eq!(stops, vec![stop, stop]);
+            assert_eq!(states.len(), 2);
+            for key in &env_ids {
+                assert_eq!(get_sim_env(key).unwrap().lock().current_frame(), frame);
+            }
+        }
+        remove_sim_envs(&env_ids);
+    }
+}
@@ -2,11 +2,13 @@ use crate::cache::no_evict::NoEvict;
 use crate::cache::InstanceCachePolicy;
 use crate::config::Config;
 use crate::with_env_sub::WithEnvHelp;
+use serde::{Deserialize, Serialize};
 use crate::{
     fn_dag::{EnvFnExt, FnContainer, FnContainerState, FnId, Func},
     mechanism::SimEnvObserve,
     request::ReqId,
     sim_env::SimEnv,
+    snapshot::NodeSnapshot,
     with_env_sub::WithEnvCore,
     NODE_CNT, NODE_LEFT_MEM_THRESHOLD, NODE_SCORE_CPU_WEIGHT, NODE_SCORE_MEM_WEIGHT,
 };
@@ -14,12 +16,12 @@ use std::ptr::NonNull;
 use std::{
     cell::{Ref, RefCell, RefMut},
     cmp::Ordering,
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
 };
 
 pub type NodeId = usize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NodeRscLimit {
     // 节点cpu上限
     pub cpu: f32,
@@ -46,7 +48,7 @@ pub struct Node {
     pending_tasks: RefCell<BTreeSet<(ReqId, FnId)>>,
 
     // 节点上已有的函数容器
-    pub fn_containers: RefCell<HashMap<FnId, FnContainer>>,
+    pub fn_containers: RefCell<BTreeMap<FnId, FnContainer>>,
 
     // 使用了的cpu
     pub cpu: f32,
@@ -101,10 +103,11 @@ impl Node {
             node_id,
             rsc_limit: NodeRscLimit {
                 // cpu: 1000.0,
-                cpu: 200.0,
+                // 每ms的计算量，换算到每帧
+                cpu: 200.0 * config.frame_duration_ms,
                 mem: 8000.0,
             },
-            fn_containers: HashMap::new().into(),
+            fn_containers: BTreeMap::new().into(),
             cpu: 0.0,
             mem: (0.0).into(),
             last_frame_cpu: 0.0,
@@ -144,6 +147,40 @@ impl Node {
         self.node_id
     }
 
+    // 导出节点的动态状态，用于环境快照
+    pub fn snapshot(&self) -> NodeSnapshot {
+        NodeSnapshot {
+            node_id: self.node_id,
+            rsc_limit: self.rsc_limit.clone(),
+            pending_tasks: self.pending_tasks.borrow().clone(),
+            fn_containers: self.fn_containers.borrow().clone(),
+            cpu: self.cpu,
+            mem: self.unready_mem(),
+            last_frame_cpu: self.last_frame_cpu,
+            last_frame_mem: self.last_frame_mem,
+            frame_run_count: self.frame_run_count,
+            cache_keys: self.instance_cache_policy.borrow().keys(),
+        }
+    }
+
+    // 从快照恢复动态状态，缓存策略按淘汰顺序重放 put
+    pub fn restore_snapshot(&mut self, snap: NodeSnapshot) {
+        assert_eq!(self.node_id, snap.node_id);
+        self.rsc_limit = snap.rsc_limit;
+        *self.pending_tasks.borrow_mut() = snap.pending_tasks;
+        *self.fn_containers.borrow_mut() = snap.fn_containers;
+        self.cpu = snap.cpu;
+        *self.mem.borrow_mut() = snap.mem;
+        self.last_frame_cpu = snap.last_frame_cpu;
+        self.last_frame_mem = snap.last_frame_mem;
+        self.frame_run_count = snap.frame_run_count;
+        let mut cache = self.instance_cache_policy.borrow_mut();
+        for fnid in snap.cache_keys {
+            let (evicted, ok) = cache.put(fnid, Box::new(|_| false));
+            assert!(evicted.is_none() && ok, "restore cache of node {} failed", self.node_id);
+        }
+    }
+
     // 比较两个节点的资源使用情况
     // pub enum Ordering {
     //     Less,
@@ -168,6 +205,43 @@ impl Node {
         self.pending_tasks.borrow().len()
     }
 
+    // 节点上还没算完的计算量：运行中任务的剩余计算量加上排队任务的全部计算量
+    pub fn remaining_calc(&self, env: &impl EnvFnExt) -> f32 {
+        let running: f32 = self
+            .fn_containers
+            .borrow()
+            .values()
+            .flat_map(|c| c.req_fn_state.values().map(|t| t.left_calc.max(0.0)))
+            .sum();
+        let pending: f32 = self
+            .pending_tasks
+            .borrow()
+            .iter()
+            .map(|&(_, fnid)| env.func(fnid).cpu)
+            .sum();
+        running + pending
+    }
+
+    // 返回节点上所有任务（待处理和正在运行）
+    pub fn tasks(&self) -> Vec<(ReqId, FnId)> {
+        let mut tasks = self.pending_tasks.borrow().iter().copied().collect::<Vec<_>>();
+        for (&fnid, c) in self.fn_containers.borrow().iter() {
+            tasks.extend(c.req_fn_state.keys().map(|&req_id| (req_id, fnid)));
+        }
+        tasks
+    }
+
+    // 移除待处理或正在运行的任务，任务不在节点上时返回 false
+    pub fn remove_task(&self, req_id: ReqId, fnid: FnId) -> bool {
+        if self.pending_tasks.borrow_mut().remove(&(req_id, fnid)) {
+            return true;
+        }
+        match self.container_mut(fnid) {
+            Some(mut c) => c.req_fn_state.remove(&req_id).is_some(),
+            None => false,
+        }
+    }
+
     // 返回节点上正在运行的任务数量
     pub fn running_task_cnt(&self) -> usize {
         self.fn_containers
@@ -451,7 +525,7 @@ impl SimEnv {
 
 impl EnvNodeExt for SimEnv {}
 impl EnvNodeExt for SimEnvObserve {}
-pub trait EnvNodeExt: WithEnvCore {
+pub trait EnvNodeExt: WithEnvCore + WithEnvHelp {
     // 返回节点数量
     fn node_cnt(&self) -> usize {
         self.core().nodes().len()
@@ -491,6 +565,11 @@ pub trait EnvNodeExt: WithEnvCore {
             _get_speed_btwn(n2, n1)
         }
     }
+    /// 获取节点间每帧能传输的数据量
+    /// - 数据量单位为 KB，1 MB/s = 1 KB/ms
+    fn node_get_trans_per_frame_btwn(&self, n1: NodeId, n2: NodeId) -> f32 {
+        self.node_get_speed_btwn(n1, n2) * self.help().config().frame_duration_ms
+    }
 
     //获取计算速度最慢的节点
     fn node_get_lowest(&self) -> NodeId {
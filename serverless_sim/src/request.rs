@@ -3,6 +3,7 @@ use std::{ cell::{ Ref, RefMut }, collections::{ BTreeMap, HashMap, HashSet }, t
 use daggy::petgraph::visit::Topo;
 
 use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     fn_dag::{DagId, EnvFnExt, FnDAG, FnId},
@@ -28,7 +29,7 @@ pub type ReqId = usize;
 //     pub fn_node: HashMap<FnId, NodeId>,
 // }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ReqFnMetric {
     pub ready_sche_time: Option<usize>,
     pub sche_time: Option<usize>, // sche_time maybe ahead of ready_sche_time
@@ -37,7 +38,18 @@ pub struct ReqFnMetric {
     pub fn_done_time: Option<usize>, // exec begin when data and cold start done
 }
 
-#[derive(Clone)]
+/// 请求的状态，完成和超时的请求都放在 done_requests 中
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReqStatus {
+    #[default]
+    Running,
+    Done,
+    /// 超时取消，见 timeout
+    TimedOut,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Request {
     /// 请求id
     pub req_id: ReqId,
@@ -78,9 +90,17 @@ pub struct Request {
     data_recv_time: Option<usize>,
 
     exe_time: Option<usize>,
+
+    #[serde(default)]
+    pub status: ReqStatus,
 }
 
 impl Request {
+    /// 超时取消的请求没有完成时间，不参与延迟类指标
+    pub fn timed_out(&self) -> bool {
+        self.status == ReqStatus::TimedOut
+    }
+
     fn fn_latency_unwrap(&self, f: FnId) -> usize {
         let fnmetric = self.fn_metric.get(&f).unwrap();
         fnmetric.fn_done_time.unwrap() - fnmetric.ready_sche_time.unwrap()
@@ -336,6 +356,7 @@ impl Request {
             wait_sche_time: None,
             data_recv_time: None,
             exe_time: None,
+            status: ReqStatus::Running,
         };
         // new.prepare_next_fn_2_bind_node(&env.dags.borrow()[dag_i].dag);
         // {
@@ -429,6 +450,15 @@ impl SimEnv {
     pub fn req_sim_gen_requests(&self) {
         let env = self;
 
+        // 事件驱动模式下，这一帧的请求已经在跳帧时生成过了
+        if *env.req_pregen_frame.borrow() == Some(env.core.current_frame()) {
+            *env.req_pregen_frame.borrow_mut() = None;
+            return;
+        }
+
+        // 等待准入的请求排在新请求前面
+        env.admission_retry_deferred();
+
         if env.core.current_frame() % REQUEST_GEN_FRAME_INTERVAL == 0 {
             let mut total_req_cnt = 0;
 
@@ -442,6 +472,8 @@ impl SimEnv {
                 else {
                     avg_frequency *= 0.3;
                 }
+                // 频率按每ms给出，换算到每帧
+                avg_frequency *= env.help.config().frame_duration_ms as f64;
                 // avg_frequency *= 100.0;
                 // avg_frequency *= 10.0;
                 let mut bind = self.help.dag_accumulate_call_frequency.borrow_mut();
@@ -454,9 +486,7 @@ impl SimEnv {
 
 
                 for _ in 0..req_cnt {
-                    let request = Request::new(env, *dag_i, env.core.current_frame());
-                    let req_id = request.req_id;
-                    env.core.requests_mut().insert(req_id, request);
+                    env.admission_submit(*dag_i);
                 }
             }
 
@@ -509,8 +539,12 @@ impl SimEnv {
     }
 
     pub fn on_request_done(&self, req_id: ReqId) {
-        let req = self.core.requests_mut().remove(&req_id).unwrap();
+        let mut req = self.core.requests_mut().remove(&req_id).unwrap();
+        req.status = ReqStatus::Done;
         self.help.metric_mut().add_done_request();
+        if let Some(record) = self.help.metric_record().as_ref() {
+            record.log_done_request(&req);
+        }
         self.core.done_requests_mut().push(req);
     }
 
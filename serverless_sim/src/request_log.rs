@@ -0,0 +1,144 @@
+//! 请求粒度的事件日志
+//!
+//! 请求完成后 `Request` 和 `ReqFnMetric` 中的时间点只用于计算平均指标，
+//! 打开 `Config::request_log` 后每个完成或超时的请求写一行 json 到
+//! `records/request_log/<record_name>.jsonl`，用于离线计算自定义指标或回放。
+//!
+//! 时间均为帧数，乘以 config.frame_duration_ms 得到 ms；未发生的时间点为 null
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fn_dag::{DagId, FnId},
+    node::NodeId,
+    request::{ReqId, ReqStatus, Request},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReqFnLog {
+    pub fn_id: FnId,
+    /// 被调度到的节点
+    pub node_id: Option<NodeId>,
+    /// 前驱函数都完成，可以调度
+    pub ready_sche_frame: Option<usize>,
+    pub sche_frame: Option<usize>,
+    pub cold_start_done_frame: Option<usize>,
+    pub data_recv_done_frame: Option<usize>,
+    pub done_frame: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReqLog {
+    pub req_id: ReqId,
+    pub dag_id: DagId,
+    pub begin_frame: usize,
+    pub end_frame: usize,
+    #[serde(default)]
+    pub status: ReqStatus,
+    /// 按函数 id 排序
+    pub fns: Vec<ReqFnLog>,
+}
+
+impl ReqLog {
+    pub fn new(req: &Request) -> Self {
+        let mut fnids = req
+            .fn_metric
+            .keys()
+            .chain(req.fn_node.keys())
+            .chain(req.done_fns.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        fnids.sort();
+        fnids.dedup();
+        let fns = fnids
+            .into_iter()
+            .map(|fn_id| {
+                let metric = req.fn_metric.get(&fn_id);
+                ReqFnLog {
+                    fn_id,
+                    node_id: req.fn_node.get(&fn_id).copied(),
+                    ready_sche_frame: metric.and_then(|m| m.ready_sche_time),
+                    sche_frame: metric.and_then(|m| m.sche_time),
+                    cold_start_done_frame: metric.and_then(|m| m.cold_start_done_time),
+                    data_recv_done_frame: metric.and_then(|m| m.data_recv_done_time),
+                    done_frame: metric
+                        .and_then(|m| m.fn_done_time)
+                        .or_else(|| req.done_fns.get(&fn_id).copied()),
+                }
+            })
+            .collect();
+        Self {
+            req_id: req.req_id,
+            dag_id: req.dag_i,
+            begin_frame: req.begin_frame,
+            end_frame: req.end_frame,
+            status: req.status,
+            fns,
+        }
+    }
+}
+
+pub struct RequestLogger {
+    pub path: String,
+    writer: BufWriter<File>,
+}
+
+impl RequestLogger {
+    pub fn new(record_name: &str) -> Result<Self, String> {
+        let dir = "records/request_log";
+        fs::create_dir_all(dir).map_err(|e| format!("create dir {} failed: {}", dir, e))?;
+        let path = format!("{}/{}.jsonl", dir, record_name);
+        let file = File::create(&path).map_err(|e| format!("create {} failed: {}", path, e))?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn log(&mut self, req: &Request) {
+        let line = serde_json::to_string(&ReqLog::new(req)).unwrap();
+        if let Err(e) = writeln!(self.writer, "{}", line) {
+            log::warn!("write request log {} failed: {}", self.path, e);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            log::warn!("flush request log {} failed: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReqLog;
+    use crate::{config::Config, sim_env::SimEnv};
+
+    #[test]
+    fn test_req_log_of_done_requests() {
+        let mut config = Config::new_test();
+        // 冷启动要 50~100 帧，留够时间让请求完成
+        config.total_frame = 300;
+        let mut env = SimEnv::new(config);
+        env.step(1);
+
+        let done = env.core.done_requests();
+        assert!(!done.is_empty());
+        for req in done.iter() {
+            let log = ReqLog::new(req);
+            assert_eq!(log.fns.len(), env.core.dags()[req.dag_i].dag_inner.node_count());
+            for f in &log.fns {
+                assert!(f.node_id.is_some());
+                let done_frame = f.done_frame.unwrap();
+                assert!(done_frame >= log.begin_frame && done_frame <= log.end_frame);
+            }
+            let line = serde_json::to_string(&log).unwrap();
+            assert_eq!(serde_json::from_str::<ReqLog>(&line).unwrap(), log);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! RL 训练用的可配置奖励
+//!
+//! 奖励是若干代价项的加权和取负，权重在 `Config::reward` 中配置:
+//! - latency: 区间内完成的请求的平均延迟(ms)
+//! - slo_violation: 区间内完成的请求中延迟超过 slo_ms 的比例，超时取消的请求算作违反
+//! - cost: 区间内增加的成本
+//! - cold_start: 区间内新建的容器数
+//! - waste: 区间内平均每帧空闲容器占用的内存
+//! - churn: 本次动作和上一次动作的 L1 距离
+//!
+//! 权重全为 0 时奖励恒为 0，和之前 step 返回的 score 一致。
+//! 每次 step 的原始项和加权项作为 json 放在 StepResp.info 中
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::ESActionWrapper,
+    config::ConfigError,
+    sim_env::SimEnv,
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RewardConfig {
+    pub latency: f32,
+    pub slo_violation: f32,
+    pub cost: f32,
+    pub cold_start: f32,
+    pub waste: f32,
+    pub churn: f32,
+    /// 请求延迟超过这个值(ms)算违反 SLO
+    pub slo_ms: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            latency: 0.0,
+            slo_violation: 0.0,
+            cost: 0.0,
+            cold_start: 0.0,
+            waste: 0.0,
+            churn: 0.0,
+            slo_ms: 1000.0,
+        }
+    }
+}
+
+impl RewardConfig {
+    /// 是否配置了任意一项权重
+    pub fn enabled(&self) -> bool {
+        self.weights().iter().any(|w| *w != 0.0)
+    }
+
+    fn weights(&self) -> [f32; 6] {
+        [self.latency, self.slo_violation, self.cost, self.cold_start, self.waste, self.churn]
+    }
+
+    pub fn check_valid(&self, errs: &mut Vec<ConfigError>) {
+        let names = ["latency", "slo_violation", "cost", "cold_start", "waste", "churn"];
+        for (name, w) in names.iter().zip(self.weights()) {
+            if !w.is_finite() {
+                errs.push(ConfigError::new(format!("reward.{}", name), format!("weight should be finite, got {}", w)));
+            }
+        }
+        if !(self.slo_ms.is_finite() && self.slo_ms > 0.0) {
+            errs.push(ConfigError::new("reward.slo_ms", format!("should be a positive number, got {}", self.slo_ms)));
+        }
+    }
+}
+
+/// 各项代价，越小越好
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RewardTerms {
+    pub latency: f32,
+    pub slo_violation: f32,
+    pub cost: f32,
+    pub cold_start: f32,
+    pub waste: f32,
+    pub churn: f32,
+}
+
+impl RewardTerms {
+    pub fn weighted(&self, conf: &RewardConfig) -> RewardTerms {
+        RewardTerms {
+            latency: self.latency * conf.latency,
+            slo_violation: self.slo_violation * conf.slo_violation,
+            cost: self.cost * conf.cost,
+            cold_start: self.cold_start * conf.cold_start,
+            waste: self.waste * conf.waste,
+            churn: self.churn * conf.churn,
+        }
+    }
+
+    pub fn sum(&self) -> f32 {
+        self.latency + self.slo_violation + self.cost + self.cold_start + self.waste + self.churn
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RewardBreakdown {
+    pub reward: f32,
+    pub terms: RewardTerms,
+    pub weighted: RewardTerms,
+}
+
+impl RewardBreakdown {
+    pub fn new(terms: RewardTerms, conf: &RewardConfig) -> Self {
+        let weighted = terms.weighted(conf);
+        // 避免权重为 0 时返回 -0.0
+        let reward = if conf.enabled() { -weighted.sum() } else { 0.0 };
+        Self { reward, terms, weighted }
+    }
+}
+
+/// 从仿真开始累计的逐帧统计
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RewardCounters {
+    pub frames: usize,
+    pub cold_starts: usize,
+    /// 每帧空闲容器内存之和
+    pub idle_mem: f32,
+}
+
+/// 区间开始时的状态，区间结束时和它做差
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RewardMark {
+    pub done_cnt: usize,
+    pub cost: f32,
+    pub counters: RewardCounters,
+}
+
+impl RewardMark {
+    pub fn of<E: WithEnvCore + WithEnvHelp>(env: &E) -> Self {
+        Self {
+            done_cnt: env.core().done_requests().len(),
+            cost: *env.help().cost(),
+            counters: env.help().reward().counters.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RewardTracker {
+    pub counters: RewardCounters,
+    /// 当前 step 开始时的状态
+    pub mark: RewardMark,
+    pub prev_action: Option<Vec<f32>>,
+    /// 上一次 step 的奖励
+    pub last: Option<RewardBreakdown>,
+}
+
+impl RewardTracker {
+    pub fn record_frames(&mut self, cnt: usize, cold_starts: usize, idle_mem: f32) {
+        self.counters.frames += cnt;
+        self.counters.cold_starts += cold_starts;
+        self.counters.idle_mem += idle_mem * (cnt as f32);
+    }
+}
+
+/// 从 mark 到当前的各项代价，不包含 churn
+pub fn interval_terms<E: WithEnvCore + WithEnvHelp>(env: &E, mark: &RewardMark) -> RewardTerms {
+    let config = env.help().config();
+    let done = env.core().done_requests();
+    let timed_out = done
+        .iter()
+        .skip(mark.done_cnt)
+        .filter(|req| req.timed_out())
+        .count();
+    let latencies = done
+        .iter()
+        .skip(mark.done_cnt)
+        .filter(|req| !req.timed_out())
+        .map(|req| config.frames_to_ms((req.end_frame - req.begin_frame) as f32))
+        .collect::<Vec<_>>();
+    let latency = if latencies.is_empty() {
+        0.0
+    } else {
+        latencies.iter().sum::<f32>() / (latencies.len() as f32)
+    };
+    let finished = latencies.len() + timed_out;
+    let slo_violation = if finished == 0 {
+        0.0
+    } else {
+        let violated = latencies
+            .iter()
+            .filter(|v| **v > config.reward.slo_ms)
+            .count();
+        ((violated + timed_out) as f32) / (finished as f32)
+    };
+
+    let now = env.help().reward().counters.clone();
+    let frames = now.frames - mark.counters.frames;
+    RewardTerms {
+        latency,
+        slo_violation,
+        cost: *env.help().cost() - mark.cost,
+        cold_start: (now.cold_starts - mark.counters.cold_starts) as f32,
+        waste: if frames == 0 {
+            0.0
+        } else {
+            (now.idle_mem - mark.counters.idle_mem) / (frames as f32)
+        },
+        churn: 0.0,
+    }
+}
+
+/// 两次动作的 L1 距离，维数不同时多出的部分按 0 补齐
+pub fn action_churn(prev: Option<&[f32]>, cur: &[f32]) -> f32 {
+    let Some(prev) = prev else {
+        return 0.0;
+    };
+    let len = prev.len().max(cur.len());
+    (0..len)
+        .map(|i| (prev.get(i).unwrap_or(&0.0) - cur.get(i).unwrap_or(&0.0)).abs())
+        .sum()
+}
+
+fn action_vec(action: &ESActionWrapper) -> Vec<f32> {
+    match action {
+        ESActionWrapper::Float(v) => vec![*v],
+        ESActionWrapper::Int(v) => vec![*v as f32],
+        ESActionWrapper::Vec(v) => v.clone(),
+    }
+}
+
+/// step 开始时调用，记录区间起点
+pub fn begin_step<E: WithEnvCore + WithEnvHelp>(env: &E) {
+    let mark = RewardMark::of(env);
+    env.help().reward_mut().mark = mark;
+}
+
+/// step 结束时调用，计算这次 step 的奖励并记下动作
+pub fn end_step<E: WithEnvCore + WithEnvHelp>(env: &E, action: &ESActionWrapper) -> RewardBreakdown {
+    let mark = env.help().reward().mark.clone();
+    let mut terms = interval_terms(env, &mark);
+    let action = action_vec(action);
+
+    let mut tracker = env.help().reward_mut();
+    terms.churn = action_churn(tracker.prev_action.as_deref(), &action);
+    let breakdown = RewardBreakdown::new(terms, &env.help().config().reward);
+    tracker.prev_action = Some(action);
+    tracker.last = Some(breakdown.clone());
+    breakdown
+}
+
+impl SimEnv {
+    /// 统计 cnt 帧的新建容器数和空闲容器内存，帧结束时调用
+    pub fn reward_record_frames(&self, cnt: usize) {
+        let cur_frame = self.current_frame();
+        let mut cold_starts = 0;
+        let mut idle_mem = 0.0;
+        for n in self.core.nodes().iter() {
+            for (_, c) in n.fn_containers.borrow().iter() {
+                if c.born_frame == cur_frame {
+                    cold_starts += 1;
+                }
+                if c.is_idle() {
+                    idle_mem += c.container_basic_mem(self);
+                }
+            }
+        }
+        self.help.reward_mut().record_frames(cnt, cold_starts, idle_mem);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{action_churn, RewardBreakdown, RewardConfig, RewardTerms};
+
+    #[test]
+    fn test_reward_weighting() {
+        let terms = RewardTerms {
+            latency: 100.0,
+            slo_violation: 0.5,
+            cost: 2.0,
+            cold_start: 3.0,
+            waste: 10.0,
+            churn: 1.0,
+        };
+        let off = RewardBreakdown::new(terms.clone(), &RewardConfig::default());
+        assert_eq!(off.reward, 0.0);
+
+        let conf = RewardConfig {
+            latency: 0.01,
+            cold_start: 1.0,
+            churn: 2.0,
+            ..Default::default()
+        };
+        let on = RewardBreakdown::new(terms, &conf);
+        assert_eq!(on.weighted.cold_start, 3.0);
+        assert_eq!(on.weighted.cost, 0.0);
+        assert!((on.reward + 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_action_churn() {
+        assert_eq!(action_churn(None, &[3.0]), 0.0);
+        assert_eq!(action_churn(Some(&[1.0]), &[3.0]), 2.0);
+        assert_eq!(action_churn(Some(&[1.0, 2.0]), &[0.5]), 2.5);
+    }
+}
@@ -1,11 +1,23 @@
 // 注册rl 调用目标。rl step 到来时，http调用注册的对象的step，
 // 传入action，传出score和state
+//
+// 每个 env 有自己的 RlAgentHub，放在 SimEnvHelperState 中随 SimEnvObserve 传给机制线程。
+// 除了整体 step 的 RlTarget 之外，调度、扩缩容的每一次决策都可以作为控制点：
+// agent 通过 attach 打开控制点后，机制线程做决策时暂停，把观测交给 agent 并等待它的动作，
+// 没有打开的控制点仍由内置算法决定
 
-use std::ptr::NonNull;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
-lazy_static! {
-    pub static ref RL_TARGET: Option<Box<dyn RlTarget>> = None;
-}
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::{actions::ESActionWrapper, fn_dag::FnId, request::ReqId, spaces::Space};
+
+/// agent 超过这个时间没有给出动作，使用内置算法的选择
+pub const DECISION_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub trait RlTarget: 'static + Send + Sync {
     // state, score, done
@@ -13,12 +25,211 @@ pub trait RlTarget: 'static + Send + Sync {
     fn set_stop(&self);
 }
 
-pub fn register_rl_target(rl_target: Box<dyn RlTarget>) {
-    unsafe {
-        let mut replace = NonNull::new(
-            &*RL_TARGET as *const _ as *mut Option<Box<dyn RlTarget>>
-        ).unwrap();
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlPoint {
+    /// 为一个 ScheCmd 选择节点，动作为节点 id
+    Sche,
+    /// scale_for_fn 给出的目标容器数，动作为容器数
+    ScaleNum,
+}
+
+impl ControlPoint {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sche" => Some(ControlPoint::Sche),
+            "scale_num" => Some(ControlPoint::ScaleNum),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PendingDecision {
+    pub id: u64,
+    pub point: ControlPoint,
+    pub fnid: FnId,
+    pub reqid: Option<ReqId>,
+    /// 内置算法的选择，供 agent 参考
+    pub default_choice: usize,
+    /// spaces::observe 的观测
+    pub obs: Vec<f32>,
+    pub action_space: Space,
+}
+
+struct DecisionSlot {
+    next_id: u64,
+    pending: Option<PendingDecision>,
+    action: Option<ESActionWrapper>,
+    // 机制线程等待 agent 的累计时间，不计入算法延迟
+    wait_ms: u64,
+}
+
+pub struct RlAgentHub {
+    target: Mutex<Option<Box<dyn RlTarget>>>,
+    points: Mutex<HashSet<ControlPoint>>,
+    slot: Mutex<DecisionSlot>,
+    cond: Condvar,
+}
+
+impl Default for RlAgentHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RlAgentHub {
+    pub fn new() -> Self {
+        Self {
+            target: Mutex::new(None),
+            points: Mutex::new(HashSet::new()),
+            slot: Mutex::new(DecisionSlot {
+                next_id: 0,
+                pending: None,
+                action: None,
+                wait_ms: 0,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    pub fn register_target(&self, target: Box<dyn RlTarget>) {
+        *self.target.lock() = Some(target);
+    }
+
+    /// 没有注册 RlTarget 时返回 None
+    pub fn target_step(&self, action: usize) -> Option<(Vec<f64>, f32, bool)> {
+        self.target.lock().as_ref().map(|t| t.step(action))
+    }
+
+    /// 仿真结束，通知 RlTarget，并让正在等待的决策使用内置算法的选择
+    pub fn set_stop(&self) {
+        if let Some(t) = self.target.lock().as_ref() {
+            t.set_stop();
+        }
+        self.points.lock().clear();
+        self.slot.lock().pending = None;
+        self.cond.notify_all();
+    }
+
+    /// 替换打开的控制点，传空表示全部关闭
+    pub fn attach(&self, points: HashSet<ControlPoint>) {
+        *self.points.lock() = points;
+    }
+
+    pub fn attached(&self, point: ControlPoint) -> bool {
+        self.points.lock().contains(&point)
+    }
+
+    /// 机制线程调用，阻塞直到 agent 给出动作；超时或仿真结束返回 None
+    pub fn decide(
+        &self,
+        point: ControlPoint,
+        fnid: FnId,
+        reqid: Option<ReqId>,
+        default_choice: usize,
+        obs: Vec<f32>,
+        action_space: Space,
+    ) -> Option<ESActionWrapper> {
+        let begin = Instant::now();
+        let deadline = begin + DECISION_TIMEOUT;
+        let mut slot = self.slot.lock();
+        slot.next_id += 1;
+        let id = slot.next_id;
+        slot.pending = Some(PendingDecision {
+            id,
+            point,
+            fnid,
+            reqid,
+            default_choice,
+            obs,
+            action_space,
+        });
+        slot.action = None;
+        self.cond.notify_all();
+
+        let res = loop {
+            if let Some(action) = slot.action.take() {
+                break Some(action);
+            }
+            if slot.pending.as_ref().map(|p| p.id) != Some(id) {
+                break None;
+            }
+            if self.cond.wait_until(&mut slot, deadline).timed_out() {
+                log::warn!("rl decision {} {:?} timeout, use default choice", id, point);
+                break slot.action.take();
+            }
+        };
+        if slot.pending.as_ref().map(|p| p.id) == Some(id) {
+            slot.pending = None;
+        }
+        slot.wait_ms += begin.elapsed().as_millis() as u64;
+        res
+    }
+
+    /// agent 调用，最多等待 wait 取出正在等待动作的决策
+    pub fn poll(&self, wait: Duration) -> Option<PendingDecision> {
+        let deadline = Instant::now() + wait;
+        let mut slot = self.slot.lock();
+        while slot.pending.is_none() || slot.action.is_some() {
+            if self.cond.wait_until(&mut slot, deadline).timed_out() {
+                break;
+            }
+        }
+        if slot.action.is_some() {
+            return None;
+        }
+        slot.pending.clone()
+    }
+
+    /// agent 调用，按决策的动作空间解码展平的动作
+    pub fn act(&self, id: u64, raw: &[f32]) -> Result<(), String> {
+        let mut slot = self.slot.lock();
+        let Some(pending) = slot.pending.as_ref().filter(|p| p.id == id) else {
+            return Err(format!("decision {} is not pending", id));
+        };
+        let action = pending.action_space.decode_action(raw)?;
+        slot.action = Some(action);
+        self.cond.notify_all();
+        Ok(())
+    }
+
+    /// 取出并清零等待 agent 的累计时间
+    pub fn take_wait_ms(&self) -> u64 {
+        std::mem::take(&mut self.slot.lock().wait_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc, time::Duration};
+
+    use super::{ControlPoint, RlAgentHub};
+    use crate::{actions::ESActionWrapper, spaces::Space};
+
+    #[test]
+    fn test_decision_roundtrip() {
+        let hub = Arc::new(RlAgentHub::new());
+        hub.attach([ControlPoint::Sche].into_iter().collect::<HashSet<_>>());
+        assert!(hub.attached(ControlPoint::Sche));
+        assert!(!hub.attached(ControlPoint::ScaleNum));
 
-        let _ = replace.as_mut().replace(rl_target);
+        let agent = {
+            let hub = hub.clone();
+            std::thread::spawn(move || {
+                let pending = loop {
+                    if let Some(p) = hub.poll(Duration::from_millis(100)) {
+                        break p;
+                    }
+                };
+                assert_eq!(pending.default_choice, 1);
+                assert!(hub.act(pending.id, &[5.0]).is_err());
+                hub.act(pending.id, &[2.0]).unwrap();
+            })
+        };
+        let action = hub.decide(ControlPoint::Sche, 0, Some(0), 1, vec![], Space::Discrete { n: 3 });
+        agent.join().unwrap();
+        assert_eq!(action, Some(ESActionWrapper::Int(2)));
+        assert!(hub.poll(Duration::from_millis(1)).is_none());
     }
 }
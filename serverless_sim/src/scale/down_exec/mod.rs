@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{self, AlgoEntry, AlgoInfo, ScaleDownExecEntry},
     config::Config,
     fn_dag::FnId,
     mechanism::{DownCmd, SimEnvObserve},
@@ -21,18 +22,15 @@ pub trait ScaleDownExec: Send {
     // fn scale_up(&mut self, sim_env: &SimEnv, fnid: FnId, scale_cnt: usize) -> usize;
 }
 
-pub fn new_scale_down_exec(c: &Config) -> Option<Box<dyn ScaleDownExec>> {
-    let es = &c.mech;
-    let (scale_down_exec_name, _scale_down_exec_attr) = es.scale_down_exec_conf();
+/// 注册的缩容执行器
+pub const ALGOS: &[ScaleDownExecEntry] = &[AlgoEntry {
+    info: AlgoInfo::simple("default", &[]),
+    new: |_| Box::new(DefaultScaleDownExec),
+}];
 
-    match &*scale_down_exec_name {
-        "default" => {
-            return Some(Box::new(DefaultScaleDownExec));
-        }
-        _ => {
-            return None;
-        }
-    }
+pub fn new_scale_down_exec(c: &Config) -> Option<Box<dyn ScaleDownExec>> {
+    let (scale_down_exec_name, scale_down_exec_attr) = c.mech.scale_down_exec_conf();
+    algo_registry::find(ALGOS, &scale_down_exec_name).map(|e| (e.new)(&scale_down_exec_attr))
 }
 
 pub struct DefaultScaleDownExec;
@@ -1,127 +1,178 @@
-// use crate::{
-//     actions::ESActionWrapper,
-//     config::Config,
-//     fn_dag::FnId,
-//     scale::down_exec::{ScaleDownExec, ScaleOption},
-//     sim_env::SimEnv,
-// };
-
-// use super::{
-//     down_filter::{CarefulScaleDownFilter, ScaleDownFilter},
-//     ScaleNum,
-// };
-
-// pub struct AIScaleNum {
-//     // hpa: HpaESScaler,
-//     pub scale_down_filter: Box<dyn ScaleDownFilter + Send>,
-//     fn_available_count: usize,
-// }
-
-// impl AIScaleNum {
-//     pub fn new() -> Self {
-//         Self {
-//             // hpa: HpaESScaler::new(),
-//             scale_down_filter: Box::new(CarefulScaleDownFilter::new()),
-//             fn_available_count: 0,
-//         }
-//     }
-// }
-
-// impl ScaleNum for AIScaleNum {
-//     fn fn_available_count(&self, fnid: FnId, env: &SimEnv) -> usize {
-//         self.fn_available_count
-//     }
-//     fn scale_for_fn(
-//         &mut self,
-//         env: &SimEnv,
-//         fnid: crate::fn_dag::FnId,
-//         metric: &crate::algos::ContainerMetric,
-//         action: &ESActionWrapper,
-//     ) -> (f32, bool) {
-//         let raw_action = (match action {
-//             ESActionWrapper::Int(raw_action) => *raw_action,
-//         }) as usize;
-//         // *env.hpa_action.borrow_mut() = self.hpa.action(env, fnid, metric);
-//         // *env.distance2hpa.borrow_mut() = raw_action.abs_diff(*env.hpa_action.borrow_mut());
-//         let mut desired_container_cnt = raw_action % 10;
-//         let container_cnt = env.fn_container_cnt(fnid);
-//         let mut score_trans = 0.0;
-
-//         // skip
-//         if raw_action == 11 {
-//             desired_container_cnt = container_cnt;
-//         }
-
-//         // Maybe there's bigger optimization space
-//         //
-//         // // no need to scale up
-//         // if metric.ready_2_schedule_fn_reqs.len() == 0 && desired_container_cnt > container_cnt {
-//         //     desired_container_cnt = container_cnt;
-//         //     score_trans -= 500.0;
-//         // }
-
-//         // can't scale down to 0
-//         if metric.ready_2_schedule_fn_reqs.len() != 0 && desired_container_cnt == 0 {
-//             // return -1000.0;
-//             desired_container_cnt = 1;
-//             score_trans -= 1000.0;
-//         }
-
-//         log::info!(
-//             "fnid: {}, desired_container_cnt: {}, total: {}",
-//             fnid,
-//             desired_container_cnt,
-//             env.core.fns().len()
-//         );
-
-//         let desired_container_cnt =
-//             self.scale_down_filter
-//                 .filter_desired(fnid, desired_container_cnt, container_cnt);
-
-//         if desired_container_cnt < container_cnt {
-//             // # scale down
-//             let scale = container_cnt - desired_container_cnt;
-
-//             env.mechanisms.scale_executor_mut().exec_scale_down(
-//                 env,
-//                 ScaleOption::new().for_spec_fn(fnid).with_scale_cnt(scale),
-//             );
-//         }
-//         // else if desired_container_cnt > container_cnt {
-//         //     // # scale up
-//         //     let scale = desired_container_cnt - container_cnt;
-//         //     env.scale_executor.borrow_mut().scale_up(env, fnid, scale);
-//         // }
-//         self.fn_available_count = desired_container_cnt;
-//         (score_trans, true)
-//     }
-// }
-
-// // /// return continue loop or not
-// // pub fn step_scale(
-// //     env: &SimEnv,
-// //     raw_action: &ESActionWrapper,
-// //     action_done: &mut bool,
-// //     action_score: &mut f32,
-// //     es_state: &mut ESState
-// // ) -> bool {
-// //     if *action_done {
-// //         // next action effect stage is prepared
-// //         return false;
-// //     }
-// //     log::info!("scale for fns");
-// //     *action_done = true;
-// //     let action = match raw_action {
-// //         // ESActionWrapper::Float(raw_action) => (*raw_action * 11.0) as u32,
-// //         ESActionWrapper::Int(raw_action) => *raw_action,
-// //     };
-// //     *action_score += step_scale_for_fns(
-// //         env,
-// //         action,
-// //         es_state.stage.as_scale_for_fns_mut().unwrap()
-// //     );
-// //     if !es_state.stage.as_scale_for_fns_mut().unwrap().prepare_next() {
-// //         es_state.trans_stage(env);
-// //     }
-// //     true
-// // }
+//! 由 RL agent 决定容器数的扩缩容
+//!
+//! 每次 step 传入的 action 就是 agent 的决策，不依赖其他全局状态，任何外部训练的策略都可以接入:
+//! - per_fn=false: 动作空间 Discrete(max_containers + 2)，所有函数用同一个动作
+//! - per_fn=true: 动作空间 MultiDiscrete，第 i 维是函数 i 的动作
+//!
+//! 动作取值 0..=max_containers 为目标容器数，max_containers + 1 表示保持当前容器数。
+//! 还有没调度的请求时不会缩到 0
+
+use serde::{Deserialize, Serialize};
+
+use super::ScaleNum;
+use crate::{
+    actions::ESActionWrapper,
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScaleNumEntry},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::SimEnvObserve,
+    spaces::Space,
+    with_env_sub::WithEnvHelp,
+};
+
+fn default_max_containers() -> usize {
+    10
+}
+
+/// 类型化配置 {"type": "ai", "max_containers": 10, "per_fn": true}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AiParams {
+    #[serde(default = "default_max_containers")]
+    pub max_containers: usize,
+    #[serde(default)]
+    pub per_fn: bool,
+}
+
+impl Default for AiParams {
+    fn default() -> Self {
+        Self {
+            max_containers: default_max_containers(),
+            per_fn: false,
+        }
+    }
+}
+
+impl AlgoParams for AiParams {
+    fn to_attr(&self) -> String {
+        format!("max_containers={},per_fn={}", self.max_containers, self.per_fn)
+    }
+}
+
+impl AiParams {
+    /// 旧格式参数 "max_containers=10,per_fn=true"，省略的项取默认值
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for (k, v) in parse_kv_attr("ai", arg)? {
+            let bad = |e: &dyn std::fmt::Display| format!("ai {} invalid {:?}: {}", k, v, e);
+            match k {
+                "max_containers" => {
+                    params.max_containers = v.parse().map_err(|e| bad(&e))?;
+                }
+                "per_fn" => {
+                    params.per_fn = v.parse().map_err(|e| bad(&e))?;
+                }
+                _ => {
+                    return Err(format!("ai arg unknown key {:?}, should be max_containers or per_fn", k));
+                }
+            }
+        }
+        if params.max_containers == 0 {
+            return Err("ai max_containers should be positive".to_owned());
+        }
+        Ok(params)
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+
+    /// 每个函数的动作取值个数
+    fn action_n(&self) -> usize {
+        self.max_containers + 2
+    }
+
+    /// 函数 fnid 的目标容器数，动作缺失或越界时保持当前容器数
+    pub fn decode(&self, action: &ESActionWrapper, fnid: FnId, current: usize) -> usize {
+        let raw = match action {
+            ESActionWrapper::Int(a) => Some(*a as usize),
+            ESActionWrapper::Float(a) => Some(a.round().max(0.0) as usize),
+            ESActionWrapper::Vec(v) => v.get(fnid).map(|a| a.round().max(0.0) as usize),
+        };
+        match raw {
+            Some(a) if a <= self.max_containers => a,
+            _ => current,
+        }
+    }
+}
+
+pub struct AIScaleNum {
+    params: AiParams,
+}
+
+impl AIScaleNum {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            params: AiParams::parse(arg).unwrap_or_else(|e| panic!("{}", e)),
+        }
+    }
+}
+
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "ai",
+        params: &[
+            ParamSpec {
+                name: "max_containers",
+                ty: "usize",
+                default: "10",
+                desc: "动作能指定的最大容器数，max_containers + 1 表示保持当前容器数",
+            },
+            ParamSpec {
+                name: "per_fn",
+                ty: "bool",
+                default: "false",
+                desc: "每个函数各一个动作(MultiDiscrete)，否则所有函数共用一个动作",
+            },
+        ],
+        mech_types: &["scale_sche_separated", "scale_sche_joint"],
+        parse_params: parse_params::<AiParams>,
+        check_attr: AiParams::check_arg,
+    },
+    new: |attr| Box::new(AIScaleNum::new(attr)),
+};
+
+impl ScaleNum for AIScaleNum {
+    fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, action: &ESActionWrapper) -> usize {
+        let mut desired = self.params.decode(action, fnid, env.fn_container_cnt(fnid));
+        // can't scale down to 0
+        if desired == 0 && env.help().mech_metric().fn_unsche_req_cnt(fnid) > 0 {
+            desired = 1;
+        }
+        desired
+    }
+
+    fn action_space(&self, fn_cnt: usize) -> Space {
+        if self.params.per_fn {
+            Space::MultiDiscrete { nvec: vec![self.params.action_n(); fn_cnt] }
+        } else {
+            Space::Discrete { n: self.params.action_n() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AIScaleNum, AiParams};
+    use crate::{actions::ESActionWrapper, algo_registry::AlgoParams, scale::num::ScaleNum, spaces::Space};
+
+    #[test]
+    fn test_ai_decode() {
+        let p = AiParams::parse("max_containers=4,per_fn=true").unwrap();
+        assert_eq!(AiParams::parse(&p.to_attr()).unwrap(), p);
+        assert!(AiParams::parse("max_containers=0").is_err());
+
+        assert_eq!(p.decode(&ESActionWrapper::Int(3), 7, 1), 3);
+        // max_containers + 1 保持当前
+        assert_eq!(p.decode(&ESActionWrapper::Int(5), 7, 1), 1);
+        let v = ESActionWrapper::Vec(vec![0.0, 4.0]);
+        assert_eq!(p.decode(&v, 0, 2), 0);
+        assert_eq!(p.decode(&v, 1, 2), 4);
+        // 没有这个函数的动作
+        assert_eq!(p.decode(&v, 2, 2), 2);
+
+        let space = AIScaleNum::new(&p.to_attr()).action_space(3);
+        assert_eq!(space, Space::MultiDiscrete { nvec: vec![6, 6, 6] });
+        assert_eq!(AIScaleNum::new("").action_space(3), Space::Discrete { n: 12 });
+    }
+}
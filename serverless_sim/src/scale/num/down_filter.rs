@@ -1,11 +1,66 @@
+//! 扩缩容目标的过滤器
+//!
+//! scale_num 给出的目标容器数依次经过选中的过滤器，顺序由 mech.filter 数组的顺序决定
+//! (见 `MechConfig::filter_chain`)，旧格式按 ALGOS 的顺序。每个过滤器最多选一次
+//! - careful_down: 目标不小于历史上所有缩容目标时才缩容
+//! - replicas: 每个函数的容器数限制在 [min, max]
+//! - rate_limit: 每次决策最多扩 max_up 个、缩 max_down 个
+//! - stabilize: 扩容取 up_window_ms 内目标的最小值，缩容取 down_window_ms 内目标的最大值
+//! - budget: 所有函数的容器总内存不超过 max_mem、总容器数不超过 max_containers，只限制扩容
+//! - zero_delay: 目标连续为 0 达到 delay_ms 后才缩到 0
+
 use std::collections::{HashMap, VecDeque};
 
-use crate::fn_dag::FnId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, FilterEntry, ParamSpec},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::SimEnvObserve,
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
 
 pub trait ScaleFilter: Send {
-    fn filter_desired(&mut self, fnid: FnId, desired: usize, current: usize) -> usize;
+    fn filter_desired(&mut self, env: &SimEnvObserve, fnid: FnId, desired: usize, current: usize) -> usize;
+
+    /// 快照时导出跨帧保留的内部状态，没有这类状态的算法不用实现
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    /// 恢复 save_state 导出的状态
+    fn load_state(&mut self, _state: Value) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// 解析 "k1=v1,k2=v2" 中的数值参数，set 返回 false 表示未知的键
+fn parse_num_attr<T: std::str::FromStr>(
+    algo: &str,
+    arg: &str,
+    mut set: impl FnMut(&str, T) -> bool
+) -> Result<(), String>
+    where T::Err: std::fmt::Display
+{
+    for (k, v) in parse_kv_attr(algo, arg)? {
+        let v = v.parse::<T>().map_err(|e| format!("{} {} invalid {:?}: {}", algo, k, v, e))?;
+        if !set(k, v) {
+            return Err(format!("{} arg unknown key {:?}", algo, k));
+        }
+    }
+    Ok(())
 }
 
+fn check_ms(algo: &str, name: &str, v: f32) -> Result<(), String> {
+    if !(v.is_finite() && v >= 0.0) {
+        return Err(format!("{} {} should be non-negative, got {}", algo, name, v));
+    }
+    Ok(())
+}
+
+#[derive(Default)]
 pub struct CarefulScaleDownFilter {
     history_desired_container_cnt: HashMap<FnId, VecDeque<usize>>,
 }
@@ -27,7 +82,7 @@ impl CarefulScaleDownFilter {
         let history = self
             .history_desired_container_cnt
             .entry(fnid)
-            .or_insert_with(|| VecDeque::new());
+            .or_default();
         history.push_back(desired);
         if history.len() > 100 {
             history.pop_front();
@@ -35,8 +90,32 @@ impl CarefulScaleDownFilter {
     }
 }
 
+pub const CAREFUL_DOWN_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo::simple("careful_down", &[]),
+    new: |_| Box::new(CarefulScaleDownFilter::new()),
+};
+
+/// 可选的过滤器，旧格式配置按这里的顺序依次作用
+pub const ALGOS: &[FilterEntry] = &[
+    CAREFUL_DOWN_ENTRY,
+    REPLICAS_ENTRY,
+    RATE_LIMIT_ENTRY,
+    STABILIZE_ENTRY,
+    BUDGET_ENTRY,
+    ZERO_DELAY_ENTRY,
+];
+
 impl ScaleFilter for CarefulScaleDownFilter {
-    fn filter_desired(&mut self, fnid: FnId, desired: usize, current: usize) -> usize {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.history_desired_container_cnt))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.history_desired_container_cnt = load_algo_state("careful_down", state)?;
+        Ok(())
+    }
+
+    fn filter_desired(&mut self, _env: &SimEnvObserve, fnid: FnId, desired: usize, current: usize) -> usize {
         // log::info!("do careful scale down filter");
         if desired < current {
             let ret = if self.smaller_than_history(fnid, desired) {
@@ -51,3 +130,484 @@ impl ScaleFilter for CarefulScaleDownFilter {
         }
     }
 }
+
+/// {"type": "replicas", "min": 1, "max": 10}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReplicasFilter {
+    #[serde(default)]
+    pub min: usize,
+    #[serde(default = "usize_max")]
+    pub max: usize,
+}
+
+fn usize_max() -> usize {
+    usize::MAX
+}
+
+impl AlgoParams for ReplicasFilter {
+    fn to_attr(&self) -> String {
+        format!("min={},max={}", self.min, self.max)
+    }
+}
+
+impl ReplicasFilter {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut f = Self { min: 0, max: usize::MAX };
+        parse_num_attr("replicas", arg, |k, v| {
+            match k {
+                "min" => f.min = v,
+                "max" => f.max = v,
+                _ => {
+                    return false;
+                }
+            }
+            true
+        })?;
+        if f.min > f.max {
+            return Err(format!("replicas min {} should not be larger than max {}", f.min, f.max));
+        }
+        Ok(f)
+    }
+
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+pub const REPLICAS_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "replicas",
+        params: &[
+            ParamSpec { name: "min", ty: "usize", default: "0", desc: "每个函数最少的容器数" },
+            ParamSpec { name: "max", ty: "usize", default: "不限", desc: "每个函数最多的容器数" },
+        ],
+        mech_types: &[],
+        parse_params: parse_params::<ReplicasFilter>,
+        check_attr: ReplicasFilter::check_arg,
+    },
+    new: |attr| Box::new(ReplicasFilter::parse(attr).unwrap_or_else(|e| panic!("{}", e))),
+};
+
+impl ScaleFilter for ReplicasFilter {
+    fn filter_desired(&mut self, _env: &SimEnvObserve, _fnid: FnId, desired: usize, _current: usize) -> usize {
+        desired.clamp(self.min, self.max)
+    }
+}
+
+/// {"type": "rate_limit", "max_up": 4, "max_down": 1}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitFilter {
+    #[serde(default = "usize_max")]
+    pub max_up: usize,
+    #[serde(default = "usize_max")]
+    pub max_down: usize,
+}
+
+impl AlgoParams for RateLimitFilter {
+    fn to_attr(&self) -> String {
+        format!("max_up={},max_down={}", self.max_up, self.max_down)
+    }
+}
+
+impl RateLimitFilter {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut f = Self { max_up: usize::MAX, max_down: usize::MAX };
+        parse_num_attr("rate_limit", arg, |k, v| {
+            match k {
+                "max_up" => f.max_up = v,
+                "max_down" => f.max_down = v,
+                _ => {
+                    return false;
+                }
+            }
+            true
+        })?;
+        Ok(f)
+    }
+
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+pub const RATE_LIMIT_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "rate_limit",
+        params: &[
+            ParamSpec { name: "max_up", ty: "usize", default: "不限", desc: "每次决策最多扩容的个数" },
+            ParamSpec { name: "max_down", ty: "usize", default: "不限", desc: "每次决策最多缩容的个数" },
+        ],
+        mech_types: &[],
+        parse_params: parse_params::<RateLimitFilter>,
+        check_attr: RateLimitFilter::check_arg,
+    },
+    new: |attr| Box::new(RateLimitFilter::parse(attr).unwrap_or_else(|e| panic!("{}", e))),
+};
+
+impl ScaleFilter for RateLimitFilter {
+    fn filter_desired(&mut self, _env: &SimEnvObserve, _fnid: FnId, desired: usize, current: usize) -> usize {
+        desired.clamp(current.saturating_sub(self.max_down), current.saturating_add(self.max_up))
+    }
+}
+
+/// {"type": "stabilize", "up_window_ms": 0, "down_window_ms": 1000}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StabilizeParams {
+    #[serde(default)]
+    pub up_window_ms: f32,
+    #[serde(default = "default_down_window_ms")]
+    pub down_window_ms: f32,
+}
+
+fn default_down_window_ms() -> f32 {
+    1000.0
+}
+
+impl AlgoParams for StabilizeParams {
+    fn to_attr(&self) -> String {
+        format!("up_window_ms={},down_window_ms={}", self.up_window_ms, self.down_window_ms)
+    }
+}
+
+impl StabilizeParams {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut p = Self { up_window_ms: 0.0, down_window_ms: default_down_window_ms() };
+        parse_num_attr("stabilize", arg, |k, v| {
+            match k {
+                "up_window_ms" => p.up_window_ms = v,
+                "down_window_ms" => p.down_window_ms = v,
+                _ => {
+                    return false;
+                }
+            }
+            true
+        })?;
+        check_ms("stabilize", "up_window_ms", p.up_window_ms)?;
+        check_ms("stabilize", "down_window_ms", p.down_window_ms)?;
+        Ok(p)
+    }
+
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+/// 类似 k8s HPA 的稳定窗口，目标抖动时不会反复扩缩
+pub struct StabilizeFilter {
+    params: StabilizeParams,
+    /// 每个函数最近的 (帧, 目标)
+    history: HashMap<FnId, VecDeque<(usize, usize)>>,
+}
+
+impl StabilizeFilter {
+    /// 记录这一帧的目标，返回 (上窗口内最小目标, 下窗口内最大目标)
+    fn record(&mut self, fnid: FnId, frame: usize, desired: usize, up_frames: usize, down_frames: usize) -> (usize, usize) {
+        let history = self.history.entry(fnid).or_default();
+        history.push_back((frame, desired));
+        let keep = up_frames.max(down_frames);
+        while history.front().is_some_and(|&(f, _)| f + keep < frame) {
+            history.pop_front();
+        }
+        let (mut up_min, mut down_max) = (desired, desired);
+        for &(f, d) in history.iter() {
+            if f + up_frames >= frame {
+                up_min = up_min.min(d);
+            }
+            if f + down_frames >= frame {
+                down_max = down_max.max(d);
+            }
+        }
+        (up_min, down_max)
+    }
+}
+
+pub const STABILIZE_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "stabilize",
+        params: &[
+            ParamSpec { name: "up_window_ms", ty: "f32", default: "0", desc: "扩容取这段时间内目标的最小值" },
+            ParamSpec {
+                name: "down_window_ms",
+                ty: "f32",
+                default: "1000",
+                desc: "缩容取这段时间内目标的最大值",
+            },
+        ],
+        mech_types: &[],
+        parse_params: parse_params::<StabilizeParams>,
+        check_attr: StabilizeParams::check_arg,
+    },
+    new: |attr| {
+        Box::new(StabilizeFilter {
+            params: StabilizeParams::parse(attr).unwrap_or_else(|e| panic!("{}", e)),
+            history: HashMap::new(),
+        })
+    },
+};
+
+impl ScaleFilter for StabilizeFilter {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.history))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.history = load_algo_state("stabilize", state)?;
+        Ok(())
+    }
+
+    fn filter_desired(&mut self, env: &SimEnvObserve, fnid: FnId, desired: usize, current: usize) -> usize {
+        let config = env.help().config();
+        let (up_frames, down_frames) = (
+            config.ms_to_frames(self.params.up_window_ms),
+            config.ms_to_frames(self.params.down_window_ms),
+        );
+        let (up_min, down_max) = self.record(fnid, env.core().current_frame(), desired, up_frames, down_frames);
+        if desired > current {
+            up_min.max(current)
+        } else if desired < current {
+            down_max.min(current)
+        } else {
+            desired
+        }
+    }
+}
+
+/// {"type": "budget", "max_mem": 8000, "max_containers": 50}，为 0 表示不限制
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetParams {
+    #[serde(default)]
+    pub max_mem: f32,
+    #[serde(default)]
+    pub max_containers: usize,
+}
+
+impl AlgoParams for BudgetParams {
+    fn to_attr(&self) -> String {
+        format!("max_mem={},max_containers={}", self.max_mem, self.max_containers)
+    }
+}
+
+impl BudgetParams {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut p = Self::default();
+        for (k, v) in parse_kv_attr("budget", arg)? {
+            let bad = |e: &dyn std::fmt::Display| format!("budget {} invalid {:?}: {}", k, v, e);
+            match k {
+                "max_mem" => {
+                    p.max_mem = v.parse().map_err(|e| bad(&e))?;
+                }
+                "max_containers" => {
+                    p.max_containers = v.parse().map_err(|e| bad(&e))?;
+                }
+                _ => {
+                    return Err(format!("budget arg unknown key {:?}, should be max_mem or max_containers", k));
+                }
+            }
+        }
+        check_ms("budget", "max_mem", p.max_mem)?;
+        Ok(p)
+    }
+
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+/// 容器内存按 `Func::container_mem` 估算。同一帧内先决策的函数按决策后的目标计入
+pub struct BudgetFilter {
+    params: BudgetParams,
+    /// 本帧已决策的函数的目标
+    decided: HashMap<FnId, usize>,
+    decided_frame: usize,
+}
+
+impl BudgetFilter {
+    /// 其他函数占用 (容器数, 内存)
+    fn others_usage(&self, env: &SimEnvObserve, fnid: FnId) -> (usize, f32) {
+        let (mut cnt, mut mem) = (0, 0.0);
+        for f in env.core().fns().iter().filter(|f| f.fn_id != fnid) {
+            let c = self.decided.get(&f.fn_id).copied().unwrap_or_else(|| env.fn_container_cnt(f.fn_id));
+            cnt += c;
+            mem += (c as f32) * f.container_mem();
+        }
+        (cnt, mem)
+    }
+}
+
+pub const BUDGET_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "budget",
+        params: &[
+            ParamSpec { name: "max_mem", ty: "f32", default: "0", desc: "所有函数容器的总内存上限，0 不限" },
+            ParamSpec { name: "max_containers", ty: "usize", default: "0", desc: "所有函数的总容器数上限，0 不限" },
+        ],
+        mech_types: &[],
+        parse_params: parse_params::<BudgetParams>,
+        check_attr: BudgetParams::check_arg,
+    },
+    new: |attr| {
+        Box::new(BudgetFilter {
+            params: BudgetParams::parse(attr).unwrap_or_else(|e| panic!("{}", e)),
+            decided: HashMap::new(),
+            decided_frame: 0,
+        })
+    },
+};
+
+impl ScaleFilter for BudgetFilter {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&(&self.decided, self.decided_frame)))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        (self.decided, self.decided_frame) = load_algo_state("budget", state)?;
+        Ok(())
+    }
+
+    fn filter_desired(&mut self, env: &SimEnvObserve, fnid: FnId, desired: usize, current: usize) -> usize {
+        if self.decided_frame != env.core().current_frame() {
+            self.decided_frame = env.core().current_frame();
+            self.decided.clear();
+        }
+        let (other_cnt, other_mem) = self.others_usage(env, fnid);
+        let mut allowed = usize::MAX;
+        if self.params.max_containers > 0 {
+            allowed = allowed.min(self.params.max_containers.saturating_sub(other_cnt));
+        }
+        let mem = env.func(fnid).container_mem();
+        if self.params.max_mem > 0.0 && mem > 0.0 {
+            allowed = allowed.min(((self.params.max_mem - other_mem).max(0.0) / mem).floor() as usize);
+        }
+        // 超出预算时不扩容，但也不强制缩容
+        let target = desired.min(allowed.max(current.min(desired)));
+        self.decided.insert(fnid, target);
+        target
+    }
+}
+
+/// {"type": "zero_delay", "delay_ms": 1000}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ZeroDelayParams {
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: f32,
+}
+
+fn default_delay_ms() -> f32 {
+    1000.0
+}
+
+impl AlgoParams for ZeroDelayParams {
+    fn to_attr(&self) -> String {
+        format!("delay_ms={}", self.delay_ms)
+    }
+}
+
+impl ZeroDelayParams {
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut p = Self { delay_ms: default_delay_ms() };
+        parse_num_attr("zero_delay", arg, |k, v| {
+            if k != "delay_ms" {
+                return false;
+            }
+            p.delay_ms = v;
+            true
+        })?;
+        check_ms("zero_delay", "delay_ms", p.delay_ms)?;
+        Ok(p)
+    }
+
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+pub struct ZeroDelayFilter {
+    params: ZeroDelayParams,
+    /// 目标开始连续为 0 的帧
+    zero_since: HashMap<FnId, usize>,
+}
+
+pub const ZERO_DELAY_ENTRY: FilterEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "zero_delay",
+        params: &[
+            ParamSpec { name: "delay_ms", ty: "f32", default: "1000", desc: "目标连续为 0 这么久后才缩到 0" },
+        ],
+        mech_types: &[],
+        parse_params: parse_params::<ZeroDelayParams>,
+        check_attr: ZeroDelayParams::check_arg,
+    },
+    new: |attr| {
+        Box::new(ZeroDelayFilter {
+            params: ZeroDelayParams::parse(attr).unwrap_or_else(|e| panic!("{}", e)),
+            zero_since: HashMap::new(),
+        })
+    },
+};
+
+impl ScaleFilter for ZeroDelayFilter {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.zero_since))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.zero_since = load_algo_state("zero_delay", state)?;
+        Ok(())
+    }
+
+    fn filter_desired(&mut self, env: &SimEnvObserve, fnid: FnId, desired: usize, current: usize) -> usize {
+        if desired > 0 || current == 0 {
+            self.zero_since.remove(&fnid);
+            return desired;
+        }
+        let frame = env.core().current_frame();
+        let since = *self.zero_since.entry(fnid).or_insert(frame);
+        if frame < since + env.help().config().ms_to_frames(self.params.delay_ms) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BudgetParams, RateLimitFilter, ReplicasFilter, StabilizeFilter, StabilizeParams, ZeroDelayParams};
+    use crate::algo_registry::AlgoParams;
+
+    #[test]
+    fn test_filter_params() {
+        let r = ReplicasFilter::parse("min=1,max=5").unwrap();
+        assert_eq!((r.min, r.max), (1, 5));
+        assert_eq!(ReplicasFilter::parse(&r.to_attr()).unwrap(), r);
+        assert_eq!(ReplicasFilter::parse("").unwrap().max, usize::MAX);
+        assert!(ReplicasFilter::parse("min=3,max=2").is_err());
+
+        let l = RateLimitFilter::parse("max_up=2").unwrap();
+        assert_eq!((l.max_up, l.max_down), (2, usize::MAX));
+        assert_eq!(RateLimitFilter::parse(&l.to_attr()).unwrap(), l);
+
+        assert!(StabilizeParams::parse("down_window_ms=-1").is_err());
+        assert!(BudgetParams::parse("max_cost=1").is_err());
+        assert!(ZeroDelayParams::parse("delay_ms=x").is_err());
+        assert_eq!(ZeroDelayParams::parse("delay_ms=20").unwrap().delay_ms, 20.0);
+    }
+
+    #[test]
+    fn test_stabilize_window() {
+        let mut f = StabilizeFilter {
+            params: StabilizeParams::parse("").unwrap(),
+            history: Default::default(),
+        };
+        // 上窗口 0 帧、下窗口 3 帧
+        assert_eq!(f.record(0, 0, 5, 0, 3), (5, 5));
+        assert_eq!(f.record(0, 1, 2, 0, 3), (2, 5));
+        assert_eq!(f.record(0, 4, 1, 0, 3), (1, 2));
+        assert_eq!(f.record(0, 8, 3, 0, 3), (3, 3));
+    }
+}
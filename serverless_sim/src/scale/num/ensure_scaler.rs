@@ -2,6 +2,7 @@
 use std::cell::{ RefCell };
 use std::collections::{ HashMap, VecDeque };
 
+use crate::algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry };
 use crate::fn_dag::EnvFnExt;
 use crate::mechanism::SimEnvObserve;
 use crate::node::EnvNodeExt;
@@ -20,6 +21,11 @@ impl EnsureScaleNum {
     }
 }
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("ensure_scaler", &["scale_sche_joint"]),
+    new: |_| Box::new(EnsureScaleNum::new()),
+};
+
 impl ScaleNum for EnsureScaleNum {
     fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, _action: &ESActionWrapper) -> usize {
         
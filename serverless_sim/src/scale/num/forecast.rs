@@ -0,0 +1,709 @@
+//! 基于到达预测的扩缩容
+//!
+//! 每个函数的到达数按 bucket_ms 分桶，桶结束时喂给该函数的预测器在线拟合:
+//! - ewma: 指数加权平均
+//! - holt_winters: 加性 Holt-Winters，周期为 season 个桶
+//! - arima: ARIMA(2,1,0) 带漂移项，在最近 history 个桶上最小二乘拟合
+//! - fourier: 常数加 2 阶傅里叶级数，周期为 season 个桶，在最近 history 个桶上最小二乘拟合
+//!
+//! 预测的时间范围为函数的冷启动时间加 lead_ms，取范围内预测到达率的最大值，
+//! 除以容器最近的平均处理速度得到期望容器数，让新容器在负载到来前启动完成
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ScaleNum;
+use crate::{
+    actions::ESActionWrapper,
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScaleNumEntry},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::SimEnvObserve,
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+pub const FORECAST_MODELS: &[&str] = &["ewma", "holt_winters", "arima", "fourier"];
+
+/// Holt-Winters 趋势和季节项的平滑系数
+const HW_BETA: f64 = 0.1;
+const HW_GAMMA: f64 = 0.1;
+/// fourier 的谐波阶数
+const FOURIER_ORDER: usize = 2;
+/// 最小二乘的岭系数，避免到达数不变时方程奇异
+const RIDGE: f64 = 1e-6;
+
+fn default_model() -> String {
+    "ewma".to_owned()
+}
+fn default_bucket_ms() -> f32 {
+    100.0
+}
+fn default_season() -> usize {
+    10
+}
+fn default_history() -> usize {
+    60
+}
+fn default_alpha() -> f64 {
+    0.5
+}
+
+/// 类型化配置 {"type": "forecast", "model": "holt_winters", "season": 10, "lead_ms": 50}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ForecastParams {
+    #[serde(default = "default_model")]
+    pub model: String,
+    /// 到达数分桶的时间
+    #[serde(default = "default_bucket_ms")]
+    pub bucket_ms: f32,
+    /// 在冷启动时间之外额外提前的时间
+    #[serde(default)]
+    pub lead_ms: f32,
+    /// holt_winters、fourier 的周期(桶数)
+    #[serde(default = "default_season")]
+    pub season: usize,
+    /// arima、fourier 拟合用的桶数
+    #[serde(default = "default_history")]
+    pub history: usize,
+    /// ewma、holt_winters 水平项的平滑系数
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+}
+
+impl Default for ForecastParams {
+    fn default() -> Self {
+        Self {
+            model: default_model(),
+            bucket_ms: default_bucket_ms(),
+            lead_ms: 0.0,
+            season: default_season(),
+            history: default_history(),
+            alpha: default_alpha(),
+        }
+    }
+}
+
+impl AlgoParams for ForecastParams {
+    fn to_attr(&self) -> String {
+        format!(
+            "model={},bucket_ms={},lead_ms={},season={},history={},alpha={}",
+            self.model,
+            self.bucket_ms,
+            self.lead_ms,
+            self.season,
+            self.history,
+            self.alpha
+        )
+    }
+}
+
+impl ForecastParams {
+    /// 旧格式参数 "model=fourier,season=24,history=96"，省略的项取默认值
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for (k, v) in parse_kv_attr("forecast", arg)? {
+            let bad = |e: &dyn std::fmt::Display| format!("forecast {} invalid {:?}: {}", k, v, e);
+            match k {
+                "model" => {
+                    if !FORECAST_MODELS.contains(&v) {
+                        return Err(format!("forecast model should be one of {:?}, got {:?}", FORECAST_MODELS, v));
+                    }
+                    params.model = v.to_owned();
+                }
+                "bucket_ms" => {
+                    params.bucket_ms = v.parse().map_err(|e| bad(&e))?;
+                }
+                "lead_ms" => {
+                    params.lead_ms = v.parse().map_err(|e| bad(&e))?;
+                }
+                "season" => {
+                    params.season = v.parse().map_err(|e| bad(&e))?;
+                }
+                "history" => {
+                    params.history = v.parse().map_err(|e| bad(&e))?;
+                }
+                "alpha" => {
+                    params.alpha = v.parse().map_err(|e| bad(&e))?;
+                }
+                _ => {
+                    return Err(format!("forecast arg unknown key {:?}", k));
+                }
+            }
+        }
+        if !(params.bucket_ms.is_finite() && params.bucket_ms > 0.0) {
+            return Err(format!("forecast bucket_ms should be positive, got {}", params.bucket_ms));
+        }
+        if !(params.lead_ms.is_finite() && params.lead_ms >= 0.0) {
+            return Err(format!("forecast lead_ms should be non-negative, got {}", params.lead_ms));
+        }
+        if params.season < 2 {
+            return Err("forecast season should be at least 2".to_owned());
+        }
+        if params.history < 2 * FOURIER_ORDER + 2 {
+            return Err(format!("forecast history should be at least {}", 2 * FOURIER_ORDER + 2));
+        }
+        if !(params.alpha > 0.0 && params.alpha <= 1.0) {
+            return Err(format!("forecast alpha should be in (0, 1], got {}", params.alpha));
+        }
+        Ok(params)
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+/// 按桶在线拟合的到达数预测器
+pub trait Forecaster: Send {
+    /// 一个桶结束，记录它的到达数
+    fn observe(&mut self, v: f64);
+    /// 预测之后第 steps 个桶的到达数，steps 从 1 开始
+    fn forecast(&self, steps: usize) -> f64;
+    /// 快照时导出拟合状态
+    fn save_state(&self) -> Value;
+    fn load_state(&mut self, state: Value) -> Result<(), String>;
+}
+
+pub fn new_forecaster(params: &ForecastParams) -> Box<dyn Forecaster> {
+    match &*params.model {
+        "holt_winters" => Box::new(HoltWinters::new(params.alpha, params.season)),
+        "arima" => Box::new(Arima::new(params.history)),
+        "fourier" => Box::new(Fourier::new(params.season, params.history)),
+        _ => Box::new(Ewma::new(params.alpha)),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Ewma {
+    alpha: f64,
+    level: Option<f64>,
+}
+
+impl Ewma {
+    pub fn new(alpha: f64) -> Self {
+        Self { alpha, level: None }
+    }
+}
+
+impl Forecaster for Ewma {
+    fn observe(&mut self, v: f64) {
+        self.level = Some(self.level.map_or(v, |l| self.alpha * v + (1.0 - self.alpha) * l));
+    }
+
+    fn forecast(&self, _steps: usize) -> f64 {
+        self.level.unwrap_or(0.0)
+    }
+
+    fn save_state(&self) -> Value {
+        save_algo_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        *self = load_algo_state("ewma", state)?;
+        Ok(())
+    }
+}
+
+/// 加性 Holt-Winters，第一个周期的平均值和偏差作为初始水平和季节项
+#[derive(Serialize, Deserialize)]
+pub struct HoltWinters {
+    alpha: f64,
+    level: f64,
+    trend: f64,
+    seasonals: Vec<f64>,
+    /// 第一个周期还没满时的到达数
+    init: Vec<f64>,
+    /// 已记录的桶数
+    n: usize,
+}
+
+impl HoltWinters {
+    pub fn new(alpha: f64, season: usize) -> Self {
+        Self {
+            alpha,
+            level: 0.0,
+            trend: 0.0,
+            seasonals: vec![0.0; season],
+            init: vec![],
+            n: 0,
+        }
+    }
+}
+
+impl Forecaster for HoltWinters {
+    fn observe(&mut self, v: f64) {
+        let m = self.seasonals.len();
+        if self.n < m {
+            self.init.push(v);
+            if self.init.len() == m {
+                self.level = self.init.iter().sum::<f64>() / (m as f64);
+                for (s, x) in self.seasonals.iter_mut().zip(self.init.iter()) {
+                    *s = x - self.level;
+                }
+            }
+        } else {
+            let i = self.n % m;
+            let last_level = self.level;
+            self.level = self.alpha * (v - self.seasonals[i]) + (1.0 - self.alpha) * (last_level + self.trend);
+            self.trend = HW_BETA * (self.level - last_level) + (1.0 - HW_BETA) * self.trend;
+            self.seasonals[i] = HW_GAMMA * (v - self.level) + (1.0 - HW_GAMMA) * self.seasonals[i];
+        }
+        self.n += 1;
+    }
+
+    fn forecast(&self, steps: usize) -> f64 {
+        let m = self.seasonals.len();
+        if self.n < m {
+            if self.init.is_empty() {
+                return 0.0;
+            }
+            return self.init.iter().sum::<f64>() / (self.init.len() as f64);
+        }
+        let i = (self.n + steps - 1) % m;
+        (self.level + (steps as f64) * self.trend + self.seasonals[i]).max(0.0)
+    }
+
+    fn save_state(&self) -> Value {
+        save_algo_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        *self = load_algo_state("holtwinters", state)?;
+        Ok(())
+    }
+}
+
+/// ARIMA(2,1,0) 带漂移项: d_t = c + φ1 d_{t-1} + φ2 d_{t-2}，d 为相邻桶的差分
+#[derive(Serialize, Deserialize)]
+pub struct Arima {
+    history: usize,
+    ys: VecDeque<f64>,
+    /// [c, φ1, φ2]，数据不够时为 None
+    coef: Option<Vec<f64>>,
+}
+
+impl Arima {
+    pub fn new(history: usize) -> Self {
+        Self {
+            history,
+            ys: VecDeque::new(),
+            coef: None,
+        }
+    }
+
+    fn fit(&mut self) {
+        let d = self.ys
+            .iter()
+            .zip(self.ys.iter().skip(1))
+            .map(|(a, b)| b - a)
+            .collect::<Vec<_>>();
+        if d.len() < 6 {
+            self.coef = None;
+            return;
+        }
+        let rows = (2..d.len()).map(|t| (vec![1.0, d[t - 1], d[t - 2]], d[t])).collect::<Vec<_>>();
+        self.coef = least_squares(&rows);
+    }
+}
+
+impl Forecaster for Arima {
+    fn observe(&mut self, v: f64) {
+        self.ys.push_back(v);
+        while self.ys.len() > self.history {
+            self.ys.pop_front();
+        }
+        self.fit();
+    }
+
+    fn forecast(&self, steps: usize) -> f64 {
+        let Some(&last) = self.ys.back() else {
+            return 0.0;
+        };
+        let Some(coef) = &self.coef else {
+            return last;
+        };
+        let n = self.ys.len();
+        let (mut d1, mut d2) = (self.ys[n - 1] - self.ys[n - 2], self.ys[n - 2] - self.ys[n - 3]);
+        let mut y = last;
+        for _ in 0..steps {
+            let d = coef[0] + coef[1] * d1 + coef[2] * d2;
+            y += d;
+            d2 = d1;
+            d1 = d;
+        }
+        y.max(0.0)
+    }
+
+    fn save_state(&self) -> Value {
+        save_algo_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        *self = load_algo_state("arima", state)?;
+        Ok(())
+    }
+}
+
+/// y_t = c + Σ_k (a_k cos(2πkt/m) + b_k sin(2πkt/m))，t 为从开始记录起的桶序号
+#[derive(Serialize, Deserialize)]
+pub struct Fourier {
+    season: usize,
+    history: usize,
+    ys: VecDeque<f64>,
+    n: usize,
+    coef: Option<Vec<f64>>,
+}
+
+impl Fourier {
+    pub fn new(season: usize, history: usize) -> Self {
+        Self {
+            season,
+            history,
+            ys: VecDeque::new(),
+            n: 0,
+            coef: None,
+        }
+    }
+
+    fn order(&self) -> usize {
+        FOURIER_ORDER.min(self.season / 2)
+    }
+
+    fn features(&self, t: usize) -> Vec<f64> {
+        let mut x = vec![1.0];
+        for k in 1..=self.order() {
+            let w = 2.0 * PI * (k as f64) * ((t % self.season) as f64) / (self.season as f64);
+            x.push(w.cos());
+            x.push(w.sin());
+        }
+        x
+    }
+
+    fn fit(&mut self) {
+        if self.ys.len() < 2 * self.order() + 2 {
+            self.coef = None;
+            return;
+        }
+        let begin = self.n - self.ys.len();
+        let rows = self.ys
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| (self.features(begin + i), y))
+            .collect::<Vec<_>>();
+        self.coef = least_squares(&rows);
+    }
+}
+
+impl Forecaster for Fourier {
+    fn observe(&mut self, v: f64) {
+        self.ys.push_back(v);
+        self.n += 1;
+        while self.ys.len() > self.history {
+            self.ys.pop_front();
+        }
+        self.fit();
+    }
+
+    fn forecast(&self, steps: usize) -> f64 {
+        let Some(coef) = &self.coef else {
+            if self.ys.is_empty() {
+                return 0.0;
+            }
+            return self.ys.iter().sum::<f64>() / (self.ys.len() as f64);
+        };
+        let x = self.features(self.n + steps - 1);
+        x.iter()
+            .zip(coef.iter())
+            .map(|(a, b)| a * b)
+            .sum::<f64>()
+            .max(0.0)
+    }
+
+    fn save_state(&self) -> Value {
+        save_algo_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        *self = load_algo_state("fourier", state)?;
+        Ok(())
+    }
+}
+
+/// 带岭项的最小二乘，rows 为 (特征, 目标)，解正规方程
+fn least_squares(rows: &[(Vec<f64>, f64)]) -> Option<Vec<f64>> {
+    let dim = rows.first()?.0.len();
+    let mut a = vec![vec![0.0; dim + 1]; dim];
+    for (x, y) in rows {
+        for i in 0..dim {
+            for j in 0..dim {
+                a[i][j] += x[i] * x[j];
+            }
+            a[i][dim] += x[i] * y;
+        }
+    }
+    for (i, row) in a.iter_mut().enumerate() {
+        row[i] += RIDGE * (rows.len() as f64);
+    }
+    // 列主元高斯消元
+    for col in 0..dim {
+        let pivot = (col..dim).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for (i, row) in a.iter_mut().enumerate() {
+            if i != col {
+                let f = row[col] / pivot_row[col];
+                for (v, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                    *v -= f * p;
+                }
+            }
+        }
+    }
+    Some((0..dim).map(|i| a[i][dim] / a[i][i]).collect())
+}
+
+/// 一个函数当前桶的到达数和预测器
+struct FnForecast {
+    forecaster: Box<dyn Forecaster>,
+    bucket: usize,
+    bucket_cnt: f64,
+}
+
+impl FnForecast {
+    /// 推进到 bucket，中间结束的桶依次记录，跳过的桶记为 0
+    fn advance(&mut self, bucket: usize) {
+        while self.bucket < bucket {
+            self.forecaster.observe(self.bucket_cnt);
+            self.bucket_cnt = 0.0;
+            self.bucket += 1;
+        }
+    }
+}
+
+pub struct ForecastScaleNum {
+    params: ForecastParams,
+    fns: HashMap<FnId, FnForecast>,
+    /// 已统计到达数的最后一帧
+    counted_frame: Option<usize>,
+}
+
+impl ForecastScaleNum {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            params: ForecastParams::parse(arg).unwrap_or_else(|e| panic!("{}", e)),
+            fns: HashMap::new(),
+            counted_frame: None,
+        }
+    }
+
+    fn fn_state(&mut self, fnid: FnId, bucket: usize) -> &mut FnForecast {
+        let params = &self.params;
+        let st = self.fns.entry(fnid).or_insert_with(|| FnForecast {
+            forecaster: new_forecaster(params),
+            bucket,
+            bucket_cnt: 0.0,
+        });
+        st.advance(bucket);
+        st
+    }
+
+    /// 统计上次之后到达的请求，每帧只统计一次
+    fn count_arrivals(&mut self, env: &SimEnvObserve, bucket: usize) {
+        let frame = env.core().current_frame();
+        if self.counted_frame.is_some_and(|f| f >= frame) {
+            return;
+        }
+        // 第一次只统计当前帧到达的
+        let from = self.counted_frame.map_or(frame, |f| f + 1);
+        self.counted_frame = Some(frame);
+        let mut arrivals: HashMap<FnId, usize> = HashMap::new();
+        for req in env.core().requests().values() {
+            if req.begin_frame < from || req.begin_frame > frame {
+                continue;
+            }
+            let dag = env.dag(req.dag_i);
+            let mut walker = dag.new_dag_walker();
+            while let Some(fngi) = walker.next(&dag.dag_inner) {
+                *arrivals.entry(dag.dag_inner[fngi]).or_default() += 1;
+            }
+        }
+        for (fnid, cnt) in arrivals {
+            self.fn_state(fnid, bucket).bucket_cnt += cnt as f64;
+        }
+    }
+}
+
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "forecast",
+        params: &[
+            ParamSpec {
+                name: "model",
+                ty: "ewma|holt_winters|arima|fourier",
+                default: "ewma",
+                desc: "到达数预测模型",
+            },
+            ParamSpec {
+                name: "bucket_ms",
+                ty: "f32",
+                default: "100",
+                desc: "到达数分桶的时间",
+            },
+            ParamSpec {
+                name: "lead_ms",
+                ty: "f32",
+                default: "0",
+                desc: "在冷启动时间之外额外提前扩容的时间",
+            },
+            ParamSpec {
+                name: "season",
+                ty: "usize",
+                default: "10",
+                desc: "holt_winters、fourier 的周期(桶数)",
+            },
+            ParamSpec {
+                name: "history",
+                ty: "usize",
+                default: "60",
+                desc: "arima、fourier 拟合用的桶数",
+            },
+            ParamSpec {
+                name: "alpha",
+                ty: "f64",
+                default: "0.5",
+                desc: "ewma、holt_winters 水平项的平滑系数",
+            },
+        ],
+        mech_types: &["scale_sche_separated", "scale_sche_joint"],
+        parse_params: parse_params::<ForecastParams>,
+        check_attr: ForecastParams::check_arg,
+    },
+    new: |attr| Box::new(ForecastScaleNum::new(attr)),
+};
+
+/// 快照中一个函数的状态: (预测器状态, 当前桶, 当前桶的到达数)
+type FnForecastState = (Value, usize, f64);
+
+impl ScaleNum for ForecastScaleNum {
+    fn save_state(&self) -> Result<Value, String> {
+        let fns = self.fns
+            .iter()
+            .map(|(&fnid, st)| (fnid, (st.forecaster.save_state(), st.bucket, st.bucket_cnt)))
+            .collect::<BTreeMap<FnId, FnForecastState>>();
+        Ok(save_algo_state(&(fns, self.counted_frame)))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        let (fns, counted_frame): (BTreeMap<FnId, FnForecastState>, Option<usize>) = load_algo_state(
+            "forecast",
+            state
+        )?;
+        self.fns.clear();
+        for (fnid, (forecaster_state, bucket, bucket_cnt)) in fns {
+            let mut forecaster = new_forecaster(&self.params);
+            forecaster.load_state(forecaster_state)?;
+            self.fns.insert(fnid, FnForecast { forecaster, bucket, bucket_cnt });
+        }
+        self.counted_frame = counted_frame;
+        Ok(())
+    }
+
+    fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, _action: &ESActionWrapper) -> usize {
+        let config = env.help().config();
+        let bucket_frames = config.ms_to_frames(self.params.bucket_ms).max(1);
+        let bucket = env.core().current_frame() / bucket_frames;
+        self.count_arrivals(env, bucket);
+
+        let lead_frames = env.func(fnid).cold_start_time + config.ms_to_frames(self.params.lead_ms);
+        let steps = lead_frames.div_ceil(bucket_frames).max(1);
+        let st = self.fn_state(fnid, bucket);
+        let predicted = (1..=steps)
+            .map(|s| st.forecaster.forecast(s))
+            .fold(0.0, f64::max);
+        let rate = (predicted as f32) / (bucket_frames as f32);
+
+        // 容器最近每帧平均处理的任务数
+        let (mut speed_sum, mut speed_cnt) = (0.0, 0);
+        env.fn_containers_for_each(fnid, |c| {
+            let speed = c.recent_handle_speed();
+            if speed > 0.0 {
+                speed_sum += speed;
+                speed_cnt += 1;
+            }
+        });
+        let mut desired = if speed_cnt > 0 {
+            (rate / (speed_sum / (speed_cnt as f32))).ceil() as usize
+        } else if rate > 0.0 {
+            // 还不知道处理速度时保持已有的容器
+            env.fn_container_cnt(fnid).max(1)
+        } else {
+            0
+        };
+
+        if desired == 0 && env.help().mech_metric().fn_unsche_req_cnt(fnid) > 0 {
+            desired = 1;
+        }
+        desired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arima, Ewma, ForecastParams, Forecaster, Fourier, HoltWinters};
+    use crate::algo_registry::AlgoParams;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_forecast_params() {
+        assert_eq!(ForecastParams::parse("").unwrap(), ForecastParams::default());
+        let p = ForecastParams::parse("model=fourier,season=24,history=96,lead_ms=50").unwrap();
+        assert_eq!((p.model.as_str(), p.season, p.history, p.lead_ms), ("fourier", 24, 96, 50.0));
+        assert_eq!(ForecastParams::parse(&p.to_attr()).unwrap(), p);
+        for bad in ["model=lstm", "bucket_ms=0", "season=1", "history=3", "alpha=0", "x=1", "ewma"] {
+            assert!(ForecastParams::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_forecasters() {
+        let mut ewma = Ewma::new(0.5);
+        assert_eq!(ewma.forecast(1), 0.0);
+        for v in [4.0, 8.0] {
+            ewma.observe(v);
+        }
+        assert_eq!(ewma.forecast(3), 6.0);
+
+        // 周期为 4 的季节性
+        let mut hw = HoltWinters::new(0.5, 4);
+        for _ in 0..3 {
+            for v in [1.0, 5.0, 1.0, 5.0] {
+                hw.observe(v);
+            }
+        }
+        assert!((hw.forecast(1) - 1.0).abs() < 1e-6);
+        assert!((hw.forecast(2) - 5.0).abs() < 1e-6);
+
+        // 线性增长，差分为常数
+        let mut arima = Arima::new(30);
+        for t in 0..20 {
+            arima.observe(2.0 * (t as f64));
+        }
+        assert!((arima.forecast(1) - 40.0).abs() < 0.1);
+        assert!((arima.forecast(3) - 44.0).abs() < 0.3);
+
+        let mut fourier = Fourier::new(8, 40);
+        let f = |t: usize| 3.0 + 2.0 * (2.0 * PI * (t as f64) / 8.0).sin();
+        for t in 0..32 {
+            fourier.observe(f(t));
+        }
+        assert!((fourier.forecast(1) - f(32)).abs() < 1e-3);
+        assert!((fourier.forecast(2) - f(33)).abs() < 1e-3);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry };
 use crate::mechanism::SimEnvObserve;
 
 use crate::with_env_sub::{WithEnvCore};
@@ -17,6 +18,11 @@ impl FpScaleNum {
     }
 }
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("full_placement", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(FpScaleNum::new()),
+};
+
 impl ScaleNum for FpScaleNum {
     fn scale_for_fn(&mut self, env: &SimEnvObserve, _fnid: FnId, _action: &ESActionWrapper) -> usize {
         env.core().nodes().len()
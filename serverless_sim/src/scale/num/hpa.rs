@@ -1,5 +1,6 @@
 use std::collections::{ HashMap };
 
+use crate::algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry };
 use crate::fn_dag::EnvFnExt;
 use crate::mechanism::SimEnvObserve;
 use crate::node::EnvNodeExt;
@@ -37,6 +38,11 @@ impl HpaScaleNum {
     }
 }
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("hpa", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(HpaScaleNum::new()),
+};
+
 impl ScaleNum for HpaScaleNum {
     fn scale_for_fn(
         &mut self,
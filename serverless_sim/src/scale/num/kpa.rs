@@ -0,0 +1,439 @@
+//! Knative Pod Autoscaler(KPA) 风格的扩缩容
+//!
+//! 每次决策采样一次函数的负载:
+//! - concurrency: `MechMetric::fn_recent_req_cnt`，即最近几帧还没完成该函数的请求数
+//! - rps: 各容器最近每帧完成的任务数(recent_handle_speed)之和，换算成每秒
+//!
+//! 稳定窗口(默认 60s)和恐慌窗口(默认 6s)内的采样平均值除以 target 得到期望容器数。
+//! 恐慌窗口的期望容器数达到就绪容器数的 panic_threshold 倍时进入恐慌模式，
+//! 一个稳定窗口内只扩不缩，之后回到按稳定窗口决策。
+//! 每次扩容最多到就绪容器数的 max_scale_up_rate 倍，缩容最少到 1/max_scale_down_rate，
+//! 负载降为 0 后再过 scale_to_zero_grace_ms 才缩到 0
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ScaleNum;
+use crate::{
+    actions::ESActionWrapper,
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScaleNumEntry},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::SimEnvObserve,
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+pub const KPA_METRICS: &[&str] = &["concurrency", "rps"];
+
+fn default_metric() -> String {
+    "concurrency".to_owned()
+}
+fn default_target() -> f32 {
+    1.0
+}
+fn default_stable_window_ms() -> f32 {
+    60000.0
+}
+fn default_panic_window_ms() -> f32 {
+    6000.0
+}
+fn default_panic_threshold() -> f32 {
+    2.0
+}
+fn default_scale_to_zero_grace_ms() -> f32 {
+    30000.0
+}
+fn default_max_scale_up_rate() -> f32 {
+    1000.0
+}
+fn default_max_scale_down_rate() -> f32 {
+    2.0
+}
+
+/// 类型化配置 {"type": "kpa", "metric": "concurrency", "target": 1.0, "panic_threshold": 2.0}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KpaParams {
+    #[serde(default = "default_metric")]
+    pub metric: String,
+    /// 每个容器的目标并发数或每秒请求数
+    #[serde(default = "default_target")]
+    pub target: f32,
+    #[serde(default = "default_stable_window_ms")]
+    pub stable_window_ms: f32,
+    #[serde(default = "default_panic_window_ms")]
+    pub panic_window_ms: f32,
+    #[serde(default = "default_panic_threshold")]
+    pub panic_threshold: f32,
+    #[serde(default = "default_scale_to_zero_grace_ms")]
+    pub scale_to_zero_grace_ms: f32,
+    #[serde(default = "default_max_scale_up_rate")]
+    pub max_scale_up_rate: f32,
+    #[serde(default = "default_max_scale_down_rate")]
+    pub max_scale_down_rate: f32,
+}
+
+impl Default for KpaParams {
+    fn default() -> Self {
+        Self {
+            metric: default_metric(),
+            target: default_target(),
+            stable_window_ms: default_stable_window_ms(),
+            panic_window_ms: default_panic_window_ms(),
+            panic_threshold: default_panic_threshold(),
+            scale_to_zero_grace_ms: default_scale_to_zero_grace_ms(),
+            max_scale_up_rate: default_max_scale_up_rate(),
+            max_scale_down_rate: default_max_scale_down_rate(),
+        }
+    }
+}
+
+impl AlgoParams for KpaParams {
+    fn to_attr(&self) -> String {
+        format!(
+            "metric={},target={},stable_window_ms={},panic_window_ms={},panic_threshold={},scale_to_zero_grace_ms={},max_scale_up_rate={},max_scale_down_rate={}",
+            self.metric,
+            self.target,
+            self.stable_window_ms,
+            self.panic_window_ms,
+            self.panic_threshold,
+            self.scale_to_zero_grace_ms,
+            self.max_scale_up_rate,
+            self.max_scale_down_rate
+        )
+    }
+}
+
+impl KpaParams {
+    /// 旧格式参数 "metric=rps,target=50,panic_threshold=2"，省略的项取默认值
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for (k, v) in parse_kv_attr("kpa", arg)? {
+            if k == "metric" {
+                if !KPA_METRICS.contains(&v) {
+                    return Err(format!("kpa metric should be one of {:?}, got {:?}", KPA_METRICS, v));
+                }
+                params.metric = v.to_owned();
+                continue;
+            }
+            let field = match k {
+                "target" => &mut params.target,
+                "stable_window_ms" => &mut params.stable_window_ms,
+                "panic_window_ms" => &mut params.panic_window_ms,
+                "panic_threshold" => &mut params.panic_threshold,
+                "scale_to_zero_grace_ms" => &mut params.scale_to_zero_grace_ms,
+                "max_scale_up_rate" => &mut params.max_scale_up_rate,
+                "max_scale_down_rate" => &mut params.max_scale_down_rate,
+                _ => {
+                    return Err(format!("kpa arg unknown key {:?}", k));
+                }
+            };
+            *field = v.parse().map_err(|e| format!("kpa {} invalid {:?}: {}", k, v, e))?;
+        }
+        for (name, v) in [
+            ("target", params.target),
+            ("stable_window_ms", params.stable_window_ms),
+            ("panic_window_ms", params.panic_window_ms),
+        ] {
+            if !(v.is_finite() && v > 0.0) {
+                return Err(format!("kpa {} should be positive, got {}", name, v));
+            }
+        }
+        if params.panic_window_ms > params.stable_window_ms {
+            return Err("kpa panic_window_ms should not be larger than stable_window_ms".to_owned());
+        }
+        for (name, v) in [
+            ("panic_threshold", params.panic_threshold),
+            ("max_scale_up_rate", params.max_scale_up_rate),
+            ("max_scale_down_rate", params.max_scale_down_rate),
+        ] {
+            if !(v.is_finite() && v >= 1.0) {
+                return Err(format!("kpa {} should be at least 1, got {}", name, v));
+            }
+        }
+        if !(params.scale_to_zero_grace_ms.is_finite() && params.scale_to_zero_grace_ms >= 0.0) {
+            return Err(
+                format!("kpa scale_to_zero_grace_ms should be non-negative, got {}", params.scale_to_zero_grace_ms)
+            );
+        }
+        Ok(params)
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+/// 最近 span 帧内的采样，维护和用于求平均
+#[derive(Serialize, Deserialize)]
+struct SampleWindow {
+    span: usize,
+    samples: VecDeque<(usize, f32)>,
+    sum: f32,
+}
+
+impl SampleWindow {
+    fn new(span: usize) -> Self {
+        Self {
+            span,
+            samples: VecDeque::new(),
+            sum: 0.0,
+        }
+    }
+
+    /// 同一帧重复采样时只保留最后一次
+    fn push(&mut self, frame: usize, v: f32) {
+        if let Some(&(f, old)) = self.samples.back() {
+            if f == frame {
+                self.sum -= old;
+                self.samples.pop_back();
+            }
+        }
+        self.samples.push_back((frame, v));
+        self.sum += v;
+        while let Some(&(f, old)) = self.samples.front() {
+            if f + self.span > frame {
+                break;
+            }
+            self.sum -= old;
+            self.samples.pop_front();
+        }
+    }
+
+    fn avg(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        (self.sum / (self.samples.len() as f32)).max(0.0)
+    }
+}
+
+/// 单个函数的决策状态，窗口和宽限期都以帧为单位
+#[derive(Serialize, Deserialize)]
+struct KpaFnState {
+    stable: SampleWindow,
+    panic: SampleWindow,
+    /// 恐慌模式持续到这一帧(不含)
+    panic_until: Option<usize>,
+    /// 恐慌模式中决策过的最大容器数
+    panic_max: usize,
+    /// 最近一次负载不为 0 的帧
+    last_active: usize,
+}
+
+impl KpaFnState {
+    fn new(stable_frames: usize, panic_frames: usize) -> Self {
+        Self {
+            stable: SampleWindow::new(stable_frames),
+            panic: SampleWindow::new(panic_frames),
+            panic_until: None,
+            panic_max: 0,
+            last_active: 0,
+        }
+    }
+
+    /// 记录一次采样，返回期望的容器数，ready 为就绪的容器数
+    fn decide(&mut self, params: &KpaParams, frame: usize, grace_frames: usize, ready: usize, observed: f32) -> usize {
+        self.stable.push(frame, observed);
+        self.panic.push(frame, observed);
+        let (stable_avg, panic_avg) = (self.stable.avg(), self.panic.avg());
+        if stable_avg > 0.0 || panic_avg > 0.0 {
+            self.last_active = frame;
+        }
+
+        let ready_f = ready.max(1) as f32;
+        let max_up = (params.max_scale_up_rate * ready_f).ceil() as usize;
+        let max_down = ((ready as f32) / params.max_scale_down_rate).floor() as usize;
+        let clamp = |want: f32| (want.ceil() as usize).min(max_up).max(max_down);
+        let stable_want = clamp(stable_avg / params.target);
+        let panic_want = clamp(panic_avg / params.target);
+
+        if panic_avg / params.target / ready_f >= params.panic_threshold {
+            self.panic_until = Some(frame + self.stable.span);
+        }
+        let mut desired = if self.panic_until.is_some_and(|until| frame < until) {
+            // 恐慌模式只扩不缩
+            self.panic_max = self.panic_max.max(panic_want).max(ready);
+            self.panic_max
+        } else {
+            self.panic_until = None;
+            self.panic_max = 0;
+            stable_want
+        };
+
+        if desired == 0 && ready > 0 && frame < self.last_active + grace_frames {
+            desired = 1;
+        }
+        desired
+    }
+}
+
+pub struct KpaScaleNum {
+    params: KpaParams,
+    fn_states: HashMap<FnId, KpaFnState>,
+}
+
+impl KpaScaleNum {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            params: KpaParams::parse(arg).unwrap_or_else(|e| panic!("{}", e)),
+            fn_states: HashMap::new(),
+        }
+    }
+
+    fn observe(&self, env: &SimEnvObserve, fnid: FnId) -> f32 {
+        if self.params.metric == "rps" {
+            let mut per_frame = 0.0;
+            env.fn_containers_for_each(fnid, |c| {
+                per_frame += c.recent_handle_speed();
+            });
+            per_frame * 1000.0 / env.help().config().frame_duration_ms
+        } else {
+            env.help().mech_metric().fn_recent_req_cnt(fnid)
+        }
+    }
+}
+
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "kpa",
+        params: &[
+            ParamSpec {
+                name: "metric",
+                ty: "concurrency|rps",
+                default: "concurrency",
+                desc: "按并发数还是每秒请求数扩缩容",
+            },
+            ParamSpec {
+                name: "target",
+                ty: "f32",
+                default: "1",
+                desc: "每个容器的目标并发数或每秒请求数",
+            },
+            ParamSpec {
+                name: "stable_window_ms",
+                ty: "f32",
+                default: "60000",
+                desc: "稳定窗口，也是恐慌模式的持续时间",
+            },
+            ParamSpec {
+                name: "panic_window_ms",
+                ty: "f32",
+                default: "6000",
+                desc: "恐慌窗口，不大于稳定窗口",
+            },
+            ParamSpec {
+                name: "panic_threshold",
+                ty: "f32",
+                default: "2",
+                desc: "恐慌窗口的期望容器数达到就绪容器数的这个倍数时进入恐慌模式",
+            },
+            ParamSpec {
+                name: "scale_to_zero_grace_ms",
+                ty: "f32",
+                default: "30000",
+                desc: "负载降为 0 后保留最后一个容器的时间",
+            },
+            ParamSpec {
+                name: "max_scale_up_rate",
+                ty: "f32",
+                default: "1000",
+                desc: "一次最多扩到就绪容器数的倍数",
+            },
+            ParamSpec {
+                name: "max_scale_down_rate",
+                ty: "f32",
+                default: "2",
+                desc: "一次最多缩到就绪容器数的几分之一",
+            },
+        ],
+        mech_types: &["scale_sche_separated", "scale_sche_joint"],
+        parse_params: parse_params::<KpaParams>,
+        check_attr: KpaParams::check_arg,
+    },
+    new: |attr| Box::new(KpaScaleNum::new(attr)),
+};
+
+impl ScaleNum for KpaScaleNum {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.fn_states))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.fn_states = load_algo_state("kpa", state)?;
+        Ok(())
+    }
+
+    fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, _action: &ESActionWrapper) -> usize {
+        let config = env.help().config();
+        let frame = env.core().current_frame();
+        let observed = self.observe(env, fnid);
+        let mut ready = 0;
+        env.fn_containers_for_each(fnid, |c| {
+            if c.state().is_running() {
+                ready += 1;
+            }
+        });
+        let (stable_frames, panic_frames) = (
+            config.ms_to_frames(self.params.stable_window_ms),
+            config.ms_to_frames(self.params.panic_window_ms),
+        );
+        let grace_frames = config.ms_to_frames(self.params.scale_to_zero_grace_ms);
+        let state = self.fn_states
+            .entry(fnid)
+            .or_insert_with(|| KpaFnState::new(stable_frames, panic_frames));
+        let mut desired = state.decide(&self.params, frame, grace_frames, ready, observed);
+
+        // 从 0 激活: 还有没调度的请求时至少保留一个
+        if desired == 0 && env.help().mech_metric().fn_unsche_req_cnt(fnid) > 0 {
+            desired = 1;
+        }
+        desired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KpaFnState, KpaParams};
+    use crate::algo_registry::AlgoParams;
+
+    #[test]
+    fn test_kpa_params() {
+        assert_eq!(KpaParams::parse("").unwrap(), KpaParams::default());
+        let p = KpaParams::parse("metric=rps,target=50,panic_threshold=3").unwrap();
+        assert_eq!((p.metric.as_str(), p.target, p.panic_threshold), ("rps", 50.0, 3.0));
+        assert_eq!(KpaParams::parse(&p.to_attr()).unwrap(), p);
+        for bad in ["metric=cpu", "target=0", "panic_window_ms=70000", "max_scale_down_rate=0.5", "x=1", "rps"] {
+            assert!(KpaParams::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_kpa_decide() {
+        let params = KpaParams {
+            target: 1.0,
+            ..Default::default()
+        };
+        // 稳定窗口 10 帧，恐慌窗口 2 帧，宽限 5 帧
+        let mut st = KpaFnState::new(10, 2);
+        for f in 0..10 {
+            assert_eq!(st.decide(&params, f, 5, 4, 4.0), 4);
+        }
+        // 突发: 恐慌窗口平均 6，达到就绪数 4 的 1.5 倍不到 2 倍，按稳定窗口扩
+        assert_eq!(st.decide(&params, 10, 5, 4, 8.0), 5);
+        // 恐慌窗口平均 14，进入恐慌模式直接扩到 14
+        assert_eq!(st.decide(&params, 11, 5, 4, 20.0), 14);
+        // 恐慌模式中负载下降也不缩
+        assert_eq!(st.decide(&params, 13, 5, 14, 0.0), 14);
+        // 一个稳定窗口后退出恐慌模式，一次最多缩到一半
+        assert_eq!(st.decide(&params, 22, 5, 14, 0.0), 7);
+        // 负载为 0 的宽限期内保留一个容器
+        let mut st = KpaFnState::new(2, 1);
+        assert_eq!(st.decide(&params, 0, 5, 1, 1.0), 1);
+        assert_eq!(st.decide(&params, 3, 5, 1, 0.0), 1);
+        assert_eq!(st.decide(&params, 5, 5, 1, 0.0), 0);
+    }
+}
@@ -5,6 +5,7 @@ use super::{
 };
 use crate::{
     actions::ESActionWrapper,fn_dag::FnId, node::EnvNodeExt, mechanism::SimEnvObserve, with_env_sub::{WithEnvHelp, WithEnvCore},
+    algo_registry::{AlgoEntry, AlgoInfo, ScaleNumEntry},
 };
 
 pub struct LassScaleNum {
@@ -25,6 +26,11 @@ impl LassScaleNum {
 
 // unsafe impl Send for LassEFScaler {}
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("lass", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(LassScaleNum::new()),
+};
+
 impl ScaleNum for LassScaleNum {
     fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, _action: &ESActionWrapper) -> usize {
         // 请求时间=请求数/(当前容器数(cc)*每个容器请求处理速率(r/t))
@@ -1,6 +1,8 @@
 pub mod ai;
 pub mod down_filter;
+pub mod forecast;
 pub mod hpa;
+pub mod kpa;
 pub mod lass;
 pub mod no;
 pub mod temp_scaler;
@@ -10,63 +12,55 @@ pub mod ensure_scaler;
 
 use crate::{
     actions::ESActionWrapper,
+    algo_registry::{ self, ScaleNumEntry },
     config::Config,
     fn_dag::{ FnId },
     mechanism::SimEnvObserve,
     sim_env::SimEnv,
+    spaces::Space,
 };
-
-use self::{
-    hpa::HpaScaleNum,
-    lass::LassScaleNum,
-    no::NoScaleNum,
-    temp_scaler::TempScaleNum,
-    full_placement::FpScaleNum,
-    rela::RelaScaleNum,
-    ensure_scaler::EnsureScaleNum,
-};
+use serde_json::Value;
 
 pub trait ScaleNum: Send {
     /// return target scale count
     /// - action_is_done: need prepare next state and wait for new action
     fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, action: &ESActionWrapper) -> usize;
 
+    /// step 传入的 action 的取值空间，默认算法不读取 action
+    fn action_space(&self, _fn_cnt: usize) -> Space {
+        Space::Discrete { n: 1 }
+    }
+
+    /// 快照时导出跨帧保留的内部状态，没有这类状态的算法不用实现
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    /// 恢复 save_state 导出的状态
+    fn load_state(&mut self, _state: Value) -> Result<(), String> {
+        Ok(())
+    }
+
     // fn fn_available_count(&self, fnid: FnId, env: &SimEnv) -> usize;
 }
 
-pub fn new_scale_num(c: &Config) -> Option<Box<dyn ScaleNum + Send>> {
-    let es = &c.mech;
-    let (scale_num_name, _scale_num_attr) = es.scale_num_conf();
+/// 注册的扩缩容数量算法，新增算法时在实现文件中声明 ENTRY 并加到这里
+pub const ALGOS: &[ScaleNumEntry] = &[
+    no::ENTRY,
+    ai::ENTRY,
+    hpa::ENTRY,
+    kpa::ENTRY,
+    forecast::ENTRY,
+    lass::ENTRY,
+    temp_scaler::ENTRY,
+    full_placement::ENTRY,
+    rela::ENTRY,
+    ensure_scaler::ENTRY,
+];
 
-    match &*scale_num_name {
-        // "ai" => {
-        //     return Some(Box::new(AIScaleNum::new()));
-        // }
-        "no" => {
-            return Some(Box::new(NoScaleNum::new()));
-        }
-        "hpa" => {
-            return Some(Box::new(HpaScaleNum::new()));
-        }
-        "lass" => {
-            return Some(Box::new(LassScaleNum::new()));
-        }
-        "temp_scaler" => {
-            return Some(Box::new(TempScaleNum::new()));
-        }
-        "full_placement" => {
-            return Some(Box::new(FpScaleNum::new()));
-        }
-        "rela" => {
-            return Some(Box::new(RelaScaleNum::new()));
-        }
-        "ensure_scaler" => {
-            return Some(Box::new(EnsureScaleNum::new()));
-        }
-        _ => {
-            return None;
-        }
-    }
+pub fn new_scale_num(c: &Config) -> Option<Box<dyn ScaleNum + Send>> {
+    let (scale_num_name, scale_num_attr) = c.mech.scale_num_conf();
+    algo_registry::find(ALGOS, &scale_num_name).map(|e| (e.new)(&scale_num_attr))
 }
 
 impl SimEnv {
@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry },
     mechanism::SimEnvObserve, scale::up_exec::no::NoScaleUpExec,
 };
 
@@ -16,6 +17,11 @@ impl NoScaleNum {
     }
 }
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("no", &["no_scale"]),
+    new: |_| Box::new(NoScaleNum::new()),
+};
+
 impl ScaleNum for NoScaleNum {
     fn scale_for_fn(
         &mut self,
@@ -4,14 +4,17 @@
 use std::{ collections::HashMap, sync::Arc };
 
 use parking_lot::{ Mutex };
+use serde_json::Value;
 
 use crate::{
+    algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry },
     mechanism::SimEnvObserve,
     fn_dag::{ FnId, EnvFnExt },
     actions::ESActionWrapper,
-    rl_target::{ self, RlTarget },
+    reward::{ self, RewardBreakdown, RewardMark },
+    rl_target::RlTarget,
     util::{ self, Window },
-    with_env_sub::{ WithEnvCore },
+    with_env_sub::{ WithEnvCore, WithEnvHelp },
     score::EnvMetricExt,
 };
 use super::{ ScaleNum, hpa::{ HpaScaleNum, Target } };
@@ -21,6 +24,8 @@ const SCALE_WINDOW_SIZE: usize = 10;
 pub struct RelaScaleNum {
     rl: Arc<RelaRlTargetInner>,
     last_env_frame: Option<usize>,
+    // 配置了 config.reward 时，score 为上次更新以来的奖励
+    reward_mark: RewardMark,
 }
 
 struct EachFnState {
@@ -172,15 +177,11 @@ impl RelaScaleNum {
             fncnt: 0,
             curfn: 0,
         });
-        rl_target::register_rl_target(
-            Box::new(RelaRlTarget {
-                inner: rl.clone(),
-            })
-        );
 
         RelaScaleNum {
             rl,
             last_env_frame: None,
+            reward_mark: RewardMark::default(),
         }
     }
 }
@@ -189,6 +190,11 @@ impl RelaScaleNum {
 
 // cur_frame
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("rela", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(RelaScaleNum::new()),
+};
+
 // 计算总量
 // 总内存占用量
 // 总cpu占用量
@@ -199,8 +205,21 @@ impl RelaScaleNum {
 // 平均请求延迟
 // 平均请求成本
 impl ScaleNum for RelaScaleNum {
+    // 状态和外部 agent 的交互进度绑定，不能单独恢复
+    fn save_state(&self) -> Result<Value, String> {
+        Err("rela is driven by an external agent and can't be snapshotted".to_owned())
+    }
+
     fn scale_for_fn(&mut self, env: &SimEnvObserve, fnid: FnId, action: &ESActionWrapper) -> usize {
         if self.last_env_frame.is_none() {
+            // 注册到这个 env 的 agent，http rl_step 按 env_id 找到这里
+            env.help()
+                .rl_agent()
+                .register_target(
+                    Box::new(RelaRlTarget {
+                        inner: self.rl.clone(),
+                    })
+                );
             unsafe {
                 let mut rl = util::non_null(&*self.rl);
                 let fncnt = env.core().fns().len();
@@ -274,7 +293,14 @@ impl ScaleNum for RelaScaleNum {
                     env.req_done_time_avg(),
                     env.cost_each_req()
                 );
-                state_score.1 = env.quality_price_ratio();
+                let reward_conf = &env.help().config().reward;
+                state_score.1 = if reward_conf.enabled() {
+                    // 动作是逐函数给出的，不计 churn
+                    let mark = std::mem::replace(&mut self.reward_mark, RewardMark::of(env));
+                    RewardBreakdown::new(reward::interval_terms(env, &mark), reward_conf).reward
+                } else {
+                    env.quality_price_ratio()
+                };
             }
 
             unsafe {
@@ -5,15 +5,21 @@
 use std::cell::{ RefCell };
 use std::collections::{ HashMap, VecDeque };
 
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+
+use crate::algo_registry::{ AlgoEntry, AlgoInfo, ScaleNumEntry };
 use crate::fn_dag::EnvFnExt;
 use crate::mechanism::SimEnvObserve;
 use crate::node::EnvNodeExt;
+use crate::snapshot::{ load_algo_state, save_algo_state };
 use crate::with_env_sub::{ WithEnvCore };
 use crate::{ actions::ESActionWrapper, fn_dag::FnId, CONTAINER_BASIC_MEM };
 
 use super::{ down_filter::{ CarefulScaleDownFilter, ScaleFilter }, ScaleNum };
 
 // 定义Hawkes过程参数类型
+#[derive(Serialize, Deserialize)]
 struct HawkesParams {
     mu: f64, // 在无历史调用影响下的平均调用率
     alpha: f64, // 单个触发事件的影响力
@@ -30,6 +36,7 @@ impl HawkesParams {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 struct FrameCountTemp {
     frame: usize,
     count: usize,
@@ -170,8 +177,49 @@ impl TempScaleNum {
     }
 }
 
+pub const ENTRY: ScaleNumEntry = AlgoEntry {
+    info: AlgoInfo::simple("temp_scaler", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(TempScaleNum::new()),
+};
+
 // 实现核心 trait
+// 快照中的状态: 各函数的 Hawkes 参数、调用记录、温度记录、扩缩容帧数，以及三个决策计数
+type TempScaleState = (
+    HashMap<FnId, RefCell<HawkesParams>>,
+    HashMap<FnId, RefCell<VecDeque<FrameCountTemp>>>,
+    HashMap<FnId, RefCell<VecDeque<FrameCountTemp>>>,
+    HashMap<FnId, usize>,
+    [usize; 3],
+);
+
 impl ScaleNum for TempScaleNum {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(
+            save_algo_state(
+                &(
+                    &self.fn_params,
+                    &self.fn_call_history,
+                    &self.fn_temp_history,
+                    &self.fn_temp_scale_sign,
+                    [self.decide_to_up_count, self.resource_decide_to_up_count, self.decide_to_down_count],
+                )
+            )
+        )
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        let (params, call_history, temp_history, scale_sign, counts): TempScaleState = load_algo_state(
+            "temp_scaler",
+            state
+        )?;
+        self.fn_params = params;
+        self.fn_call_history = call_history;
+        self.fn_temp_history = temp_history;
+        self.fn_temp_scale_sign = scale_sign;
+        [self.decide_to_up_count, self.resource_decide_to_up_count, self.decide_to_down_count] = counts;
+        Ok(())
+    }
+
     // 设置指定函数的目标容器数量
     fn scale_for_fn(
         &mut self,
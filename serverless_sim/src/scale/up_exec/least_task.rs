@@ -3,6 +3,7 @@ use crate::mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes};
 use crate::node::EnvNodeExt;
 use crate::with_env_sub::WithEnvHelp;
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScaleUpExecEntry},
     fn_dag::FnId,
     mechanism::{SimEnvObserve, UpCmd},
 };
@@ -15,6 +16,11 @@ impl LeastTaskScaleUpExec {
     }
 }
 
+pub const ENTRY: ScaleUpExecEntry = AlgoEntry {
+    info: AlgoInfo::simple("least_task", &["scale_sche_separated", "scale_sche_joint"]),
+    new: |_| Box::new(LeastTaskScaleUpExec::new()),
+};
+
 impl ScaleUpExec for LeastTaskScaleUpExec {
     fn exec_scale_up(
         &self,
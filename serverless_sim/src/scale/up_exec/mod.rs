@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{self, ScaleUpExecEntry},
     config::Config,
     fn_dag::FnId,
     mechanism::{SimEnvObserve, UpCmd},
@@ -18,18 +19,10 @@ pub trait ScaleUpExec: Send {
     ) -> Vec<UpCmd>;
 }
 
+/// 注册的扩容执行器
+pub const ALGOS: &[ScaleUpExecEntry] = &[least_task::ENTRY, no::ENTRY];
+
 pub fn new_scale_up_exec(conf: &Config) -> Option<Box<dyn ScaleUpExec>> {
-    let es = &conf.mech;
-    let (scale_up_exec_name, _scale_up_exec_attr) = es.scale_up_exec_conf();
-    match &*scale_up_exec_name {
-        "least_task" => {
-            return Some(Box::new(least_task::LeastTaskScaleUpExec::new()));
-        }
-        "no" => {
-            return Some(Box::new(no::NoScaleUpExec));
-        }
-        _ => {
-            return None;
-        }
-    }
+    let (scale_up_exec_name, scale_up_exec_attr) = conf.mech.scale_up_exec_conf();
+    algo_registry::find(ALGOS, &scale_up_exec_name).map(|e| (e.new)(&scale_up_exec_attr))
 }
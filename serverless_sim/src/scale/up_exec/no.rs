@@ -1,5 +1,6 @@
 use super::ScaleUpExec;
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScaleUpExecEntry},
     fn_dag::FnId,
     mechanism::{SimEnvObserve, UpCmd},
     mechanism_thread::MechCmdDistributor,
@@ -7,6 +8,11 @@ use crate::{
 
 pub struct NoScaleUpExec;
 
+pub const ENTRY: ScaleUpExecEntry = AlgoEntry {
+    info: AlgoInfo::simple("no", &["no_scale"]),
+    new: |_| Box::new(NoScaleUpExec),
+};
+
 impl ScaleUpExec for NoScaleUpExec {
     fn exec_scale_up(
         &self,
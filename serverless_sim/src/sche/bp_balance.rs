@@ -3,8 +3,10 @@ use std::{borrow::Borrow, cell::Ref, collections::{HashMap, HashSet}, env, threa
 
 
 use crate::{
-    fn_dag::{EnvFnExt, FnContainerState, FnId, RunningTask}, mechanism::{DownCmd, MechanismImpl, ScheCmd, SimEnvObserve}, mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes}, node::{self, EnvNodeExt, NodeId}, request::Request, sim_run::{schedule_helper, Scheduler}, with_env_sub::{WithEnvCore, WithEnvHelp}
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
+    audit::{CandidateScore, DecisionKind}, fn_dag::{EnvFnExt, FnId}, mechanism::{DownCmd, MechanismImpl, ScheCmd, SimEnvObserve}, mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes}, node::{EnvNodeExt, NodeId}, request::Request, sim_run::{schedule_helper, Scheduler}, snapshot::{load_algo_state, save_algo_state}, with_env_sub::{WithEnvCore, WithEnvHelp}
 };
+use serde_json::Value;
 
 const CPU_THRESHOLD_TO_ADD: f32 = 1.0;
 const CPU_THRESHOLD_TO_REMOVE: f32 = 0.7;
@@ -66,8 +68,10 @@ impl BpBalanceScheduler {
     }
 
     // 找出binpack数组中 最合适的点(取决于贪婪的指标是什么) 的节点、并且要内存足够才行
-    fn find_schedule_nodeid(&self, fnid: FnId, env: &SimEnvObserve) -> usize{
+    // 同时返回所有候选节点的饥饿程度，记到决策日志里
+    fn find_schedule_nodeid(&self, fnid: FnId, env: &SimEnvObserve) -> (usize, Vec<CandidateScore>){
         let mut schedule_node_id: usize = 9999;
+        let mut candidates = vec![];
         let mut best_node_metric = 0.0;
         let bplist = self.binpack_map.get(&fnid).unwrap();
 
@@ -77,6 +81,7 @@ impl BpBalanceScheduler {
 
             // 找出 饥饿程度 最小的
             let iter_node_starve_degree = self.cal_cpu_starve_degree(iter_node_resource_status, fnid, env);
+            candidates.push(CandidateScore { nid: *node_id, score: Some(iter_node_starve_degree) });
             if schedule_node_id == 9999{
                 schedule_node_id = *node_id;
                 best_node_metric = iter_node_starve_degree;
@@ -104,7 +109,7 @@ impl BpBalanceScheduler {
             
         }
 
-        schedule_node_id
+        (schedule_node_id, candidates)
     }
     
     // 计算函数分配到节点上后，cpu饥饿程度 = fn_cpu_use / cpu_local
@@ -254,11 +259,12 @@ impl BpBalanceScheduler {
             }
 
             // 找到调度节点
-            let sche_nodeid = self.find_schedule_nodeid(fnid, env);
+            let (sche_nodeid, candidates) = self.find_schedule_nodeid(fnid, env);
 
             if sche_nodeid != 9999 {
 
                 mech_metric().add_node_task_new_cnt(sche_nodeid);
+                env.help().audit().note_candidates(DecisionKind::Sche, Some(req.req_id), fnid, candidates);
                 cmd_distributor
                     .send(MechScheduleOnceRes::ScheCmd(ScheCmd {
                         reqid: req.req_id,
@@ -293,7 +299,21 @@ impl BpBalanceScheduler {
 
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("bp_balance", &["scale_sche_joint"]),
+    new: |_| Box::new(BpBalanceScheduler::new()),
+};
+
 impl Scheduler for BpBalanceScheduler {
+    // nodes_resc_state 和 need_schedule_fn 每次调度前重新计算，不用保存
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&(&self.binpack_map, &self.latest_nodes, &self.mech_impl_sign)))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        (self.binpack_map, self.latest_nodes, self.mech_impl_sign) = load_algo_state("bp_balance", state)?;
+        Ok(())
+    }
 
     fn schedule_some(&mut self,
         env: &SimEnvObserve,
@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::EnvFnExt,
     mechanism::{MechanismImpl, ScheCmd, SimEnvObserve, UpCmd},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -86,6 +87,11 @@ impl ConsistentHashScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("consistenthash", &["no_scale"]),
+    new: |_| Box::new(ConsistentHashScheduler::new()),
+};
+
 impl Scheduler for ConsistentHashScheduler {
     fn schedule_some(
         &mut self,
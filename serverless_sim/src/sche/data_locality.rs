@@ -0,0 +1,234 @@
+//! 数据局部性调度，在线版本的 HEFT
+//!
+//! 对每个前驱都已经调度的函数，估计它在每个候选节点上的完成帧：
+//! 前驱产出数据的时间 + 按 DAG 边上的数据量和 node2node_graph 带宽算出的传输时间、
+//! 冷启动剩余时间，取两者较晚的一个，再加上和节点上其他任务分 cpu 的执行时间，
+//! 选估计完成最早的节点。前驱还没执行完时用之前调度它时的估计值
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use daggy::Walker;
+
+use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
+    audit::{CandidateScore, DecisionKind},
+    fn_dag::{EnvFnExt, FnContainerState, FnId},
+    mechanism::{MechType, MechanismImpl, ScheCmd, SimEnvObserve},
+    mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
+    node::{EnvNodeExt, NodeId},
+    request::{ReqId, Request},
+    sim_run::{schedule_helper, Scheduler},
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+/// 传输 size KB 需要的帧数，同一节点不计传输
+pub fn transfer_frames(size: f32, trans_per_frame: f32, same_node: bool) -> f32 {
+    if same_node || size <= 0.0 {
+        return 0.0;
+    }
+    size / trans_per_frame
+}
+
+/// 分离模式下只能调度到已有容器的节点，否则没有容器时节点要放得下新容器
+pub fn can_host(env: &SimEnvObserve, mech: &MechanismImpl, nid: NodeId, fnid: FnId) -> bool {
+    let node = env.node(nid);
+    if node.container(fnid).is_some() {
+        return true;
+    }
+    match mech.mech_type() {
+        MechType::ScaleScheSeparated => false,
+        _ => node.mem_enough_for_container(&env.func(fnid)),
+    }
+}
+
+/// nid 上 fnid 的容器还要多少帧才能就绪，没有容器时按完整冷启动算
+pub fn cold_start_left(env: &SimEnvObserve, nid: NodeId, fnid: FnId) -> usize {
+    let node = env.node(nid);
+    let left = match node.container(fnid) {
+        Some(c) => match c.state() {
+            FnContainerState::Starting { left_frame } => *left_frame,
+            FnContainerState::Running => 0,
+        },
+        None => env.func(fnid).cold_start_time,
+    };
+    left
+}
+
+/// 节点 cpu 被 tasks 个任务加上新任务平分时的执行帧数
+fn exec_frames(fn_cpu: f32, node_cpu: f32, tasks: usize) -> f32 {
+    fn_cpu / (node_cpu / (tasks + 1) as f32)
+}
+
+/// 数据到齐和容器就绪之后才开始执行
+fn finish_frame(data_ready: f32, cold_ready: f32, exec: f32) -> f32 {
+    data_ready.max(cold_ready) + exec
+}
+
+#[derive(Default)]
+pub struct DataLocalityScheduler {
+    /// 调度时估计的任务完成帧，后继据此估计数据什么时候产出
+    est_done: HashMap<(ReqId, FnId), f32>,
+    /// 本轮分到各节点的任务数，节点上的任务数在指令应用前还没有更新
+    round_tasks: HashMap<NodeId, usize>,
+}
+
+impl DataLocalityScheduler {
+    pub fn new() -> Self {
+        Self {
+            est_done: HashMap::new(),
+            round_tasks: HashMap::new(),
+        }
+    }
+
+    /// fnid 在 nid 上的估计完成帧，parents 为 (前驱, 前驱所在节点, 边上的数据量)
+    fn estimate(
+        &self,
+        env: &SimEnvObserve,
+        req: &Request,
+        fnid: FnId,
+        parents: &[(FnId, NodeId, f32)],
+        nid: NodeId,
+    ) -> f32 {
+        let now = env.core().current_frame() as f32;
+        let data_ready = parents
+            .iter()
+            .map(|&(p, pnode, size)| {
+                let ready = if req.done_fns.contains_key(&p) {
+                    now
+                } else {
+                    self.est_done.get(&(req.req_id, p)).copied().unwrap_or(now).max(now)
+                };
+                ready
+                    + transfer_frames(size, env.node_get_trans_per_frame_btwn(pnode, nid), pnode == nid)
+            })
+            .fold(now, f32::max);
+
+        let cold_left = cold_start_left(env, nid, fnid);
+        let func = env.func(fnid);
+        let node = env.node(nid);
+        let tasks = node.all_task_cnt() + self.round_tasks.get(&nid).copied().unwrap_or(0);
+        finish_frame(
+            data_ready,
+            now + cold_left as f32,
+            exec_frames(func.cpu, node.rsc_limit.cpu, tasks),
+        )
+    }
+
+    fn schedule_for_one_req(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        req: &Request,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        let fns = schedule_helper::collect_task_to_sche(
+            req,
+            env,
+            schedule_helper::CollectTaskConfig::All,
+        );
+        // 本轮调度的函数也要作为后继的前驱
+        let mut fn_node = req.fn_node.clone();
+        for fnid in fns {
+            let parents = {
+                let dag = env.dag_inner(req.dag_i);
+                let graph_i = env.func(fnid).graph_i;
+                let mut parents = vec![];
+                for (edge, pgi) in dag.parents(graph_i).iter(&dag) {
+                    let p = dag[pgi];
+                    let Some(&pnode) = fn_node.get(&p) else {
+                        break;
+                    };
+                    parents.push((p, pnode, *dag.edge_weight(edge).unwrap()));
+                }
+                parents
+            };
+            if parents.len() != env.func(fnid).parent_fns(env).len() {
+                // 还有前驱没调度，下一轮再看
+                continue;
+            }
+
+            let scores = (0..env.node_cnt())
+                .filter(|&nid| can_host(env, mech, nid, fnid))
+                .map(|nid| (nid, self.estimate(env, req, fnid, &parents, nid)))
+                .collect::<Vec<_>>();
+            let Some(&(best, done)) = scores.iter().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+                // 分离模式下还没有容器，等扩容
+                continue;
+            };
+
+            fn_node.insert(fnid, best);
+            self.est_done.insert((req.req_id, fnid), done);
+            *self.round_tasks.entry(best).or_insert(0) += 1;
+            env.help().audit().note_candidates(
+                DecisionKind::Sche,
+                Some(req.req_id),
+                fnid,
+                scores
+                    .into_iter()
+                    .map(|(nid, done)| CandidateScore { nid, score: Some(done) })
+                    .collect(),
+            );
+            cmd_distributor
+                .send(MechScheduleOnceRes::ScheCmd(ScheCmd {
+                    nid: best,
+                    reqid: req.req_id,
+                    fnid,
+                    memlimit: None,
+                }))
+                .unwrap();
+        }
+    }
+}
+
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("data_locality", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(DataLocalityScheduler::new()),
+};
+
+impl Scheduler for DataLocalityScheduler {
+    // 元组作键的表在 json 中存为按键排序的列表
+    fn save_state(&self) -> Result<Value, String> {
+        let mut est_done = self.est_done.iter().collect::<Vec<_>>();
+        est_done.sort_by_key(|(k, _)| **k);
+        Ok(save_algo_state(&est_done))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        let est_done: Vec<((ReqId, FnId), f32)> = load_algo_state("data_locality", state)?;
+        self.est_done = est_done.into_iter().collect();
+        Ok(())
+    }
+
+    fn schedule_some(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        self.round_tasks.clear();
+        let requests = env.core().requests();
+        self.est_done.retain(|(reqid, _), _| requests.contains_key(reqid));
+        for (_, req) in requests.iter() {
+            self.schedule_for_one_req(env, mech, req, cmd_distributor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exec_frames, finish_frame, transfer_frames};
+
+    #[test]
+    fn test_finish_estimate() {
+        assert_eq!(transfer_frames(100.0, 25.0, false), 4.0);
+        assert_eq!(transfer_frames(100.0, 25.0, true), 0.0);
+        // 两个任务加上新任务平分 cpu
+        assert_eq!(exec_frames(30.0, 30.0, 2), 3.0);
+        // 传输比冷启动慢时按传输算，反之按冷启动算
+        assert_eq!(finish_frame(4.0, 2.0, 3.0), 7.0);
+        assert_eq!(finish_frame(0.0, 5.0, 3.0), 8.0);
+    }
+}
@@ -4,6 +4,7 @@ use std::{
 };
 
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::{EnvFnExt, FnId},
     mechanism::{DownCmd, MechType, MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -64,6 +65,11 @@ impl EnsureScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("ensure_scheduler", &["scale_sche_joint"]),
+    new: |_| Box::new(EnsureScheduler::new()),
+};
+
 impl Scheduler for EnsureScheduler {
     fn schedule_some(
         &mut self,
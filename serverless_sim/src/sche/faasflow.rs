@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::{EnvFnExt, FnId},
     mechanism::{DownCmd, MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -173,6 +174,11 @@ impl FaasFlowScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("faasflow", &["no_scale"]),
+    new: |_| Box::new(FaasFlowScheduler::new()),
+};
+
 // 图形调度器中分组和调度算法的关键步骤如下所示。
 // 在初始化阶段，每个函数节点都作为单独的组进行初始化，并且工作节点是随机分配的（第1-2行）。
 // 首先，算法从拓扑排序和迭代开始。在每次迭代的开始，它将使用贪婪方法来定位DAG图中关键路径上具有最长边的两个函数，
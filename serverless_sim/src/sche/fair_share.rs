@@ -0,0 +1,282 @@
+//! 多租户公平调度
+//!
+//! 就绪任务按请求所属租户排队(见 tenant)。每次从优先级最高的租户中选加权主导份额
+//! (主导份额 / 权重)最小的一个，把它队首的任务放到任务数最少、还没到 max_node_tasks 的节点上，
+//! 即按优先级严格排序，同优先级内做加权 DRF progressive filling。
+//! 开启 preempt 后，没有节点能放下时撤回一个优先级更低的租户在可放节点上的任务让出位置，
+//! 被撤回的任务回到待调度状态
+
+use std::collections::{HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::data_locality::can_host;
+use crate::{
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScheEntry},
+    audit::{CandidateScore, DecisionKind},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::{MechanismImpl, PreemptCmd, ScheCmd, SimEnvObserve},
+    mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
+    node::{EnvNodeExt, NodeId},
+    request::ReqId,
+    sim_run::{schedule_helper, Scheduler},
+    tenant::{tenant_cnt, tenant_of, tenant_priority, tenant_weight, TenantId, TenantUsage},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+fn default_max_node_tasks() -> usize {
+    4
+}
+
+/// 类型化配置 {"type": "fair_share", "max_node_tasks": 4, "preempt": true}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FairShareParams {
+    #[serde(default = "default_max_node_tasks")]
+    pub max_node_tasks: usize,
+    #[serde(default)]
+    pub preempt: bool,
+}
+
+impl Default for FairShareParams {
+    fn default() -> Self {
+        Self {
+            max_node_tasks: default_max_node_tasks(),
+            preempt: false,
+        }
+    }
+}
+
+impl AlgoParams for FairShareParams {
+    fn to_attr(&self) -> String {
+        format!("max_node_tasks={},preempt={}", self.max_node_tasks, self.preempt)
+    }
+}
+
+impl FairShareParams {
+    /// 旧格式参数 "max_node_tasks=4,preempt=true"，省略的项取默认值
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for (k, v) in parse_kv_attr("fair_share", arg)? {
+            let bad = |e: &dyn std::fmt::Display| format!("fair_share {} invalid {:?}: {}", k, v, e);
+            match k {
+                "max_node_tasks" => {
+                    params.max_node_tasks = v.parse().map_err(|e| bad(&e))?;
+                }
+                "preempt" => {
+                    params.preempt = v.parse().map_err(|e| bad(&e))?;
+                }
+                _ => {
+                    return Err(format!("fair_share arg unknown key {:?}, should be max_node_tasks or preempt", k));
+                }
+            }
+        }
+        if params.max_node_tasks == 0 {
+            return Err("fair_share max_node_tasks should be positive".to_owned());
+        }
+        Ok(params)
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+/// 候选租户 (租户, 优先级, 加权主导份额)，优先级高的先，同优先级份额小的先
+fn pick_tenant(cands: &[(TenantId, u32, f32)]) -> Option<TenantId> {
+    cands
+        .iter()
+        .min_by(|a, b| b.1.cmp(&a.1).then(a.2.total_cmp(&b.2)).then(a.0.cmp(&b.0)))
+        .map(|c| c.0)
+}
+
+struct Victim {
+    nid: NodeId,
+    reqid: ReqId,
+    fnid: FnId,
+    tenant: TenantId,
+}
+
+pub struct FairShareScheduler {
+    params: FairShareParams,
+}
+
+impl FairShareScheduler {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            params: FairShareParams::parse(arg).unwrap_or_else(|e| panic!("{}", e)),
+        }
+    }
+
+    /// 可放 fnid 的节点上优先级低于 prio 的任务中，优先撤回优先级最低、加权份额最大的租户最新的请求
+    fn find_victim(
+        &self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        usage: &TenantUsage,
+        preempted: &HashSet<(ReqId, FnId)>,
+        fnid: FnId,
+        prio: u32,
+    ) -> Option<Victim> {
+        let tenants = &env.help().config().tenants;
+        let requests = env.core().requests();
+        let mut victims = vec![];
+        for nid in (0..env.node_cnt()).filter(|&n| can_host(env, mech, n, fnid)) {
+            for (reqid, vfnid) in env.node(nid).tasks() {
+                if preempted.contains(&(reqid, vfnid)) {
+                    continue;
+                }
+                let Some(req) = requests.get(&reqid) else {
+                    continue;
+                };
+                let t = tenant_of(tenants, req.dag_i);
+                if tenant_priority(tenants, t) < prio {
+                    victims.push(Victim { nid, reqid, fnid: vfnid, tenant: t });
+                }
+            }
+        }
+        victims.into_iter().min_by(|a, b| {
+            let key = |v: &Victim| {
+                (tenant_priority(tenants, v.tenant), usage.dominant_share(v.tenant) / tenant_weight(tenants, v.tenant))
+            };
+            let (ap, ashare) = key(a);
+            let (bp, bshare) = key(b);
+            ap.cmp(&bp).then(bshare.total_cmp(&ashare)).then(b.reqid.cmp(&a.reqid))
+        })
+    }
+}
+
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "fair_share",
+        params: &[
+            ParamSpec {
+                name: "max_node_tasks",
+                ty: "usize",
+                default: "4",
+                desc: "每个节点上最多同时放的任务数，超过后任务留在队列中",
+            },
+            ParamSpec {
+                name: "preempt",
+                ty: "bool",
+                default: "false",
+                desc: "没有节点能放下时撤回优先级更低的任务",
+            },
+        ],
+        mech_types: &["no_scale", "scale_sche_separated"],
+        parse_params: parse_params::<FairShareParams>,
+        check_attr: FairShareParams::check_arg,
+    },
+    new: |attr| Box::new(FairShareScheduler::new(attr)),
+};
+
+impl Scheduler for FairShareScheduler {
+    fn schedule_some(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        let tenants = &env.help().config().tenants;
+        let mut usage = TenantUsage::from_env(env);
+        let mut node_tasks = (0..env.node_cnt())
+            .map(|n| env.node(n).all_task_cnt())
+            .collect::<Vec<_>>();
+
+        let mut queues = vec![VecDeque::new(); tenant_cnt(tenants)];
+        for (_, req) in env.core().requests().iter() {
+            let fns = schedule_helper::collect_task_to_sche(
+                req,
+                env,
+                schedule_helper::CollectTaskConfig::PreAllDone,
+            );
+            let t = tenant_of(tenants, req.dag_i);
+            queues[t].extend(fns.into_iter().map(|fnid| (req.req_id, fnid)));
+        }
+
+        let mut preempted = HashSet::new();
+        loop {
+            let cands = queues
+                .iter()
+                .enumerate()
+                .filter(|(_, q)| !q.is_empty())
+                .map(|(t, _)| (t, tenant_priority(tenants, t), usage.dominant_share(t) / tenant_weight(tenants, t)))
+                .collect::<Vec<_>>();
+            let Some(t) = pick_tenant(&cands) else {
+                break;
+            };
+            let (reqid, fnid) = queues[t].pop_front().unwrap();
+
+            let fits = (0..env.node_cnt())
+                .filter(|&n| can_host(env, mech, n, fnid) && node_tasks[n] < self.params.max_node_tasks)
+                .collect::<Vec<_>>();
+            let nid = if let Some(&n) = fits.iter().min_by_key(|&&n| node_tasks[n]) {
+                node_tasks[n] += 1;
+                n
+            } else if self.params.preempt {
+                let Some(v) = self.find_victim(env, mech, &usage, &preempted, fnid, tenant_priority(tenants, t)) else {
+                    continue;
+                };
+                // 撤回一个任务再放入一个，节点任务数不变
+                usage.remove(v.tenant, v.nid, env.func(v.fnid).mem);
+                preempted.insert((v.reqid, v.fnid));
+                cmd_distributor
+                    .send(MechScheduleOnceRes::PreemptCmd(PreemptCmd {
+                        nid: v.nid,
+                        reqid: v.reqid,
+                        fnid: v.fnid,
+                    }))
+                    .unwrap();
+                v.nid
+            } else {
+                // 所有节点都满了，下一轮再调度
+                continue;
+            };
+
+            env.help().audit().note_candidates(
+                DecisionKind::Sche,
+                Some(reqid),
+                fnid,
+                fits.iter()
+                    .map(|&n| CandidateScore { nid: n, score: Some(node_tasks[n] as f32) })
+                    .collect(),
+            );
+            usage.add(t, nid, env.func(fnid).mem);
+            cmd_distributor
+                .send(MechScheduleOnceRes::ScheCmd(ScheCmd {
+                    nid,
+                    reqid,
+                    fnid,
+                    memlimit: None,
+                }))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_tenant, FairShareParams};
+    use crate::algo_registry::AlgoParams;
+
+    #[test]
+    fn test_fair_share_params() {
+        assert_eq!(FairShareParams::parse("").unwrap(), FairShareParams::default());
+        let p = FairShareParams::parse("preempt=true").unwrap();
+        assert_eq!((p.max_node_tasks, p.preempt), (4, true));
+        assert_eq!(FairShareParams::parse(&p.to_attr()).unwrap(), p);
+        for bad in ["max_node_tasks=0", "preempt=yes", "d=2", "4"] {
+            assert!(FairShareParams::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_pick_tenant() {
+        assert_eq!(pick_tenant(&[]), None);
+        // 同优先级选加权份额小的，份额相同选编号小的
+        assert_eq!(pick_tenant(&[(0, 0, 0.5), (1, 0, 0.2), (2, 0, 0.2)]), Some(1));
+        // 优先级高的先于份额小的
+        assert_eq!(pick_tenant(&[(0, 0, 0.0), (1, 2, 0.9), (2, 1, 0.1)]), Some(1));
+    }
+}
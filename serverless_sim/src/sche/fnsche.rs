@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::FnId,
     mechanism::{MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -35,6 +36,11 @@ impl FnScheScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("fnsche", &["no_scale"]),
+    new: |_| Box::new(FnScheScheduler::new()),
+};
+
 impl Scheduler for FnScheScheduler {
     fn schedule_some(
         &mut self,
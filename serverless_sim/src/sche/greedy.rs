@@ -4,6 +4,7 @@ use std::{
 };
 
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::{EnvFnExt, FnId},
     mechanism::{MechType, MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -82,6 +83,11 @@ impl GreedyScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("greedy", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(GreedyScheduler::new()),
+};
+
 impl Scheduler for GreedyScheduler {
     fn schedule_some(
         &mut self,
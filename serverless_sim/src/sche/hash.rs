@@ -1,4 +1,5 @@
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     mechanism::{MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
     node::EnvNodeExt,
@@ -52,6 +53,11 @@ impl HashScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("hash", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(HashScheduler::new()),
+};
+
 impl Scheduler for HashScheduler {
     fn schedule_some(
         &mut self,
@@ -0,0 +1,464 @@
+//! HEFT/CPOP 提前规划整个 DAG 的调度
+//!
+//! 请求到达时按平均执行时间和平均传输时间算出每个函数的 upward rank，
+//! 用 HEFT 或 CPOP 给请求的所有函数规划节点。函数的前驱都执行完后才真正下发：
+//! 规划的节点放不下这个函数，或者节点上积压的计算量会让它多等超过自身的执行时间时，
+//! 按当前状态重新选估计完成最早的节点
+
+use std::collections::{HashMap, HashSet};
+
+use daggy::Walker;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::data_locality::{can_host, cold_start_left, transfer_frames};
+use crate::{
+    algo_registry::{parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScheEntry},
+    audit::{CandidateScore, DecisionKind},
+    fn_dag::{EnvFnExt, FnId},
+    mechanism::{MechanismImpl, ScheCmd, SimEnvObserve},
+    mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
+    node::{EnvNodeExt, NodeId},
+    request::{ReqId, Request},
+    sim_run::{schedule_helper, Scheduler},
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HeftMode {
+    Heft,
+    Cpop,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HeftModeConf {
+    #[default]
+    Heft,
+    Cpop,
+}
+
+/// 类型化配置 {"type": "heft", "mode": "cpop"}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HeftParams {
+    #[serde(default)]
+    pub mode: HeftModeConf,
+}
+
+impl AlgoParams for HeftParams {
+    fn to_attr(&self) -> String {
+        match self.mode {
+            HeftModeConf::Heft => "heft".to_owned(),
+            HeftModeConf::Cpop => "cpop".to_owned(),
+        }
+    }
+}
+
+/// 请求的 DAG，fns 按拓扑序，边上为数据量
+struct RankDag {
+    fns: Vec<FnId>,
+    /// 在平均节点上的执行帧数
+    w: HashMap<FnId, f32>,
+    preds: HashMap<FnId, Vec<(FnId, f32)>>,
+    succs: HashMap<FnId, Vec<(FnId, f32)>>,
+    /// 按平均带宽每 KB 的传输帧数
+    comm_per_kb: f32,
+}
+
+impl RankDag {
+    fn from_env(env: &SimEnvObserve, dag_i: usize) -> Self {
+        let nodes_cnt = env.node_cnt();
+        let avg_cpu = env.nodes().iter().map(|n| n.rsc_limit.cpu).sum::<f32>() / nodes_cnt as f32;
+        let mut bw_sum = 0.0;
+        let mut pairs = 0;
+        for i in 0..nodes_cnt {
+            for j in i + 1..nodes_cnt {
+                bw_sum += env.node_get_trans_per_frame_btwn(i, j);
+                pairs += 1;
+            }
+        }
+
+        let dag = env.dag(dag_i);
+        let mut rdag = RankDag {
+            fns: vec![],
+            w: HashMap::new(),
+            preds: HashMap::new(),
+            succs: HashMap::new(),
+            comm_per_kb: if pairs == 0 { 0.0 } else { pairs as f32 / bw_sum },
+        };
+        let mut walker = dag.new_dag_walker();
+        while let Some(gi) = walker.next(&dag.dag_inner) {
+            let fnid = dag.dag_inner[gi];
+            let edges = |walk: Vec<(daggy::EdgeIndex, daggy::NodeIndex)>| {
+                walk.into_iter()
+                    .map(|(e, n)| (dag.dag_inner[n], *dag.dag_inner.edge_weight(e).unwrap()))
+                    .collect::<Vec<_>>()
+            };
+            let preds = edges(dag.dag_inner.parents(gi).iter(&dag.dag_inner).collect());
+            let succs = edges(dag.dag_inner.children(gi).iter(&dag.dag_inner).collect());
+            rdag.fns.push(fnid);
+            rdag.w.insert(fnid, env.func(fnid).cpu / avg_cpu);
+            rdag.preds.insert(fnid, preds);
+            rdag.succs.insert(fnid, succs);
+        }
+        rdag
+    }
+
+    /// 到出口的最长路径，包含自身
+    fn upward_ranks(&self) -> HashMap<FnId, f32> {
+        let mut rank = HashMap::new();
+        for &f in self.fns.iter().rev() {
+            let succ_max = self.succs[&f]
+                .iter()
+                .map(|&(s, size)| size * self.comm_per_kb + rank[&s])
+                .fold(0.0, f32::max);
+            rank.insert(f, self.w[&f] + succ_max);
+        }
+        rank
+    }
+
+    /// 从入口过来的最长路径，不含自身
+    fn downward_ranks(&self) -> HashMap<FnId, f32> {
+        let mut rank = HashMap::new();
+        for &f in self.fns.iter() {
+            let pred_max = self.preds[&f]
+                .iter()
+                .map(|&(p, size)| rank[&p] + self.w[&p] + size * self.comm_per_kb)
+                .fold(0.0, f32::max);
+            rank.insert(f, pred_max);
+        }
+        rank
+    }
+
+    /// CPOP 的关键路径：从优先级最高的入口开始，每步走优先级等于关键路径长度的后继
+    fn critical_path(&self, prio: &HashMap<FnId, f32>) -> HashSet<FnId> {
+        let mut path = HashSet::new();
+        let Some(mut cur) = self.fns
+            .iter()
+            .copied()
+            .filter(|f| self.preds[f].is_empty())
+            .max_by(|a, b| prio[a].total_cmp(&prio[b]))
+        else {
+            return path;
+        };
+        let cp_len = prio[&cur];
+        path.insert(cur);
+        while let Some(next) = self.succs[&cur]
+            .iter()
+            .map(|&(s, _)| s)
+            .filter(|s| (prio[s] - cp_len).abs() <= 1e-3 * cp_len.max(1.0))
+            .max_by(|a, b| prio[a].total_cmp(&prio[b]))
+        {
+            path.insert(next);
+            cur = next;
+        }
+        path
+    }
+
+    /// 每次从前驱都已排好的函数中取优先级最高的
+    fn list_order(&self, prio: &HashMap<FnId, f32>) -> Vec<FnId> {
+        let mut left_preds = self.fns
+            .iter()
+            .map(|f| (*f, self.preds[f].len()))
+            .collect::<HashMap<_, _>>();
+        let mut ready = self.fns
+            .iter()
+            .copied()
+            .filter(|f| left_preds[f] == 0)
+            .collect::<Vec<_>>();
+        let mut order = vec![];
+        while let Some((i, _)) = ready
+            .iter()
+            .enumerate()
+            .max_by(|a, b| prio[a.1].total_cmp(&prio[b.1]).then(b.0.cmp(&a.0)))
+        {
+            let f = ready.remove(i);
+            order.push(f);
+            for &(s, _) in &self.succs[&f] {
+                let left = left_preds.get_mut(&s).unwrap();
+                *left -= 1;
+                if *left == 0 {
+                    ready.push(s);
+                }
+            }
+        }
+        order
+    }
+}
+
+pub struct HeftScheduler {
+    mode: HeftMode,
+    /// 请求到达时规划的节点，规划时没有可选节点的函数不在里面
+    plans: HashMap<ReqId, HashMap<FnId, NodeId>>,
+    /// 本轮新分到各节点的计算量，节点状态在指令应用前还没有更新
+    round_calc: HashMap<NodeId, f32>,
+}
+
+impl HeftScheduler {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            mode: Self::parse_mode(arg).unwrap_or_else(|e| panic!("{}", e)),
+            plans: HashMap::new(),
+            round_calc: HashMap::new(),
+        }
+    }
+
+    fn parse_mode(arg: &str) -> Result<HeftMode, String> {
+        match arg {
+            "" | "heft" => Ok(HeftMode::Heft),
+            "cpop" => Ok(HeftMode::Cpop),
+            _ => Err(format!("heft arg can only be 1 of: heft, cpop, got {:?}", arg)),
+        }
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse_mode(arg).map(|_| ())
+    }
+
+    /// 节点把已有和本轮新分的计算量算完的帧
+    fn node_avail(&self, env: &SimEnvObserve, nid: NodeId, now: f32) -> f32 {
+        let node = env.node(nid);
+        let calc = node.remaining_calc(env) + self.round_calc.get(&nid).copied().unwrap_or(0.0);
+        now + calc / node.rsc_limit.cpu
+    }
+
+    fn plan_req(&self, env: &SimEnvObserve, mech: &MechanismImpl, req: &Request) -> HashMap<FnId, NodeId> {
+        let now = env.core().current_frame() as f32;
+        let rdag = RankDag::from_env(env, req.dag_i);
+        let up = rdag.upward_ranks();
+        let (prio, cp) = match self.mode {
+            HeftMode::Heft => (up, HashSet::new()),
+            HeftMode::Cpop => {
+                let down = rdag.downward_ranks();
+                let prio = up
+                    .iter()
+                    .map(|(f, u)| (*f, u + down[f]))
+                    .collect::<HashMap<_, _>>();
+                let cp = rdag.critical_path(&prio);
+                (prio, cp)
+            }
+        };
+
+        let nodes_cnt = env.node_cnt();
+        let mut avail = (0..nodes_cnt).map(|n| self.node_avail(env, n, now)).collect::<Vec<_>>();
+        // 关键路径放在能放下整条路径的最快节点
+        let cp_node = (0..nodes_cnt)
+            .filter(|&n| cp.iter().all(|&f| can_host(env, mech, n, f)))
+            .max_by(|&a, &b| env.node(a).rsc_limit.cpu.total_cmp(&env.node(b).rsc_limit.cpu));
+
+        let mut plan = HashMap::new();
+        let mut finish: HashMap<FnId, f32> = HashMap::new();
+        for f in rdag.list_order(&prio) {
+            let data_ready = |n: Option<NodeId>| {
+                rdag.preds[&f]
+                    .iter()
+                    .map(|&(p, size)| {
+                        let trans = match (plan.get(&p), n) {
+                            (Some(&pn), Some(n)) =>
+                                transfer_frames(size, env.node_get_trans_per_frame_btwn(pn, n), pn == n),
+                            _ => size * rdag.comm_per_kb,
+                        };
+                        finish[&p] + trans
+                    })
+                    .fold(now, f32::max)
+            };
+            let eft = |n: NodeId| {
+                let start = data_ready(Some(n))
+                    .max(avail[n])
+                    .max(now + cold_start_left(env, n, f) as f32);
+                start + env.func(f).cpu / env.node(n).rsc_limit.cpu
+            };
+            let chosen = match cp_node {
+                Some(n) if cp.contains(&f) => Some((n, eft(n))),
+                _ =>
+                    (0..nodes_cnt)
+                        .filter(|&n| can_host(env, mech, n, f))
+                        .map(|n| (n, eft(n)))
+                        .min_by(|a, b| a.1.total_cmp(&b.1)),
+            };
+            match chosen {
+                Some((n, ft)) => {
+                    avail[n] = ft;
+                    plan.insert(f, n);
+                    finish.insert(f, ft);
+                }
+                None => {
+                    // 分离模式下还没有容器，下发时再选
+                    let ft = data_ready(None) + rdag.w[&f];
+                    finish.insert(f, ft);
+                }
+            }
+        }
+        plan
+    }
+
+    /// 前驱都执行完的函数按规划下发，规划的节点不合适时重新选
+    fn dispatch_ready(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        req: &Request,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        let now = env.core().current_frame() as f32;
+        let fns = schedule_helper::collect_task_to_sche(
+            req,
+            env,
+            schedule_helper::CollectTaskConfig::PreAllDone,
+        );
+        for fnid in fns {
+            let parents = {
+                let dag = env.dag_inner(req.dag_i);
+                let graph_i = env.func(fnid).graph_i;
+                let parents = dag
+                    .parents(graph_i)
+                    .iter(&dag)
+                    .map(|(e, p)| (req.fn_node[&dag[p]], *dag.edge_weight(e).unwrap()))
+                    .collect::<Vec<_>>();
+                parents
+            };
+            let fn_cpu = env.func(fnid).cpu;
+            // (节点, 数据和容器都就绪的帧, 节点空出来的帧, 执行帧数)
+            let estimates = (0..env.node_cnt())
+                .filter(|&n| can_host(env, mech, n, fnid))
+                .map(|n| {
+                    let ready = parents
+                        .iter()
+                        .map(|&(pn, size)| {
+                            now + transfer_frames(size, env.node_get_trans_per_frame_btwn(pn, n), pn == n)
+                        })
+                        .fold(now + cold_start_left(env, n, fnid) as f32, f32::max);
+                    (n, ready, self.node_avail(env, n, now), fn_cpu / env.node(n).rsc_limit.cpu)
+                })
+                .collect::<Vec<_>>();
+            let eft = |&(_, ready, avail, exec): &(NodeId, f32, f32, f32)| ready.max(avail) + exec;
+            let Some(best) = estimates.iter().min_by(|a, b| eft(a).total_cmp(&eft(b))) else {
+                // 分离模式下还没有容器，等扩容
+                continue;
+            };
+            let planned = self.plans
+                .get(&req.req_id)
+                .and_then(|p| p.get(&fnid))
+                .and_then(|&n| estimates.iter().find(|e| e.0 == n));
+            let nid = match planned {
+                // 节点积压让函数多等的时间不超过它自身的执行时间时按规划走
+                Some(&(n, ready, avail, exec)) if avail - ready <= exec => n,
+                _ => best.0,
+            };
+
+            *self.round_calc.entry(nid).or_insert(0.0) += fn_cpu;
+            env.help().audit().note_candidates(
+                DecisionKind::Sche,
+                Some(req.req_id),
+                fnid,
+                estimates
+                    .iter()
+                    .map(|e| CandidateScore { nid: e.0, score: Some(eft(e)) })
+                    .collect(),
+            );
+            cmd_distributor
+                .send(MechScheduleOnceRes::ScheCmd(ScheCmd {
+                    nid,
+                    reqid: req.req_id,
+                    fnid,
+                    memlimit: None,
+                }))
+                .unwrap();
+        }
+    }
+}
+
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "heft",
+        params: &[
+            ParamSpec {
+                name: "mode",
+                ty: "heft|cpop",
+                default: "heft",
+                desc: "按 upward rank 逐个选最早完成的节点，或把关键路径放在同一个最快节点",
+            },
+        ],
+        mech_types: &["no_scale", "scale_sche_separated"],
+        parse_params: parse_params::<HeftParams>,
+        check_attr: HeftScheduler::check_arg,
+    },
+    new: |attr| Box::new(HeftScheduler::new(attr)),
+};
+
+impl Scheduler for HeftScheduler {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.plans))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.plans = load_algo_state("heft", state)?;
+        Ok(())
+    }
+
+    fn schedule_some(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        self.round_calc.clear();
+        let requests = env.core().requests();
+        self.plans.retain(|reqid, _| requests.contains_key(reqid));
+        for (reqid, req) in requests.iter() {
+            if !self.plans.contains_key(reqid) {
+                let plan = self.plan_req(env, mech, req);
+                self.plans.insert(*reqid, plan);
+            }
+            self.dispatch_ready(env, mech, req, cmd_distributor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::RankDag;
+
+    // 0 -> 1 -> 3, 0 -> 2 -> 3，1 的执行时间更长
+    fn diamond() -> RankDag {
+        let edges = [(0, 1, 10.0), (0, 2, 10.0), (1, 3, 20.0), (2, 3, 0.0)];
+        let mut preds: HashMap<usize, Vec<(usize, f32)>> = (0..4).map(|f| (f, vec![])).collect();
+        let mut succs = preds.clone();
+        for (a, b, size) in edges {
+            succs.get_mut(&a).unwrap().push((b, size));
+            preds.get_mut(&b).unwrap().push((a, size));
+        }
+        RankDag {
+            fns: vec![0, 1, 2, 3],
+            w: [(0, 1.0), (1, 5.0), (2, 2.0), (3, 1.0)].into_iter().collect(),
+            preds,
+            succs,
+            comm_per_kb: 0.1,
+        }
+    }
+
+    #[test]
+    fn test_ranks_and_order() {
+        let rdag = diamond();
+        let up = rdag.upward_ranks();
+        assert!((up[&3] - 1.0).abs() < 1e-5);
+        assert!((up[&1] - 8.0).abs() < 1e-5);
+        assert!((up[&2] - 3.0).abs() < 1e-5);
+        assert!((up[&0] - 10.0).abs() < 1e-5);
+        assert_eq!(rdag.list_order(&up), vec![0, 1, 2, 3]);
+
+        let down = rdag.downward_ranks();
+        let prio = up
+            .iter()
+            .map(|(f, u)| (*f, u + down[f]))
+            .collect::<HashMap<_, _>>();
+        let cp = rdag.critical_path(&prio);
+        assert!(cp.contains(&0) && cp.contains(&1) && cp.contains(&3));
+        assert!(!cp.contains(&2));
+    }
+}
@@ -4,6 +4,7 @@ use std::{
 };
 
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::{EnvFnExt, FnId}, mechanism::{DownCmd, MechType, MechanismImpl, ScheCmd, SimEnvObserve}, mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes}, node::{self, EnvNodeExt, Node, NodeId}, sche, sim_run::{schedule_helper, Scheduler}, with_env_sub::WithEnvCore
 };
 
@@ -45,6 +46,11 @@ impl LoadLeastScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("load_least", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(LoadLeastScheduler::new()),
+};
+
 impl Scheduler for LoadLeastScheduler {
     fn schedule_some(
         &mut self,
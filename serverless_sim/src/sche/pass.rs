@@ -4,6 +4,7 @@ use daggy::Walker;
 use rand::Rng;
 
 use crate::{
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
     fn_dag::{DagId, EnvFnExt, FnId},
     mechanism::{MechanismImpl, ScheCmd, SimEnvObserve},
     mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
@@ -126,7 +127,7 @@ impl PassScheduler {
                         // Calculate data transmission time of edge (pre, func)
                         // 计算从上个节点到当前节点的数据传输时间，取最小
                         let t_tran: f32 =
-                            func_pre.out_put_size / env.node_get_speed_btwn(node_id, i);
+                            func_pre.out_put_size / env.node_get_trans_per_frame_btwn(node_id, i);
                         if t_tran > t_tran_max {
                             t_tran_max = t_tran;
                         }
@@ -197,6 +198,11 @@ impl PassScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("pass", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(PassScheduler::new()),
+};
+
 // 图形调度器中分组和调度算法的关键步骤如下所示。
 // 在初始化阶段，每个函数节点都作为单独的组进行初始化，并且工作节点是随机分配的（第1-2行）。
 // 首先，算法从拓扑排序和迭代开始。在每次迭代的开始，它将使用贪婪方法来定位DAG图中关键路径上具有最长边的两个函数，
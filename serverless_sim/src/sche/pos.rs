@@ -1,6 +1,7 @@
 use rand::seq::SliceRandom;
 use rand::{ thread_rng, Rng };
 
+use crate::audit::{ CandidateScore, DecisionKind };
 use crate::fn_dag::EnvFnExt;
 use crate::mechanism_thread::{ MechCmdDistributor, MechScheduleOnceRes };
 use crate::node::EnvNodeExt;
@@ -8,12 +9,14 @@ use crate::request::ReqId;
 use crate::util;
 use crate::with_env_sub::{ WithEnvCore, WithEnvHelp };
 use crate::{
+    algo_registry::{ parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScheEntry },
     fn_dag::FnId,
     mechanism::{ MechanismImpl, ScheCmd, SimEnvObserve },
     node::NodeId,
     request::Request,
     sim_run::{ schedule_helper, Scheduler },
 };
+use serde::{ Deserialize, Serialize };
 use std::cell::RefCell;
 use std::collections::{ HashMap, HashSet, VecDeque };
 
@@ -23,6 +26,31 @@ enum PosMode {
     Auto,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PosModeConf {
+    #[default]
+    Greedy,
+    Random,
+}
+
+/// 类型化配置 {"type": "pos", "mode": "greedy"}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PosParams {
+    #[serde(default)]
+    pub mode: PosModeConf,
+}
+
+impl AlgoParams for PosParams {
+    fn to_attr(&self) -> String {
+        match self.mode {
+            PosModeConf::Greedy => "greedy".to_owned(),
+            PosModeConf::Random => "random".to_owned(),
+        }
+    }
+}
+
 pub struct PosScheduler {
     // new_scale_up_nodes: HashMap<FnId, HashSet<NodeId>>,
     schealeable_fns: RefCell<HashMap<FnId, HashSet<NodeId>>>,
@@ -43,14 +71,23 @@ impl PosScheduler {
                 v.reserve(1024);
                 v
             },
-            mode: match arg {
-                "greedy" => { PosMode::Greedy }
-                "random" => { PosMode::Random }
-                _ => { panic!("pos arg can only be 1 of: greedy, random") }
-                // "auto"
-            },
+            mode: Self::parse_mode(arg).unwrap_or_else(|e| panic!("{}", e)),
         }
     }
+
+    fn parse_mode(arg: &str) -> Result<PosMode, String> {
+        match arg {
+            "greedy" => Ok(PosMode::Greedy),
+            "random" => Ok(PosMode::Random),
+            // "auto"
+            _ => Err(format!("pos arg can only be 1 of: greedy, random, got {:?}", arg)),
+        }
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse_mode(arg).map(|_| ())
+    }
 }
 
 impl PosScheduler {
@@ -160,11 +197,15 @@ impl PosScheduler {
             let mut nodes2select = self.new_scale_up_nodes(fnid);
 
             // random
-            let best_node = match &self.mode {
+            let (best_node, candidates) = match &self.mode {
                 PosMode::Random => {
                     let i = thread_rng().gen_range(0..nodes2select.len());
                     let best_node = *nodes2select.iter().nth(i).unwrap();
-                    best_node
+                    let candidates = nodes2select
+                        .iter()
+                        .map(|n| CandidateScore { nid: *n, score: None })
+                        .collect();
+                    (best_node, candidates)
                 }
                 PosMode::Greedy => {
                     {
@@ -206,7 +247,12 @@ impl PosScheduler {
                                 score1.partial_cmp(&score2).unwrap()
                             })
                             .unwrap().1;
-                        best_node
+                        let candidates = nodes2select
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, n)| CandidateScore { nid: *n, score: Some(score_of_idx(idx)) })
+                            .collect();
+                        (best_node, candidates)
                     }
                 }
                 PosMode::Auto => {
@@ -218,6 +264,7 @@ impl PosScheduler {
 
             // env.schedule_reqfn_on_node(req, fnid, best_node);
             mech_metric().add_node_task_new_cnt(best_node);
+            env.help().audit().note_candidates(DecisionKind::Sche, Some(req.0), fnid, candidates);
             cmd_distributor
                 .send(
                     MechScheduleOnceRes::ScheCmd(ScheCmd {
@@ -232,6 +279,24 @@ impl PosScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "pos",
+        params: &[
+            ParamSpec {
+                name: "mode",
+                ty: "greedy|random",
+                default: "greedy",
+                desc: "在有容器的节点中选新任务最少的，或随机选",
+            },
+        ],
+        mech_types: &["scale_sche_joint"],
+        parse_params: parse_params::<PosParams>,
+        check_attr: PosScheduler::check_arg,
+    },
+    new: |attr| Box::new(PosScheduler::new(attr)),
+};
+
 impl Scheduler for PosScheduler {
     fn schedule_some(
         &mut self,
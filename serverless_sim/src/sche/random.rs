@@ -2,6 +2,7 @@ use crate::mechanism_thread::{ MechCmdDistributor, MechScheduleOnceRes };
 use crate::node::EnvNodeExt;
 use crate::with_env_sub::WithEnvCore;
 use crate::{
+    algo_registry::{ AlgoEntry, AlgoInfo, ScheEntry },
     mechanism::{ MechType, MechanismImpl, ScheCmd, SimEnvObserve },
     sim_run::{ schedule_helper, Scheduler },
 };
@@ -15,6 +16,11 @@ impl RandomScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("random", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(RandomScheduler::new()),
+};
+
 impl Scheduler for RandomScheduler {
     fn schedule_some(
         &mut self,
@@ -1,7 +1,10 @@
 use std::collections::HashSet;
 
+use serde_json::Value;
+
 use crate::{
-    fn_dag::EnvFnExt, mechanism::{MechanismImpl, ScheCmd, SimEnvObserve}, mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes}, node::EnvNodeExt, request::Request, sim_run::{schedule_helper, Scheduler}, with_env_sub::WithEnvCore
+    algo_registry::{AlgoEntry, AlgoInfo, ScheEntry},
+    fn_dag::EnvFnExt, mechanism::{MechanismImpl, ScheCmd, SimEnvObserve}, mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes}, node::EnvNodeExt, request::Request, sim_run::{schedule_helper, Scheduler}, snapshot::{load_algo_state, save_algo_state}, with_env_sub::WithEnvCore
 };
 
 
@@ -75,7 +78,21 @@ impl RotateScheduler {
     }
 }
 
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo::simple("rotate", &["no_scale", "scale_sche_separated"]),
+    new: |_| Box::new(RotateScheduler::new()),
+};
+
 impl Scheduler for RotateScheduler {
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(save_algo_state(&self.last_schedule_node_id))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        self.last_schedule_node_id = load_algo_state("rotate", state)?;
+        Ok(())
+    }
+
     fn schedule_some(
         &mut self,
         env: &SimEnvObserve,
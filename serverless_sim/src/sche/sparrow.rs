@@ -0,0 +1,310 @@
+//! Sparrow 风格的分布式调度
+//!
+//! 每个就绪的任务随机探测 d 个能放下它的节点，放到排队任务最少的一个(power of d choices)。
+//! 延迟绑定模式下任务先在 d 个节点上预约，哪个节点先空闲就绑定到哪个，
+//! 等待超过 `LATE_BIND_MAX_WAIT` 帧仍没有空闲节点时绑定到预约节点中排队最少的。
+//! 每条探测消息按 probe_ms 计入算法耗时 algo_exc_time
+
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::data_locality::can_host;
+use crate::{
+    algo_registry::{parse_kv_attr, parse_params, AlgoEntry, AlgoInfo, AlgoParams, ParamSpec, ScheEntry},
+    audit::{CandidateScore, DecisionKind},
+    fn_dag::FnId,
+    mechanism::{MechanismImpl, ScheCmd, SimEnvObserve},
+    mechanism_thread::{MechCmdDistributor, MechScheduleOnceRes},
+    node::{EnvNodeExt, NodeId},
+    request::ReqId,
+    sim_run::{schedule_helper, Scheduler},
+    snapshot::{load_algo_state, save_algo_state},
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+/// 延迟绑定最多等待的帧数，避免节点一直忙时任务饿死
+pub const LATE_BIND_MAX_WAIT: usize = 10;
+
+fn default_d() -> usize {
+    2
+}
+
+fn default_probe_ms() -> f32 {
+    0.5
+}
+
+/// 类型化配置 {"type": "sparrow", "d": 2, "late_binding": true, "probe_ms": 0.5}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SparrowParams {
+    #[serde(default = "default_d")]
+    pub d: usize,
+    #[serde(default)]
+    pub late_binding: bool,
+    #[serde(default = "default_probe_ms")]
+    pub probe_ms: f32,
+}
+
+impl Default for SparrowParams {
+    fn default() -> Self {
+        Self {
+            d: default_d(),
+            late_binding: false,
+            probe_ms: default_probe_ms(),
+        }
+    }
+}
+
+impl AlgoParams for SparrowParams {
+    fn to_attr(&self) -> String {
+        format!("d={},late_binding={},probe_ms={}", self.d, self.late_binding, self.probe_ms)
+    }
+}
+
+impl SparrowParams {
+    /// 旧格式参数 "d=2,late_binding=true,probe_ms=0.5"，省略的项取默认值
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        for (k, v) in parse_kv_attr("sparrow", arg)? {
+            let bad = |e: &dyn std::fmt::Display| format!("sparrow {} invalid {:?}: {}", k, v, e);
+            match k {
+                "d" => {
+                    params.d = v.parse().map_err(|e| bad(&e))?;
+                }
+                "late_binding" => {
+                    params.late_binding = v.parse().map_err(|e| bad(&e))?;
+                }
+                "probe_ms" => {
+                    params.probe_ms = v.parse().map_err(|e| bad(&e))?;
+                }
+                _ => {
+                    return Err(format!("sparrow arg unknown key {:?}, should be d, late_binding or probe_ms", k));
+                }
+            }
+        }
+        if params.d == 0 {
+            return Err("sparrow d should be positive".to_owned());
+        }
+        if !(params.probe_ms >= 0.0 && params.probe_ms.is_finite()) {
+            return Err(format!("sparrow probe_ms should be non-negative, got {}", params.probe_ms));
+        }
+        Ok(params)
+    }
+
+    /// 配置检查用
+    pub fn check_arg(arg: &str) -> Result<(), String> {
+        Self::parse(arg).map(|_| ())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Reservation {
+    nodes: Vec<NodeId>,
+    since_frame: usize,
+}
+
+pub struct SparrowScheduler {
+    params: SparrowParams,
+    /// 延迟绑定时还没绑定的任务
+    reservations: HashMap<(ReqId, FnId), Reservation>,
+    /// 本轮分到各节点的任务数，节点上的任务数在指令应用前还没有更新
+    round_tasks: HashMap<NodeId, usize>,
+    /// 本轮发出的探测消息数
+    round_probes: usize,
+}
+
+impl SparrowScheduler {
+    pub fn new(arg: &str) -> Self {
+        Self {
+            params: SparrowParams::parse(arg).unwrap_or_else(|e| panic!("{}", e)),
+            reservations: HashMap::new(),
+            round_tasks: HashMap::new(),
+            round_probes: 0,
+        }
+    }
+
+    fn queue_len(&self, env: &SimEnvObserve, nid: NodeId) -> usize {
+        env.node(nid).all_task_cnt() + self.round_tasks.get(&nid).copied().unwrap_or(0)
+    }
+
+    /// 没有排队的任务，上一帧 cpu 也没用满
+    fn node_free(&self, env: &SimEnvObserve, nid: NodeId) -> bool {
+        let node = env.node(nid);
+        node.pending_task_cnt() == 0
+            && self.round_tasks.get(&nid).copied().unwrap_or(0) == 0
+            && node.last_frame_cpu < node.rsc_limit.cpu
+    }
+
+    fn sample(&mut self, env: &SimEnvObserve, mech: &MechanismImpl, fnid: FnId) -> Vec<NodeId> {
+        let candidates = (0..env.node_cnt())
+            .filter(|&n| can_host(env, mech, n, fnid))
+            .collect::<Vec<_>>();
+        let sampled = candidates
+            .choose_multiple(&mut rand::thread_rng(), self.params.d)
+            .copied()
+            .collect::<Vec<_>>();
+        self.round_probes += sampled.len();
+        sampled
+    }
+
+    /// 返回要绑定的节点，None 表示继续等
+    fn late_bind(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        reqid: ReqId,
+        fnid: FnId,
+    ) -> Option<(NodeId, Vec<NodeId>)> {
+        let now = env.core().current_frame();
+        if !self.reservations.contains_key(&(reqid, fnid)) {
+            let nodes = self.sample(env, mech, fnid);
+            if nodes.is_empty() {
+                return None;
+            }
+            self.reservations.insert((reqid, fnid), Reservation { nodes, since_frame: now });
+        }
+        let resv = &self.reservations[&(reqid, fnid)];
+        // 预约后节点上的容器可能被缩掉
+        let nodes = resv.nodes
+            .iter()
+            .copied()
+            .filter(|&n| can_host(env, mech, n, fnid))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            self.reservations.remove(&(reqid, fnid));
+            return None;
+        }
+        let waited_out = now.saturating_sub(resv.since_frame) >= LATE_BIND_MAX_WAIT;
+        let bind = nodes
+            .iter()
+            .copied()
+            .filter(|&n| waited_out || self.node_free(env, n))
+            .min_by_key(|&n| self.queue_len(env, n))?;
+        // 空闲节点向调度器要任务的一次往返
+        self.round_probes += 1;
+        self.reservations.remove(&(reqid, fnid));
+        Some((bind, nodes))
+    }
+}
+
+pub const ENTRY: ScheEntry = AlgoEntry {
+    info: AlgoInfo {
+        name: "sparrow",
+        params: &[
+            ParamSpec {
+                name: "d",
+                ty: "usize",
+                default: "2",
+                desc: "每个任务探测的节点数",
+            },
+            ParamSpec {
+                name: "late_binding",
+                ty: "bool",
+                default: "false",
+                desc: "在探测的节点上预约，先空闲的节点执行",
+            },
+            ParamSpec {
+                name: "probe_ms",
+                ty: "f32",
+                default: "0.5",
+                desc: "每条探测消息计入算法耗时的毫秒数",
+            },
+        ],
+        mech_types: &["no_scale", "scale_sche_separated"],
+        parse_params: parse_params::<SparrowParams>,
+        check_attr: SparrowParams::check_arg,
+    },
+    new: |attr| Box::new(SparrowScheduler::new(attr)),
+};
+
+impl Scheduler for SparrowScheduler {
+    // 元组作键的表在 json 中存为按键排序的列表
+    fn save_state(&self) -> Result<Value, String> {
+        let mut reservations = self.reservations.iter().collect::<Vec<_>>();
+        reservations.sort_by_key(|(k, _)| **k);
+        Ok(save_algo_state(&reservations))
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), String> {
+        let reservations: Vec<((ReqId, FnId), Reservation)> = load_algo_state("sparrow", state)?;
+        self.reservations = reservations.into_iter().collect();
+        Ok(())
+    }
+
+    fn schedule_some(
+        &mut self,
+        env: &SimEnvObserve,
+        mech: &MechanismImpl,
+        cmd_distributor: &MechCmdDistributor,
+    ) {
+        self.round_tasks.clear();
+        self.round_probes = 0;
+        let requests = env.core().requests();
+        self.reservations.retain(|(reqid, fnid), _| {
+            requests.get(reqid).is_some_and(|r| !r.fn_node.contains_key(fnid))
+        });
+
+        for (_, req) in requests.iter() {
+            let fns = schedule_helper::collect_task_to_sche(
+                req,
+                env,
+                schedule_helper::CollectTaskConfig::PreAllDone,
+            );
+            for fnid in fns {
+                let chosen = if self.params.late_binding {
+                    self.late_bind(env, mech, req.req_id, fnid)
+                } else {
+                    let sampled = self.sample(env, mech, fnid);
+                    let best = sampled.iter().copied().min_by_key(|&n| self.queue_len(env, n));
+                    best.map(|n| (n, sampled))
+                };
+                let Some((nid, probed)) = chosen else {
+                    continue;
+                };
+
+                env.help().audit().note_candidates(
+                    DecisionKind::Sche,
+                    Some(req.req_id),
+                    fnid,
+                    probed
+                        .iter()
+                        .map(|&n| CandidateScore {
+                            nid: n,
+                            score: Some(self.queue_len(env, n) as f32),
+                        })
+                        .collect(),
+                );
+                *self.round_tasks.entry(nid).or_insert(0) += 1;
+                cmd_distributor
+                    .send(MechScheduleOnceRes::ScheCmd(ScheCmd {
+                        nid,
+                        reqid: req.req_id,
+                        fnid,
+                        memlimit: None,
+                    }))
+                    .unwrap();
+            }
+        }
+        mech.add_sim_overhead_ms(self.round_probes as f32 * self.params.probe_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SparrowParams;
+    use crate::algo_registry::AlgoParams;
+
+    #[test]
+    fn test_sparrow_params() {
+        assert_eq!(SparrowParams::parse("").unwrap(), SparrowParams::default());
+        let p = SparrowParams::parse("d=3, late_binding=true").unwrap();
+        assert_eq!((p.d, p.late_binding, p.probe_ms), (3, true, 0.5));
+        assert_eq!(SparrowParams::parse(&p.to_attr()).unwrap(), p);
+        for bad in ["d=0", "d=x", "late=true", "probe_ms=-1", "3"] {
+            assert!(SparrowParams::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -8,9 +8,24 @@ impl EnvMetricExt for SimEnv {}
 impl EnvMetricExt for SimEnvObserve {}
 
 pub trait EnvMetricExt: WithEnvCore + WithEnvHelp {
-    /// req_done_avg 平均每个请求处理完的时间 越低越好
+    /// 正常完成的请求数，不含超时取消的
+    fn succeeded_req_cnt(&self) -> usize {
+        self.core()
+            .done_requests()
+            .iter()
+            .filter(|req| !req.timed_out())
+            .count()
+    }
+
+    /// 超时取消的请求数
+    fn timed_out_req_cnt(&self) -> usize {
+        self.core().done_requests().len() - self.succeeded_req_cnt()
+    }
+
+    /// req_done_avg 平均每个请求处理完的时间(ms) 越低越好
     fn req_done_time_avg(&self) -> f32 {
-        if self.core().done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
@@ -18,10 +33,11 @@ pub trait EnvMetricExt: WithEnvCore + WithEnvHelp {
             .core()
             .done_requests()
             .iter()
-            .map(|req| (req.end_frame - req.begin_frame) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help().config().frames_to_ms((req.end_frame - req.begin_frame) as f32))
             .sum::<f32>();
 
-        sum / (self.core().done_requests().len() as f32)
+        sum / (cnt as f32)
     }
 
     // 已完成请求的平均成本 越低越好
@@ -49,75 +65,74 @@ pub trait EnvMetricExt: WithEnvCore + WithEnvHelp {
 
 impl SimEnv {
     pub fn req_exe_time_avg(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
         let sum = self.core
             .done_requests_mut()
             .iter_mut()
-            .map(|req| req.exe_time(self) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help.config().frames_to_ms(req.exe_time(self) as f32))
             .sum::<f32>();
 
-        sum / (self.core.done_requests().len() as f32)
+        sum / (cnt as f32)
     }
     pub fn req_wait_sche_time_avg(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
         let sum = self.core
             .done_requests_mut()
             .iter_mut()
-            .map(|req| req.wait_sche_time(self) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help.config().frames_to_ms(req.wait_sche_time(self) as f32))
             .sum::<f32>();
 
-        sum / (self.core.done_requests().len() as f32)
+        sum / (cnt as f32)
     }
     pub fn req_data_recv_time_avg(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
         let sum = self.core
             .done_requests_mut()
             .iter_mut()
-            .map(|req| req.data_recv_time(self) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help.config().frames_to_ms(req.data_recv_time(self) as f32))
             .sum::<f32>();
 
-        sum / (self.core.done_requests().len() as f32)
+        sum / (cnt as f32)
     }
     pub fn req_wait_coldstart_time_avg(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
         let sum = self.core
             .done_requests_mut()
             .iter_mut()
-            .map(|req| req.wait_cold_start_time(self) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help.config().frames_to_ms(req.wait_cold_start_time(self) as f32))
             .sum::<f32>();
 
-        sum / (self.core.done_requests().len() as f32)
+        sum / (cnt as f32)
     }
-    /// req_done_avg 平均每个请求处理完的时间 越低越好
+    /// req_done_avg 平均每个请求处理完的时间(ms) 越低越好
     pub fn req_done_time_avg(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
-            return 0.0;
-        }
-
-        let sum = self.core
-            .done_requests()
-            .iter()
-            .map(|req| (req.end_frame - req.begin_frame) as f32)
-            .sum::<f32>();
-
-        sum / (self.core.done_requests().len() as f32)
+        EnvMetricExt::req_done_time_avg(self)
     }
 
     /// req_done_std 平均每个请求处理完的时间的标准差 越低越好
     pub fn req_done_time_std(&self) -> f32 {
-        if self.core.done_requests().len() == 0 {
+        let cnt = self.succeeded_req_cnt();
+        if cnt == 0 {
             return 0.0;
         }
 
@@ -125,10 +140,10 @@ impl SimEnv {
         let sum = self.core
             .done_requests()
             .iter()
-            // .filter(|req| req.is_done(self))
-            .map(|req| (((req.end_frame - req.begin_frame) as f32) - avg).powi(2))
+            .filter(|req| !req.timed_out())
+            .map(|req| (self.help.config().frames_to_ms((req.end_frame - req.begin_frame) as f32) - avg).powi(2))
             .sum::<f32>();
-        (sum / (self.core.done_requests().len() as f32)).sqrt()
+        (sum / (cnt as f32)).sqrt()
     }
 
     /// req_done_90 90%的请求处理完的时间 越低越好
@@ -136,8 +151,8 @@ impl SimEnv {
         let mut req_done_times = self.core
             .done_requests()
             .iter()
-            // .filter(|req| req.is_done(self))
-            .map(|req| (req.end_frame - req.begin_frame) as f32)
+            .filter(|req| !req.timed_out())
+            .map(|req| self.help.config().frames_to_ms((req.end_frame - req.begin_frame) as f32))
             .collect::<Vec<f32>>();
         req_done_times.sort_by(|a, b| a.partial_cmp(b).expect("can't cmp f32"));
         let req_done_90p_cnt = req_done_times.len() * (0.9 as usize);
@@ -194,6 +209,28 @@ impl SimEnv {
 
         score -= self.req_done_time_avg();
 
+        // 超时的请求按已等待的时间加 penalty_ms 算
+        let mut done = self.succeeded_req_cnt() as f32;
+        let timeout_penalty_ms = self.help.config().timeout.penalty_ms;
+        let timed_out = self.core
+            .done_requests()
+            .iter()
+            .filter(|req| req.timed_out())
+            .map(|req| self.help.config().frames_to_ms((req.end_frame - req.begin_frame) as f32) + timeout_penalty_ms)
+            .collect::<Vec<_>>();
+        if !timed_out.is_empty() {
+            let cnt = timed_out.len() as f32;
+            score = (score * done - timed_out.iter().sum::<f32>()) / (done + cnt);
+            done += cnt;
+        }
+
+        // 被准入控制丢弃的请求按延迟 penalty_ms 的完成请求算
+        let penalty_ms = self.help.config().admission.penalty_ms;
+        let shed = self.help.admission().stats.shed();
+        if penalty_ms > 0.0 && shed > 0 {
+            score = (score * done - penalty_ms * (shed as f32)) / (done + (shed as f32));
+        }
+
         score
     }
 }
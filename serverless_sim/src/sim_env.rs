@@ -3,24 +3,32 @@ use std::{
     collections::{BTreeMap, HashMap, HashSet},
     process::Command,
     str,
-    sync::mpsc,
+    sync::{mpsc, Arc},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use parking_lot::Mutex;
 use rand_pcg::Pcg64;
 use rand_seeder::Seeder;
 
 use crate::{
     actions::ESActionWrapper,
-    config::Config,
+    admission::AdmissionState,
+    audit::AuditHub,
+    config::{config_errors_str, Config},
     fn_dag::{DagId, FnDAG, FnId, Func},
-    mechanism::ConfigNewMec,
+    mechanism::{ConfigNewMec, MechanismImpl},
     mechanism_thread::{self, MechScheduleOnce},
     metric::{MechMetric, OneFrameMetric, Recorder, Records},
     node::{Node, NodeId},
     request::{ReqId, Request},
+    reward::{self, RewardTracker},
+    rl_target::RlAgentHub,
+    sim_timer::Timer,
     scale::{down_exec::DefaultScaleDownExec, num::ScaleNum, up_exec::ScaleUpExec},
     sim_run::Scheduler,
+    spaces::Space,
+    telemetry::TelemetryHub,
     with_env_sub::WithEnvHelp,
     CONTAINER_BASIC_MEM,
 };
@@ -76,6 +84,14 @@ pub struct SimEnvHelperState {
     pub dag_accumulate_call_frequency: RefCell<BTreeMap<DagId, f64>>,
     // key: frame_idx  value: exe_time
     algo_exc_time: RefCell<HashMap<usize, usize>>,
+    // 每个 env 自己的 rl agent，和机制线程共享
+    rl_agent: Arc<RlAgentHub>,
+    reward: RefCell<RewardTracker>,
+    admission: RefCell<AdmissionState>,
+    // 逐帧遥测的订阅者，reset 时保留
+    telemetry: Arc<TelemetryHub>,
+    // 调度、扩缩容决策日志，和机制线程共享
+    audit: Arc<AuditHub>,
 }
 
 impl Clone for SimEnvHelperState {
@@ -91,6 +107,11 @@ impl Clone for SimEnvHelperState {
             mech_metric: self.mech_metric.clone(),
             algo_exc_time: self.algo_exc_time.clone(),
             dag_accumulate_call_frequency: BTreeMap::new().into(),
+            rl_agent: self.rl_agent.clone(),
+            reward: self.reward.clone(),
+            admission: self.admission.clone(),
+            telemetry: self.telemetry.clone(),
+            audit: self.audit.clone(),
         }
     }
 }
@@ -106,6 +127,38 @@ impl SimEnvHelperState {
         *self.req_next_id.borrow_mut() += 1;
         ret
     }
+    // 下一个请求id和函数id，快照用
+    pub fn next_ids(&self) -> (ReqId, FnId) {
+        (*self.req_next_id.borrow(), *self.fn_next_id.borrow())
+    }
+    pub fn set_next_ids(&self, req_next_id: ReqId, fn_next_id: FnId) {
+        *self.req_next_id.borrow_mut() = req_next_id;
+        *self.fn_next_id.borrow_mut() = fn_next_id;
+    }
+    pub fn rl_agent(&self) -> &Arc<RlAgentHub> {
+        &self.rl_agent
+    }
+    pub fn telemetry(&self) -> &Arc<TelemetryHub> {
+        &self.telemetry
+    }
+    pub fn set_telemetry(&mut self, telemetry: Arc<TelemetryHub>) {
+        self.telemetry = telemetry;
+    }
+    pub fn audit(&self) -> &Arc<AuditHub> {
+        &self.audit
+    }
+    pub fn reward<'a>(&'a self) -> Ref<'a, RewardTracker> {
+        self.reward.borrow()
+    }
+    pub fn reward_mut<'a>(&'a self) -> RefMut<'a, RewardTracker> {
+        self.reward.borrow_mut()
+    }
+    pub fn admission<'a>(&'a self) -> Ref<'a, AdmissionState> {
+        self.admission.borrow()
+    }
+    pub fn admission_mut<'a>(&'a self) -> RefMut<'a, AdmissionState> {
+        self.admission.borrow_mut()
+    }
     pub fn config<'a>(&'a self) -> &'a Config {
         &self.config
     }
@@ -282,8 +335,8 @@ impl SimEnvMechanisms {}
 pub struct SimEnv {
     pub recent_use_time: Duration,
     pub rander: RefCell<Pcg64>,
-    // end time - tasks
-    pub timers: RefCell<HashMap<usize, Vec<Box<dyn FnMut(&SimEnv) + Send>>>>,
+    // end frame - tasks，按帧计
+    pub timers: RefCell<BTreeMap<usize, Vec<Timer>>>,
 
     pub help: SimEnvHelperState,
     pub core: SimEnvCoreState,
@@ -291,6 +344,12 @@ pub struct SimEnv {
     // pub new_mech: MechanismImpl,
     pub master_mech_not_running: bool,
     pub mech_caller: mpsc::Sender<MechScheduleOnce>,
+    /// 和机制线程共享，快照时读写算法的内部状态
+    pub mech: Arc<Mutex<MechanismImpl>>,
+    /// 事件驱动模式下，跳帧时已经提前生成过请求的帧
+    pub req_pregen_frame: RefCell<Option<usize>>,
+    /// 由扩缩容算法声明，机制线程启动前取出
+    pub action_space: Space,
 }
 
 impl SimEnv {
@@ -299,20 +358,34 @@ impl SimEnv {
         let start = SystemTime::now();
         let recent_use_time = start.duration_since(UNIX_EPOCH).unwrap();
 
+        let mech = Arc::new(
+            Mutex::new(
+                config
+                    .new_mec()
+                    .unwrap_or_else(|errs| panic!("invalid config: {}", config_errors_str(&errs)))
+            )
+        );
+        // 机制线程在函数生成之后再启动，先占位
+        let (mech_caller, _) = mpsc::channel();
         // let args = parse_arg::get_arg();
-        let newenv = Self {
+        let mut newenv = Self {
             help: SimEnvHelperState {
                 // nodes: vec![Node::new(0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0)],
                 req_next_id: RefCell::new(0),
                 fn_next_id: RefCell::new(0),
                 cost: RefCell::new(0.00000001),
                 metric: RefCell::new(OneFrameMetric::new()),
-                metric_record: RefCell::new(Some(Recorder::new(config.str()))),
+                metric_record: RefCell::new(Some(Recorder::new(&config))),
                 config: config.clone(),
                 mech_metric: RefCell::new(MechMetric::new()),
                 dag_call_frequency: RefCell::new(BTreeMap::new()),
                 algo_exc_time: RefCell::new(HashMap::new()),
                 dag_accumulate_call_frequency: RefCell::new(BTreeMap::new()),
+                rl_agent: Arc::new(RlAgentHub::new()),
+                reward: RefCell::new(RewardTracker::default()),
+                admission: RefCell::new(AdmissionState::default()),
+                telemetry: Arc::new(TelemetryHub::new()),
+                audit: Arc::new(AuditHub::new(&config)),
             },
             core: SimEnvCoreState {
                 node2node_graph: RefCell::new(Vec::new()),
@@ -335,12 +408,18 @@ impl SimEnv {
             master_mech_not_running: true,
             recent_use_time,
             rander: RefCell::new(Seeder::from(&*config.rand_seed).make_rng()),
-            timers: HashMap::new().into(),
-            mech_caller: mechanism_thread::spawn(config.new_mec().unwrap()),
+            timers: BTreeMap::new().into(),
+            mech_caller,
+            mech: mech.clone(),
+            req_pregen_frame: RefCell::new(None),
+            action_space: Space::Discrete { n: 1 },
         };
 
         // 为模拟环境创建所有的dag、node、func
         newenv.init();
+        // 动作空间可能和函数数量有关
+        newenv.action_space = mech.lock().action_space(newenv.core.fns().len());
+        newenv.mech_caller = mechanism_thread::spawn(mech);
         newenv
     }
     pub fn reset(&mut self) {
@@ -425,10 +504,23 @@ impl SimEnv {
     }
 
     // 根据给定的 raw_action，执行仿真环境的一个时间步，返回 score 和 state
+    // score 为 reward 模块按 config.reward 计算的奖励
     pub fn step(&mut self, raw_action: u32) -> (f32, String) {
+        self.step_action(ESActionWrapper::Int(raw_action))
+    }
+
+    // 和 step 相同，action 可以是 action_space 解码出的任意类型
+    pub fn step_action(&mut self, action: ESActionWrapper) -> (f32, String) {
         // update to current time
         self.avoid_gc();
-        self.step_es(ESActionWrapper::Int(raw_action), None, None, None, None)
+        reward::begin_step(self);
+        let (_, state) = if self.help.config().event_driven {
+            self.step_event_driven(action.clone(), None, None)
+        } else {
+            self.step_es(action.clone(), None, None, None, None)
+        };
+        let breakdown = reward::end_step(self, &action);
+        (breakdown.reward, state)
     }
 
     // 在模拟一帧开始时调用，更新节点状态、清空已完成请求、重置性能指标等
@@ -467,11 +559,7 @@ impl SimEnv {
         self.help.metric.borrow_mut().on_frame_begin();
 
         // timer
-        if let Some(timers) = self.timers.borrow_mut().remove(&self.current_frame()) {
-            for mut timer in timers {
-                timer(self);
-            }
-        }
+        self.fire_timers();
 
         // *self.distance2hpa.borrow_mut() = 0;
     }
@@ -495,9 +583,14 @@ impl SimEnv {
             }
             // 更新模拟环境的总成本
             let mut cost = self.help.cost_mut();
-            *cost += n.cpu * 0.00001 + n.unready_mem() * 0.00001;
+            *cost += n.cpu * 0.00001
+                + n.unready_mem() * 0.00001 * self.help.config().frame_duration_ms;
         }
 
+        // 奖励用的新建容器数和空闲内存，推送遥测
+        self.reward_record_frames(1);
+        self.telemetry_publish(1);
+
         // 将这一帧的数据记录到表中
         self.help
             .metric_record_mut()
@@ -0,0 +1,589 @@
+//! 事件驱动的仿真推进方式
+//!
+//! 逐帧推进时，即使几百帧内没有任何状态变化，也要遍历所有节点、容器和传输路径。
+//! 这里在每个完整模拟的帧结束后，预测各实体(冷启动、数据传输、计算)的完成帧，
+//! 放入优先队列，然后把中间的空闲帧批量推进，直接跳到下一个事件所在的帧。
+//!
+//! 事件帧本身仍然调用 `one_frame` 完整模拟，因此 `sim_events::on_*` 回调和
+//! `start_timer` 的语义保持不变；被跳过的帧不调用 hook，但和逐帧推进一样写入 records。
+//! 算法在每一帧(包括被跳过的帧)开始前基于当前状态决策，指令在下一帧开始前应用，
+//! 算法下发指令后停止跳帧；no_mech_latency 为 false 时，算法耗时对应的帧内不再启动算法，
+//! 和 step_es 中算法比仿真快的情况一致。
+//! 确定性的算法(包括 hpa 等有内部状态的扩缩容算法)在两种推进方式下得到相同的请求完成时间。
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap},
+};
+
+use crate::{
+    actions::ESActionWrapper,
+    fn_dag::{EnvFnExt, FnContainerState, FnId},
+    node::{EnvNodeExt, Node, NodeId},
+    request::ReqId,
+    sim_env::SimEnv,
+    sim_loop::SimHook,
+    CONTAINER_BASIC_MEM,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimEvent {
+    /// 容器冷启动完成
+    ColdStartDone { node: NodeId, fnid: FnId },
+    /// 一条数据传输路径传输完成
+    TransferDone { node: NodeId, fnid: FnId, reqid: ReqId },
+    /// 任务计算完成
+    ComputeDone { node: NodeId, fnid: FnId, reqid: ReqId },
+    /// start_timer 注册的定时器到期
+    Timer,
+    /// 有请求超时，见 timeout
+    Timeout,
+    /// 有待加载的任务、待调度的函数或本地数据，下一帧必须完整模拟
+    Busy,
+    /// 仿真的最后一帧
+    SimEnd,
+}
+
+pub struct SimEventQueue {
+    heap: BinaryHeap<Reverse<(usize, SimEvent)>>,
+}
+
+impl Default for SimEventQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimEventQueue {
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+        }
+    }
+    pub fn push(&mut self, frame: usize, event: SimEvent) {
+        self.heap.push(Reverse((frame, event)));
+    }
+    pub fn peek_frame(&self) -> Option<usize> {
+        self.heap.peek().map(|Reverse((frame, _))| *frame)
+    }
+    pub fn pop(&mut self) -> Option<(usize, SimEvent)> {
+        self.heap.pop().map(|Reverse(v)| v)
+    }
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+/// 两个事件之间，节点上保持不变的计算状态
+struct NodeSteady {
+    /// 每个任务分到的cpu
+    each_fn_cpu: f32,
+    /// (fnid, reqid)，与 sim_compute_collect_compute_data 的结果一致
+    run: BTreeSet<(FnId, ReqId)>,
+    starting_cnt: usize,
+    mem: f32,
+}
+
+/// 一条跨节点的数据传输路径
+struct PathSteady {
+    send_node: NodeId,
+    recv_node: NodeId,
+    reqid: ReqId,
+    fnid: FnId,
+}
+
+struct SteadyState {
+    nodes: Vec<Option<NodeSteady>>,
+    paths: Vec<PathSteady>,
+    /// (min node, max node) - path count
+    pair_path_cnt: HashMap<(NodeId, NodeId), usize>,
+    busy: bool,
+}
+
+// 需要多少帧才能把 left 消耗完，至少一帧
+fn frames_to_done(left: f32, per_frame: f32) -> usize {
+    ((left / per_frame).ceil() as usize).max(1)
+}
+
+impl SimEnv {
+    /// 事件驱动版本的 step_es，空闲帧批量推进，算法仍然每帧运行
+    pub fn step_event_driven(
+        &mut self,
+        raw_action: ESActionWrapper,
+        mut hook_frame_begin: SimHook,
+        mut hook_req_gen: SimHook,
+    ) -> (f32, String) {
+        self.avoid_gc();
+        let mut pregen_from = None;
+        let mut mech_next_frame = 0;
+        loop {
+            let pregen = pregen_from.take();
+            let mech_begin_frame = self.current_frame();
+            let mech_rx = (mech_begin_frame >= mech_next_frame)
+                .then(|| self.mech_once_begin(&raw_action, pregen));
+            let running = self.one_frame(&mut hook_frame_begin, &mut hook_req_gen);
+            // 与逐帧推进一致，算法基于帧开始前的状态决策，指令在下一帧开始前应用
+            if let Some(rx) = mech_rx {
+                let (mech_run_ms, _) = self.mech_once_end(&rx, running);
+                mech_next_frame = mech_begin_frame + self.mech_run_frames(mech_run_ms).max(1);
+            }
+            if !running {
+                log::info!("simulation end");
+                break;
+            }
+
+            let steady = self.event_steady_state();
+            let mut queue = SimEventQueue::new();
+            self.event_predict(&steady, &mut queue);
+            let (next, event) = queue.pop().unwrap();
+            let (skipped, first_new_req) =
+                self.event_skip_to(next, &steady, &raw_action, &mut mech_next_frame);
+            pregen_from = first_new_req;
+            if skipped > 0 {
+                log::info!(
+                    "skip {} idle frames to frame {} for {:?}",
+                    skipped,
+                    self.current_frame(),
+                    event
+                );
+            }
+        }
+
+        (0.0, "no action".to_string())
+    }
+
+    // 只读地复现 sim_compute_collect_compute_data 的收集逻辑
+    fn event_node_steady(&self, n: &Node) -> Option<NodeSteady> {
+        let containers = n.fn_containers.borrow();
+        let starting_cnt = containers
+            .iter()
+            .filter(|(_, fc)| !fc.is_running())
+            .count();
+        let mut mem: f32 = containers
+            .values()
+            .map(|c| c.container_basic_mem(self))
+            .sum();
+        let mut run = BTreeSet::new();
+        for (&fnid, fc) in containers.iter() {
+            if !fc.is_running() {
+                continue;
+            }
+            for (&req_id, task) in &fc.req_fn_state {
+                if task.data_recv_done() && n.rsc_limit.mem - mem > self.func(fnid).mem {
+                    mem += self.func(fnid).mem;
+                    run.insert((fnid, req_id));
+                }
+            }
+        }
+        if run.is_empty() && starting_cnt == 0 {
+            return None;
+        }
+        Some(NodeSteady {
+            each_fn_cpu: n.rsc_limit.cpu / ((run.len() + starting_cnt) as f32),
+            run,
+            starting_cnt,
+            mem,
+        })
+    }
+
+    fn event_steady_state(&self) -> SteadyState {
+        let mut busy = self.core.requests().iter().any(|(_, req)| {
+            req.fn_node.len() < self.dag_inner(req.dag_i).node_count()
+        });
+        let mut nodes = vec![];
+        let mut paths = vec![];
+        let mut pair_path_cnt = HashMap::new();
+        for n in self.nodes().iter() {
+            if n.pending_task_cnt() > 0 {
+                busy = true;
+            }
+            nodes.push(self.event_node_steady(n));
+
+            let node_id = n.node_id();
+            for (&fnid, fc) in n.fn_containers.borrow().iter().filter(|(_, c)| c.is_running()) {
+                for (&reqid, task) in fc
+                    .req_fn_state
+                    .iter()
+                    .filter(|(reqid, _)| self.request(**reqid).parents_all_done(self, fnid))
+                {
+                    for (&send_node, (all, recved)) in &task.data_recv {
+                        if *all - *recved <= 0.00001 {
+                            continue;
+                        }
+                        if send_node == node_id {
+                            // 本地数据下一帧直接标记完成
+                            busy = true;
+                            continue;
+                        }
+                        *pair_path_cnt
+                            .entry((send_node.min(node_id), send_node.max(node_id)))
+                            .or_insert(0) += 1;
+                        paths.push(PathSteady {
+                            send_node,
+                            recv_node: node_id,
+                            reqid,
+                            fnid,
+                        });
+                    }
+                }
+            }
+        }
+        SteadyState {
+            nodes,
+            paths,
+            pair_path_cnt,
+            busy,
+        }
+    }
+
+    /// 预测各个实体的完成帧
+    ///
+    /// 浮点累加可能和预测存在误差，计算和传输事件都提前一帧，
+    /// 保证完成的那一帧一定被完整模拟
+    fn event_predict(&self, steady: &SteadyState, queue: &mut SimEventQueue) {
+        let cur = self.current_frame();
+        queue.push(self.help.config().total_frames(), SimEvent::SimEnd);
+        if steady.busy {
+            queue.push(cur, SimEvent::Busy);
+        }
+        for (&frame, _) in self.timers.borrow().iter() {
+            queue.push(frame.max(cur), SimEvent::Timer);
+        }
+        if let Some(frame) = self.timeout_next_deadline() {
+            queue.push(frame.max(cur), SimEvent::Timeout);
+        }
+
+        for (node, n) in self.nodes().iter().enumerate() {
+            let Some(ns) = &steady.nodes[node] else {
+                continue;
+            };
+            for (&fnid, fc) in n.fn_containers.borrow().iter() {
+                match fc.state() {
+                    FnContainerState::Starting { left_frame } => {
+                        queue.push(cur + *left_frame - 1, SimEvent::ColdStartDone { node, fnid });
+                    }
+                    FnContainerState::Running => {
+                        for (&reqid, task) in &fc.req_fn_state {
+                            if !ns.run.contains(&(fnid, reqid)) {
+                                continue;
+                            }
+                            let frames = frames_to_done(task.left_calc, ns.each_fn_cpu);
+                            queue.push(
+                                cur + frames.saturating_sub(2),
+                                SimEvent::ComputeDone { node, fnid, reqid },
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        for p in &steady.paths {
+            let key = (p.send_node.min(p.recv_node), p.send_node.max(p.recv_node));
+            let each_path_bandwith = self.node_get_trans_per_frame_btwn(key.1, key.0)
+                / (steady.pair_path_cnt[&key] as f32);
+            let event = SimEvent::TransferDone {
+                node: p.recv_node,
+                fnid: p.fnid,
+                reqid: p.reqid,
+            };
+            if each_path_bandwith <= 0.0 {
+                queue.push(cur, event);
+                continue;
+            }
+            let node = self.node(p.recv_node);
+            let fc = node.container(p.fnid).unwrap();
+            let (all, recved) = fc.req_fn_state[&p.reqid].data_recv[&p.send_node];
+            let frames = frames_to_done(all - recved - 0.00001, each_path_bandwith);
+            queue.push(cur + frames.saturating_sub(2), event);
+        }
+    }
+
+    /// 从当前帧逐帧推进到 to_frame，返回跳过的帧数和提前生成的第一个请求
+    ///
+    /// 跳过的帧内仍然逐帧生成请求，一旦有新请求到达，就停在那一帧完整模拟；
+    /// 算法照常在每帧开始前运行(有耗时时按 mech_next_frame 隔帧运行)，
+    /// 一旦下发了指令，状态不再稳定，停在下一帧完整模拟
+    fn event_skip_to(
+        &self,
+        to_frame: usize,
+        steady: &SteadyState,
+        raw_action: &ESActionWrapper,
+        mech_next_frame: &mut usize,
+    ) -> (usize, Option<ReqId>) {
+        let begin = self.current_frame();
+        for frame in begin..to_frame {
+            let (next_reqid, _) = self.help.next_ids();
+            let req_cnt = self.core.requests().len();
+            self.req_sim_gen_requests();
+            if self.core.requests().len() > req_cnt {
+                *self.req_pregen_frame.borrow_mut() = Some(frame);
+                return (frame - begin, Some(next_reqid));
+            }
+
+            let mech_rx = (frame >= *mech_next_frame).then(|| self.mech_once_begin(raw_action, None));
+            self.help.metric_mut().on_frame_begin();
+            self.help.mech_metric_mut().on_new_req_generated(self);
+            self.event_advance_frame(steady);
+            *self.core.current_frame_mut() = frame + 1;
+            if let Some(rx) = mech_rx {
+                let (mech_run_ms, has_cmd) = self.mech_once_end(&rx, true);
+                *mech_next_frame = frame + self.mech_run_frames(mech_run_ms).max(1);
+                if has_cmd {
+                    return (frame + 1 - begin, None);
+                }
+            }
+        }
+        (to_frame.max(begin) - begin, None)
+    }
+
+    // 按照稳定状态推进一帧，对应 one_frame 中 on_frame_begin 之后的模拟和 on_frame_end 的统计
+    fn event_advance_frame(&self, steady: &SteadyState) {
+        for p in &steady.paths {
+            let key = (p.send_node.min(p.recv_node), p.send_node.max(p.recv_node));
+            let each_path_bandwith = self.node_get_trans_per_frame_btwn(key.1, key.0)
+                / (steady.pair_path_cnt[&key] as f32);
+            let node = self.node(p.recv_node);
+            let mut fc = node.container_mut(p.fnid).unwrap();
+            fc.this_frame_used = true;
+            let (_, recved) = fc
+                .req_fn_state
+                .get_mut(&p.reqid)
+                .unwrap()
+                .data_recv
+                .get_mut(&p.send_node)
+                .unwrap();
+            *recved += each_path_bandwith;
+        }
+
+        for (node, n) in self.nodes_mut().iter_mut().enumerate() {
+            n.last_frame_cpu = n.cpu;
+            n.last_frame_mem = n.unready_mem();
+            let Some(ns) = &steady.nodes[node] else {
+                // 节点空闲，只有空的运行中容器
+                for fc in n.fn_containers.borrow_mut().values_mut() {
+                    fc.last_frame_cpu_used = 0.0;
+                    fc.set_cpu_use_rate(1.0, 0.0);
+                    fc.last_frame_mem = fc.mem_use;
+                    fc.mem_use = CONTAINER_BASIC_MEM;
+                    fc.record_this_frame(self, 0, fc.req_fn_state.len());
+                }
+                let idle_mem: f32 = n
+                    .fn_containers
+                    .borrow()
+                    .values()
+                    .map(|c| c.container_basic_mem(self))
+                    .sum();
+                n.cpu = 0.0;
+                *n.unready_mem_mut() = idle_mem;
+                continue;
+            };
+            let mut node_cpu = 0.0;
+            for (&fnid, fc) in n.fn_containers.borrow_mut().iter_mut() {
+                let mut calc_cnt = 0;
+                match fc.state_mut() {
+                    FnContainerState::Starting { left_frame } => {
+                        *left_frame -= 1;
+                        let used = ns.each_fn_cpu.min(self.func(fnid).cold_start_container_cpu_use);
+                        fc.set_cpu_use_rate(ns.each_fn_cpu, used);
+                        continue;
+                    }
+                    FnContainerState::Running => {}
+                }
+                for (&reqid, task) in fc.req_fn_state.iter_mut() {
+                    if !ns.run.contains(&(fnid, reqid)) {
+                        continue;
+                    }
+                    calc_cnt += 1;
+                    task.left_calc -= ns.each_fn_cpu;
+                    node_cpu += ns.each_fn_cpu;
+                }
+                fc.last_frame_mem = fc.mem_use;
+                fc.mem_use = CONTAINER_BASIC_MEM + self.func(fnid).mem * (calc_cnt as f32);
+                if calc_cnt > 0 {
+                    fc.this_frame_used = true;
+                    let cpu = ns.each_fn_cpu * (calc_cnt as f32);
+                    fc.last_frame_cpu_used = cpu;
+                    fc.set_cpu_use_rate(cpu, cpu);
+                } else {
+                    fc.last_frame_cpu_used = 0.0;
+                    fc.set_cpu_use_rate(1.0, 0.0);
+                }
+                fc.record_this_frame(self, 0, fc.req_fn_state.len());
+            }
+            n.cpu = node_cpu;
+            *n.unready_mem_mut() = ns.mem;
+            n.frame_run_count = ns.run.len() + ns.starting_cnt;
+        }
+
+        // 对应 on_frame_end 中的容器使用次数和成本统计
+        let frame_ms = self.help.config().frame_duration_ms;
+        for n in self.nodes_mut().iter_mut() {
+            for c in n.fn_containers.borrow_mut().values_mut() {
+                if c.this_frame_used {
+                    c.this_frame_used = false;
+                    c.used_times += 1;
+                }
+            }
+            *self.help.cost_mut() += n.cpu * 0.00001 + n.unready_mem() * 0.00001 * frame_ms;
+        }
+        self.reward_record_frames(1);
+        self.telemetry_publish(1);
+        // 跳过的帧和完整模拟的帧一样逐帧写入 records
+        self.help.metric_record_mut().as_mut().unwrap().add_frame(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        collections::HashMap,
+        rc::Rc,
+        sync::{mpsc, Arc, Mutex},
+    };
+
+    use super::{frames_to_done, SimEvent, SimEventQueue};
+    use crate::{
+        actions::ESActionWrapper,
+        config::Config,
+        mechanism_thread::{MechScheduleOnce, MechScheduleOnceRes},
+        node::EnvNodeExt,
+        sim_env::SimEnv,
+        sim_loop::SimHook,
+        with_env_sub::WithEnvCore,
+    };
+
+    fn select(conf: &mut HashMap<String, Option<String>>, name: &str) {
+        for (k, v) in conf.iter_mut() {
+            *v = if k == name { Some(String::new()) } else { None };
+        }
+    }
+
+    // 已完成请求的 (reqid, begin_frame, end_frame) 和完整模拟的帧数
+    fn run(scale_num: &str, event_driven: bool) -> (Vec<(usize, usize, usize)>, usize) {
+        let mut config = Config::new_test();
+        config.total_frame = 300;
+        config.event_driven = event_driven;
+        // 算法要是确定性的，两种推进方式才能逐帧对上
+        if scale_num == "no" {
+            select(&mut config.mech.mech_type, "no_scale");
+            select(&mut config.mech.scale_up_exec, "no");
+            for v in config.mech.filter.values_mut() {
+                *v = None;
+            }
+        }
+        select(&mut config.mech.scale_num, scale_num);
+        select(&mut config.mech.sche, "greedy");
+        select(&mut config.mech.instance_cache_policy, "no_evict");
+        let mut env = SimEnv::new(config);
+        let full_frames = Rc::new(Cell::new(0));
+        let counter = full_frames.clone();
+        let hook: SimHook = Some(Box::new(move |_: &SimEnv| counter.set(counter.get() + 1)));
+        if event_driven {
+            env.step_event_driven(ESActionWrapper::Int(1), hook, None);
+        } else {
+            env.step_es(ESActionWrapper::Int(1), hook, None, None, None);
+        }
+        let mut done = env.core
+            .done_requests()
+            .iter()
+            .map(|r| (r.req_id, r.begin_frame, r.end_frame))
+            .collect::<Vec<_>>();
+        done.sort();
+        (done, full_frames.get())
+    }
+
+    #[test]
+    fn test_event_driven_same_as_fixed_frame() {
+        // hpa、kpa、forecast 有内部状态，决策依赖每一帧的观测
+        for scale_num in ["no", "hpa", "kpa", "forecast"] {
+            let (fixed, fixed_frames) = run(scale_num, false);
+            assert!(!fixed.is_empty(), "{}", scale_num);
+            let (event, event_frames) = run(scale_num, true);
+            assert_eq!(event, fixed, "{}", scale_num);
+            // 确实跳过了一些帧
+            assert!(event_frames < fixed_frames, "{}: {} frames", scale_num, event_frames);
+        }
+    }
+
+    #[test]
+    fn test_mech_latency_frames() {
+        let mut config = Config::new_test();
+        config.total_frame = 50;
+        config.no_mech_latency = false;
+        config.frame_duration_ms = 2.0;
+        let mut env = SimEnv::new(config);
+        // 算法每次耗时 10ms，即 5 帧，期间不再启动
+        let (tx, rx) = mpsc::channel::<MechScheduleOnce>();
+        env.mech_caller = tx;
+        let launch_frames = Arc::new(Mutex::new(vec![]));
+        {
+            let launch_frames = launch_frames.clone();
+            std::thread::spawn(move || {
+                while let Ok(once) = rx.recv() {
+                    launch_frames.lock().unwrap().push(once.sim_env.core().current_frame());
+                    let _ = once.responser.send(MechScheduleOnceRes::End { mech_run_ms: 10 });
+                }
+            });
+        }
+        env.step_event_driven(ESActionWrapper::Int(0), None, None);
+        let launch_frames = launch_frames.lock().unwrap().clone();
+        assert_eq!(launch_frames, (0..=50).step_by(5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_cold_start_done_frame() {
+        let mut config = Config::new_test();
+        config.total_frame = 300;
+        let mut env = SimEnv::new(config);
+        env.node_mut(0).try_load_container(0, &env);
+
+        let steady = env.event_steady_state();
+        let mut queue = SimEventQueue::new();
+        env.event_predict(&steady, &mut queue);
+        let mut predicted = None;
+        while let Some((frame, event)) = queue.pop() {
+            if event == (SimEvent::ColdStartDone { node: 0, fnid: 0 }) {
+                predicted = Some(frame);
+            }
+        }
+        let predicted = predicted.unwrap();
+
+        // 逐帧推进，容器在预测的那一帧内变为运行状态
+        let (mut hook_frame_begin, mut hook_req_gen) = (None, None);
+        loop {
+            let frame = env.current_frame();
+            assert!(frame <= predicted);
+            env.one_frame(&mut hook_frame_begin, &mut hook_req_gen);
+            if env.node(0).container(0).unwrap().is_running() {
+                assert_eq!(frame, predicted);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_queue_order() {
+        let mut queue = SimEventQueue::new();
+        queue.push(10, SimEvent::SimEnd);
+        queue.push(3, SimEvent::ColdStartDone { node: 1, fnid: 2 });
+        queue.push(5, SimEvent::Timer);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek_frame(), Some(3));
+        assert_eq!(queue.pop().unwrap().0, 3);
+        assert_eq!(queue.pop().unwrap().1, SimEvent::Timer);
+        assert_eq!(queue.pop().unwrap().0, 10);
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn test_frames_to_done() {
+        assert_eq!(frames_to_done(100.0, 50.0), 2);
+        assert_eq!(frames_to_done(101.0, 50.0), 3);
+        assert_eq!(frames_to_done(0.0, 50.0), 1);
+    }
+}
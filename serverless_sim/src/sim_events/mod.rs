@@ -1,5 +1,6 @@
 pub mod on_fnins_cold_started;
 pub mod on_task_data_recved;
 pub mod on_task_done;
+pub mod on_task_preempted;
 pub mod on_task_ready_schedule;
 pub mod on_task_scheduled;
@@ -0,0 +1,12 @@
+use crate::{fn_dag::FnId, request::Request, sim_env::SimEnv};
+
+impl SimEnv {
+    pub fn on_task_preempted(&self, req: &mut Request, fnid: FnId) {
+        // 重新调度后再记录，ready_sche_time 保留，等待调度的时间包含被抢占前后的全部时间
+        let fnmetric = req.fn_metric.get_mut(&fnid).unwrap();
+        assert!(fnmetric.fn_done_time.is_none());
+        fnmetric.sche_time = None;
+        fnmetric.data_recv_done_time = None;
+        fnmetric.cold_start_done_time = None;
+    }
+}
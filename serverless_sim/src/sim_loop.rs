@@ -3,10 +3,13 @@ use thread_priority::{set_current_thread_priority, ThreadPriority};
 
 use crate::{
     actions::ESActionWrapper,
-    mechanism::SimEnvObserve,
+    audit::{DecisionKind, DecisionOutcome},
+    fn_dag::EnvFnExt,
+    mechanism::{DownCmd, PreemptCmd, ScheCmd, SimEnvObserve, UpCmd},
     mechanism_thread::{MechScheduleOnce, MechScheduleOnceRes},
     node::EnvNodeExt,
-    rl_target::RL_TARGET,
+    request::ReqId,
+    reward,
     sim_env::SimEnv,
     with_env_sub::WithEnvHelp,
 };
@@ -17,11 +20,188 @@ use std::{
     time::Duration,
 };
 
+/// 仿真循环各阶段的回调
+pub type SimHook = Option<Box<dyn FnMut(&SimEnv) + 'static>>;
+
 impl SimEnv {
-    fn one_frame(
+    /// 将算法线程下发的调度、扩缩容指令应用到环境中
+    pub fn apply_mech_cmd(&self, res: &MechScheduleOnceRes) {
+        match res {
+            MechScheduleOnceRes::Cmds {
+                sche_cmds,
+                scale_up_cmds,
+                scale_down_cmds,
+            } => {
+                // FIXME: Should transfer the cmds for a while.
+                // FIXME: should remove conflict cmds
+                // TODO: ScheCmd has memlimit
+                for sche in sche_cmds.iter() {
+                    self.apply_sche_cmd(sche);
+                }
+                for down in scale_down_cmds.iter() {
+                    self.apply_down_cmd(down);
+                }
+                for up in scale_up_cmds.iter() {
+                    self.apply_up_cmd(up);
+                }
+            }
+            MechScheduleOnceRes::ScheCmd(sche) => self.apply_sche_cmd(sche),
+            MechScheduleOnceRes::ScaleDownCmd(down) => self.apply_down_cmd(down),
+            MechScheduleOnceRes::ScaleUpCmd(up) => self.apply_up_cmd(up),
+            MechScheduleOnceRes::PreemptCmd(preempt) => self.apply_preempt_cmd(preempt),
+            MechScheduleOnceRes::End { .. } => {}
+        }
+    }
+
+    fn apply_sche_cmd(&self, sche: &ScheCmd) {
+        // 指令下发期间请求可能已经超时被移除
+        let outcome = if !self.core.requests().contains_key(&sche.reqid) {
+            DecisionOutcome::Dropped { reason: "request already done".to_owned() }
+        } else {
+            self.help.telemetry().record_sche(sche);
+            self.schedule_reqfn_on_node(&mut self.request_mut(sche.reqid), sche.fnid, sche.nid);
+            // 节点资源不够时任务在节点上排队，不会被丢弃
+            DecisionOutcome::Applied
+        };
+        self.help.audit().record(
+            self.current_frame(),
+            DecisionKind::Sche,
+            Some(sche.reqid),
+            sche.fnid,
+            sche.nid,
+            outcome,
+        );
+    }
+
+    fn apply_preempt_cmd(&self, preempt: &PreemptCmd) {
+        let outcome = if !self.core.requests().contains_key(&preempt.reqid) {
+            DecisionOutcome::Dropped { reason: "request already done".to_owned() }
+        } else if
+            self.preempt_reqfn_on_node(&mut self.request_mut(preempt.reqid), preempt.fnid, preempt.nid)
+        {
+            DecisionOutcome::Applied
+        } else {
+            DecisionOutcome::Dropped { reason: "task not on node".to_owned() }
+        };
+        self.help.audit().record(
+            self.current_frame(),
+            DecisionKind::Preempt,
+            Some(preempt.reqid),
+            preempt.fnid,
+            preempt.nid,
+            outcome,
+        );
+    }
+
+    fn apply_down_cmd(&self, down: &DownCmd) {
+        let existed = self.node(down.nid).container(down.fnid).is_some();
+        //更新cache
+        self.node_mut(down.nid).try_unload_container(down.fnid, self, true);
+        let outcome = if existed {
+            DecisionOutcome::Applied
+        } else {
+            DecisionOutcome::Dropped { reason: "container not found".to_owned() }
+        };
+        self.help.audit().record(
+            self.current_frame(),
+            DecisionKind::ScaleDown,
+            None,
+            down.fnid,
+            down.nid,
+            outcome,
+        );
+    }
+
+    fn apply_up_cmd(&self, up: &UpCmd) {
+        let existed = self.node(up.nid).container(up.fnid).is_some();
+        self.node_mut(up.nid).try_load_container(up.fnid, self);
+        let outcome = if existed {
+            DecisionOutcome::Dropped { reason: "container already exists".to_owned() }
+        } else if self.node(up.nid).container(up.fnid).is_some() {
+            DecisionOutcome::Applied
+        } else if !self.node(up.nid).mem_enough_for_container(&self.func(up.fnid)) {
+            DecisionOutcome::Dropped { reason: "insufficient memory".to_owned() }
+        } else {
+            DecisionOutcome::Dropped { reason: "rejected by instance cache policy".to_owned() }
+        };
+        self.help.audit().record(
+            self.current_frame(),
+            DecisionKind::ScaleUp,
+            None,
+            up.fnid,
+            up.nid,
+            outcome,
+        );
+    }
+
+    /// 在算法线程上按当前状态决策一次，用 mech_once_end 等待并应用指令
+    ///
+    /// pregen_from 为跳帧时提前生成的这一帧的第一个请求，逐帧推进时算法在生成之前运行，看不到它们
+    pub(crate) fn mech_once_begin(
+        &self,
+        raw_action: &ESActionWrapper,
+        pregen_from: Option<ReqId>,
+    ) -> Receiver<MechScheduleOnceRes> {
+        let core = self.core.clone();
+        if let Some(first) = pregen_from {
+            core.requests_mut().retain(|&reqid, _| reqid < first);
+        }
+        let (tx, rx) = mpsc::channel();
+        self.mech_caller
+            .send(MechScheduleOnce {
+                sim_env: SimEnvObserve::new(core, self.help.clone()),
+                responser: tx,
+                action: raw_action.clone(),
+            })
+            .unwrap();
+        rx
+    }
+
+    /// 等算法跑完，apply 为 false 时只等待不应用指令
+    ///
+    /// 返回算法耗时(ms)和算法是否下发了指令
+    pub(crate) fn mech_once_end(&self, rx: &Receiver<MechScheduleOnceRes>, apply: bool) -> (u64, bool) {
+        let mut has_cmd = false;
+        while let Ok(res) = rx.recv() {
+            if let MechScheduleOnceRes::End { mech_run_ms } = res {
+                self.help
+                    .algo_exc_time_mut()
+                    .insert(self.current_frame(), mech_run_ms as usize);
+                self.help.audit().clear_notes();
+                return (mech_run_ms, has_cmd);
+            }
+            has_cmd = true;
+            if apply {
+                self.apply_mech_cmd(&res);
+            }
+        }
+        (0, has_cmd)
+    }
+
+    /// 算法耗时对应的帧数，一帧对应 frame_duration_ms
+    pub(crate) fn mech_run_frames(&self, mech_run_ms: u64) -> usize {
+        ((mech_run_ms as f32) / self.help.config().frame_duration_ms) as usize
+    }
+
+    /// 只推进一帧：算法按 action 决策一次，指令在下一帧开始前应用，返回 (score, stop)
+    ///
+    /// 供逐步交互的 RL 接口使用，仿真结束后再调用不会继续推进
+    pub fn step_frame(&mut self, action: ESActionWrapper) -> (f32, bool) {
+        if self.current_frame() > self.help.config().total_frames() {
+            return (0.0, true);
+        }
+        reward::begin_step(self);
+        let mech_rx = self.mech_once_begin(&action, None);
+        let running = self.one_frame(&mut None, &mut None);
+        self.mech_once_end(&mech_rx, running);
+        let breakdown = reward::end_step(self, &action);
+        (breakdown.reward, !running)
+    }
+
+    pub fn one_frame(
         &mut self,
-        hook_frame_begin: &mut Option<Box<dyn FnMut(&SimEnv) + 'static>>,
-        hook_req_gen: &mut Option<Box<dyn FnMut(&SimEnv) + 'static>>,
+        hook_frame_begin: &mut SimHook,
+        hook_req_gen: &mut SimHook,
     ) -> bool {
         // 进行帧开始时处理
         self.on_frame_begin();
@@ -42,11 +222,14 @@ impl SimEnv {
 
         self.sim_run();
 
+        // 本帧计算完还没完成的请求才算超时
+        self.timeout_check();
+
         self.on_frame_end();
 
-        if self.current_frame() > self.help().config().total_frame {
+        if self.current_frame() > self.help().config().total_frames() {
             self.help.metric_record_mut().as_ref().unwrap().flush(self);
-            RL_TARGET.as_ref().map(|v| v.set_stop());
+            self.help.rl_agent().set_stop();
             // self.reset();
             false
         } else {
@@ -57,10 +240,10 @@ impl SimEnv {
     pub fn step_es(
         &mut self,
         raw_action: ESActionWrapper,
-        mut hook_frame_begin: Option<Box<dyn FnMut(&SimEnv) + 'static>>,
-        mut hook_req_gen: Option<Box<dyn FnMut(&SimEnv) + 'static>>,
-        mut hook_algo_begin: Option<Box<dyn FnMut(&SimEnv) + 'static>>,
-        mut hook_algo_end: Option<Box<dyn FnMut(&SimEnv) + 'static>>,
+        mut hook_frame_begin: SimHook,
+        mut hook_req_gen: SimHook,
+        mut hook_algo_begin: SimHook,
+        mut hook_algo_end: SimHook,
     ) -> (f32, String) {
         // 尝试设置当前线程的优先级
         #[cfg(target_os = "windows")]
@@ -90,55 +273,13 @@ impl SimEnv {
                         res
                     };
                     match res {
-                        MechScheduleOnceRes::Cmds {
-                            sche_cmds,
-                            scale_up_cmds,
-                            scale_down_cmds,
-                        } => {
-                            // 2. handle_master's commands
-                            {
-                                // FIXME: Should transfer the cmds for a while.
-                                // FIXME: should remove conflict cmds
-                                // TODO: ScheCmd has memlimit
-                                for sche in sche_cmds.iter() {
-                                    self.schedule_reqfn_on_node(
-                                        &mut self.request_mut(sche.reqid),
-                                        sche.fnid,
-                                        sche.nid,
-                                    );
-                                }
-                                for down in scale_down_cmds.iter() {
-                                    //更新cache
-                                    self.node_mut(down.nid)
-                                        .try_unload_container(down.fnid, self, true);
-                                }
-                                for up in scale_up_cmds.iter() {
-                                    self.node_mut(up.nid).try_load_container(up.fnid, self);
-                                }
-                            }
-                        }
-                        MechScheduleOnceRes::ScheCmd(sche) => {
-                            self.schedule_reqfn_on_node(
-                                &mut self.request_mut(sche.reqid),
-                                sche.fnid,
-                                sche.nid,
-                            );
-                        }
-                        MechScheduleOnceRes::ScaleDownCmd(down) => {
-                            //更新cache
-                            self.node_mut(down.nid)
-                                .try_unload_container(down.fnid, self, true);
-                        }
-                        MechScheduleOnceRes::ScaleUpCmd(up) => {
-                            self.node_mut(up.nid).try_load_container(up.fnid, self);
-                        }
                         MechScheduleOnceRes::End { mech_run_ms } => {
                             // 1. need to handle the gap between
                             //    master_mech time and simulation time
                             //    just simulate some if mech is longer
                             {
-                                // one frame reflect to 1ms
-                                let master_mech_frame = mech_run_ms as usize;
+                                // one frame reflect to frame_duration_ms
+                                let master_mech_frame = self.mech_run_frames(mech_run_ms);
                                 let frame_ran = self.current_frame() - frame_when_master_mech_begin;
                                 let gap = if master_mech_frame > frame_ran {
                                     master_mech_frame - frame_ran
@@ -161,10 +302,12 @@ impl SimEnv {
                                     .insert(self.current_frame(), mech_run_ms as usize);
                             }
 
+                            self.help.audit().clear_notes();
                             self.master_mech_not_running = true;
                             frame_when_master_mech_begin = self.current_frame();
                             hook_algo_end.as_mut().map(|f| f(self));
                         }
+                        cmd => self.apply_mech_cmd(&cmd),
                     }
                 }
             }
@@ -195,3 +338,23 @@ impl SimEnv {
         (0.0, "no action".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{actions::ESActionWrapper, config::Config, sim_env::SimEnv};
+
+    #[test]
+    fn test_step_frame() {
+        let mut config = Config::new_test();
+        config.total_frame = 5;
+        let mut env = SimEnv::new(config);
+        for frame in 0..=5 {
+            assert_eq!(env.current_frame(), frame);
+            let (_, stop) = env.step_frame(ESActionWrapper::Int(1));
+            assert_eq!(stop, frame == 5);
+        }
+        // 结束后不再推进
+        assert!(env.step_frame(ESActionWrapper::Int(1)).1);
+        assert_eq!(env.current_frame(), 6);
+    }
+}
@@ -3,6 +3,8 @@ use std::{
     vec,
 };
 
+use serde_json::Value;
+
 use crate::{
     fn_dag::{EnvFnExt, FnContainer, FnContainerState, FnId},
     mechanism::{MechanismImpl, SimEnvObserve},
@@ -19,6 +21,16 @@ pub trait Scheduler: Send {
         mech: &MechanismImpl,
         cmd_distributor: &MechCmdDistributor,
     );
+
+    /// 快照时导出跨帧保留的内部状态，没有这类状态的算法不用实现
+    fn save_state(&self) -> Result<Value, String> {
+        Ok(Value::Null)
+    }
+
+    /// 恢复 save_state 导出的状态
+    fn load_state(&mut self, _state: Value) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 pub mod schedule_helper {
@@ -121,6 +133,19 @@ impl SimEnv {
         self.on_task_scheduled(req, fnid, nodeid);
     }
 
+    /// 撤回 nodeid 上还没完成的任务，任务不在节点上时返回 false
+    pub fn preempt_reqfn_on_node(&self, req: &mut Request, fnid: FnId, nodeid: NodeId) -> bool {
+        if req.fn_node.get(&fnid) != Some(&nodeid) || req.done_fns.contains_key(&fnid) {
+            return false;
+        }
+        if !self.node(nodeid).remove_task(req.req_id, fnid) {
+            return false;
+        }
+        req.fn_node.remove(&fnid);
+        self.on_task_preempted(req, fnid);
+        true
+    }
+
     // 模拟两个节点之间的数据传输过程
     fn sim_transfer_btwn_nodes(&self, node_a: NodeId, node_b: NodeId, transmap: &mut NodeTransMap) {
         // 两个节点不能是同一个节点
@@ -131,7 +156,7 @@ impl SimEnv {
         let _b2a = transmap.remove(&mut (node_b, node_a)).unwrap();
 
         // 获取节点间的网速带宽
-        let total_bandwith = self.node_get_speed_btwn(node_a, node_b);
+        let total_bandwith = self.node_get_trans_per_frame_btwn(node_a, node_b);
 
         // 单个路径的带宽
         let each_path_bandwith = total_bandwith / (a2b.path_cnt() as f32);
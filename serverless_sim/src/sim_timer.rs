@@ -1,25 +1,66 @@
-use std::sync::{ Arc };
+use std::collections::HashMap;
 
-use parking_lot::Mutex;
+use parking_lot::RwLock;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
 
 use crate::sim_env::SimEnv;
 
-/// SimEnv 的 timers 字段维护了一个映射，记录了每个未来帧数应执行的回调函数列表。
-/// 当到达指定帧数时，SimEnv 可以遍历对应帧数的回调函数列表并逐一执行
+/// 定时任务的执行函数，参数为 start_timer 时给出的 arg
+pub type TimerFn = fn(&SimEnv, &Value);
+
+lazy_static! {
+    /// 按名字注册的定时任务，定时器只保存名字和参数，可以写入快照
+    static ref TIMER_FNS: RwLock<HashMap<String, TimerFn>> = RwLock::new(HashMap::new());
+}
+
+/// 注册一种定时任务，同名的会被替换
+pub fn register_timer(name: &str, f: TimerFn) {
+    TIMER_FNS.write().insert(name.to_owned(), f);
+}
+
+pub fn timer_registered(name: &str) -> bool {
+    TIMER_FNS.read().contains_key(name)
+}
+
+/// 一个未触发的定时器
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Timer {
+    /// register_timer 注册的名字
+    pub name: String,
+    pub arg: Value,
+}
+
+/// SimEnv 的 timers 字段维护了一个映射，记录了每个未来帧数应执行的定时任务列表。
+/// 当到达指定帧数时，SimEnv 按注册的名字找到执行函数并逐一执行
 impl SimEnv {
-    pub fn start_timer<F: FnMut(&SimEnv) + Send + 'static>(&self, timeout: usize, f: F) {
+    /// timeout 单位为帧，不随 frame_duration_ms 换算，按真实时间设置用 start_timer_ms
+    ///
+    /// name 需要先用 register_timer 注册
+    pub fn start_timer(&self, timeout: usize, name: &str, arg: Value) {
+        assert!(timer_registered(name), "timer {} not registered", name);
         let end_frame = self.current_frame() + timeout;
-        let shared = Arc::new(Mutex::new(Some(f)));
-        // timers: RefCell<HashMap<usize, Vec<Box<dyn FnMut(&SimEnv) + Send>>>>
         self.timers
             .borrow_mut()
             .entry(end_frame)
-            // 如果键已存在, 则对相应的值进行修改
-            .and_modify(|v| {
-                let shared = shared.clone();
-                v.push(Box::new(shared.lock().take().unwrap()));
-            })
-            // 如果键不存在, 则插入一个新的键值对
-            .or_insert_with(|| { vec![Box::new(shared.lock().take().unwrap())] });
+            .or_default()
+            .push(Timer { name: name.to_owned(), arg });
+    }
+
+    /// 按真实时间(ms)设置定时器，根据 frame_duration_ms 换算成帧
+    pub fn start_timer_ms(&self, timeout_ms: f32, name: &str, arg: Value) {
+        let timeout = self.help.config().ms_to_frames(timeout_ms);
+        self.start_timer(timeout, name, arg);
+    }
+
+    /// 执行到期的定时器
+    pub(crate) fn fire_timers(&self) {
+        let Some(timers) = self.timers.borrow_mut().remove(&self.current_frame()) else {
+            return;
+        };
+        for timer in timers {
+            let f = TIMER_FNS.read()[&timer.name];
+            f(self, &timer.arg);
+        }
     }
 }
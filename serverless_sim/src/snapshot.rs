@@ -0,0 +1,358 @@
+//! 运行中仿真环境的快照、恢复与分叉
+//!
+//! dag、函数、节点网速图都由 rand_seed 等配置确定性生成，不写入快照，恢复时先用
+//! `SimEnv::new(config)` 重新生成，再覆盖节点、容器、请求、指标、随机数发生器等动态状态。
+//!
+//! 机制(扩缩容、调度算法)恢复时按 config.mech 重新创建，再载入各算法 `save_state` 导出的
+//! 跨帧状态(如 careful_down 的历史目标、kpa 的采样窗口)；定时器只保存注册名和参数。
+//!
+//! 限制:
+//! - 由外部 agent 驱动的算法(rela)不能快照
+//! - fork 时换了机制，新的算法从空状态开始
+//! - 恢复时定时器的名字需要已经用 register_timer 注册
+//! - records 只包含恢复之后的帧
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Component, Path},
+};
+
+use rand_pcg::Pcg64;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    admission::AdmissionState,
+    config::{config_errors_str, Config},
+    fn_dag::{DagId, FnContainer, FnId},
+    mechanism::MechState,
+    metric::{MechMetric, OneFrameMetric},
+    node::{NodeId, NodeRscLimit},
+    request::{ReqId, Request},
+    reward::RewardTracker,
+    sim_env::SimEnv,
+    sim_timer::{timer_registered, Timer},
+};
+
+/// http 接口读写快照只能在这个目录下
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+/// http 接口给出的快照路径，只接受 SNAPSHOT_DIR 下的相对路径，拒绝绝对路径和 `..`
+pub fn snapshot_path(name: &str) -> Result<String, String> {
+    let path = Path::new(name);
+    if name.is_empty() || path.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!(
+            "invalid snapshot path {:?}, should be a relative path under {} without ..",
+            name, SNAPSHOT_DIR
+        ));
+    }
+    Ok(format!("{}/{}", SNAPSHOT_DIR, name))
+}
+
+/// 算法跨帧保留的状态转成 json 写入快照
+pub fn save_algo_state<T: Serialize>(state: &T) -> Value {
+    serde_json::to_value(state).unwrap()
+}
+
+/// 读回 save_algo_state 写入的状态
+pub fn load_algo_state<T: DeserializeOwned>(algo: &str, state: Value) -> Result<T, String> {
+    serde_json::from_value(state).map_err(|e| format!("load {} state failed: {}", algo, e))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NodeSnapshot {
+    pub node_id: NodeId,
+    pub rsc_limit: NodeRscLimit,
+    pub pending_tasks: BTreeSet<(ReqId, FnId)>,
+    pub fn_containers: BTreeMap<FnId, FnContainer>,
+    pub cpu: f32,
+    pub mem: f32,
+    pub last_frame_cpu: f32,
+    pub last_frame_mem: f32,
+    pub frame_run_count: usize,
+    /// 缓存策略中的函数，从最早淘汰到最晚淘汰
+    pub cache_keys: Vec<FnId>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SimEnvSnapshot {
+    pub config: Config,
+    pub rander: Pcg64,
+    pub current_frame: usize,
+    /// 用于校验重新生成的函数和快照时一致
+    pub fn_cnt: usize,
+    pub nodes: Vec<NodeSnapshot>,
+    pub node2node_connection_count: Vec<Vec<usize>>,
+    pub fn_2_nodes: HashMap<FnId, HashSet<NodeId>>,
+    pub requests: BTreeMap<ReqId, Request>,
+    pub done_requests: Vec<Request>,
+    pub req_next_id: ReqId,
+    pub fn_next_id: FnId,
+    pub cost: f32,
+    pub metric: OneFrameMetric,
+    pub mech_metric: MechMetric,
+    pub dag_call_frequency: BTreeMap<DagId, (f64, f64)>,
+    pub dag_accumulate_call_frequency: BTreeMap<DagId, f64>,
+    pub algo_exc_time: HashMap<usize, usize>,
+    pub req_pregen_frame: Option<usize>,
+    #[serde(default)]
+    pub reward: RewardTracker,
+    #[serde(default)]
+    pub admission: AdmissionState,
+    /// 到期帧 - 定时器
+    #[serde(default)]
+    pub timers: BTreeMap<usize, Vec<Timer>>,
+    /// 为空时算法从空状态开始
+    #[serde(default)]
+    pub mech: Option<MechState>,
+}
+
+impl SimEnvSnapshot {
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("create dir for {} failed: {}", path, e))?;
+        }
+        let file = fs::File::create(path).map_err(|e| format!("create {} failed: {}", path, e))?;
+        serde_json::to_writer(file, self).map_err(|e| format!("write {} failed: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = fs::File::open(path).map_err(|e| format!("open {} failed: {}", path, e))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| format!("parse {} failed: {}", path, e))
+    }
+}
+
+impl SimEnv {
+    /// 在两次 step 之间导出当前状态
+    pub fn snapshot(&self) -> Result<SimEnvSnapshot, String> {
+        let mech = self.mech.lock().save_state()?;
+        let (req_next_id, fn_next_id) = self.help.next_ids();
+        Ok(SimEnvSnapshot {
+            config: self.help.config().clone(),
+            rander: self.rander.borrow().clone(),
+            current_frame: self.current_frame(),
+            fn_cnt: self.core.fns().len(),
+            nodes: self.core.nodes().iter().map(|n| n.snapshot()).collect(),
+            node2node_connection_count: self.core.node2node_connection_count().clone(),
+            fn_2_nodes: self.core.fn_2_nodes().clone(),
+            requests: self.core.requests().clone(),
+            done_requests: self.core.done_requests().clone(),
+            req_next_id,
+            fn_next_id,
+            cost: *self.help.cost(),
+            metric: self.help.metric().clone(),
+            mech_metric: self.help.mech_metric().clone(),
+            dag_call_frequency: self.help.fn_call_frequency().clone(),
+            dag_accumulate_call_frequency: self.help.dag_accumulate_call_frequency.borrow().clone(),
+            algo_exc_time: self.help.algo_exc_time().clone(),
+            req_pregen_frame: *self.req_pregen_frame.borrow(),
+            reward: self.help.reward().clone(),
+            admission: self.help.admission().clone(),
+            timers: self.timers.borrow().clone(),
+            mech: Some(mech),
+        })
+    }
+
+    /// 按快照中的 config 重新生成环境，再覆盖动态状态
+    pub fn from_snapshot(snap: SimEnvSnapshot) -> Result<SimEnv, String> {
+        if let Err(errs) = snap.config.check_valid() {
+            return Err(format!("invalid config: {}", config_errors_str(&errs)));
+        }
+        if let Some(timer) = snap.timers.values().flatten().find(|t| !timer_registered(&t.name)) {
+            return Err(format!("timer {} not registered", timer.name));
+        }
+        let env = SimEnv::new(snap.config);
+        if env.core.fns().len() != snap.fn_cnt || env.core.nodes().len() != snap.nodes.len() {
+            return Err(format!(
+                "regenerated env doesn't match snapshot, fns {}/{}, nodes {}/{}",
+                env.core.fns().len(),
+                snap.fn_cnt,
+                env.core.nodes().len(),
+                snap.nodes.len()
+            ));
+        }
+
+        *env.rander.borrow_mut() = snap.rander;
+        *env.core.current_frame_mut() = snap.current_frame;
+        for (node, node_snap) in env.core.nodes_mut().iter_mut().zip(snap.nodes) {
+            node.restore_snapshot(node_snap);
+        }
+        *env.core.node2node_connection_count_mut() = snap.node2node_connection_count;
+        *env.core.fn_2_nodes_mut() = snap.fn_2_nodes;
+        *env.core.requests_mut() = snap.requests;
+        *env.core.done_requests_mut() = snap.done_requests;
+        env.help.set_next_ids(snap.req_next_id, snap.fn_next_id);
+        *env.help.cost_mut() = snap.cost;
+        *env.help.metric_mut() = snap.metric;
+        *env.help.mech_metric_mut() = snap.mech_metric;
+        *env.help.fn_call_frequency_mut() = snap.dag_call_frequency;
+        *env.help.dag_accumulate_call_frequency.borrow_mut() = snap.dag_accumulate_call_frequency;
+        *env.help.algo_exc_time_mut() = snap.algo_exc_time;
+        *env.req_pregen_frame.borrow_mut() = snap.req_pregen_frame;
+        *env.help.reward_mut() = snap.reward;
+        *env.help.admission_mut() = snap.admission;
+        *env.timers.borrow_mut() = snap.timers;
+        if let Some(mech) = snap.mech {
+            env.mech.lock().load_state(mech)?;
+        }
+        Ok(env)
+    }
+
+    /// 从当前状态分叉出一个独立的环境，config 不为空时替换机制配置，
+    /// 用于在同一个中间状态上比较不同的扩缩容、调度算法
+    ///
+    /// 替换的配置只能修改机制，且缓存策略需保持一致，否则节点上的容器和缓存对不上
+    pub fn fork(&self, config: Option<Config>) -> Result<SimEnv, String> {
+        let mut snap = self.snapshot()?;
+        if let Some(config) = config {
+            config.check_valid().map_err(|errs| format!("invalid config: {}", config_errors_str(&errs)))?;
+            let old = &snap.config;
+            if config.no_mech_str() != old.no_mech_str()
+                || config.frame_duration_ms != old.frame_duration_ms
+                || config.mech.instance_cache_policy_conf() != old.mech.instance_cache_policy_conf()
+            {
+                return Err(format!(
+                    "fork can only change scale and sche mechanisms, from {} to {}",
+                    old.str(),
+                    config.str()
+                ));
+            }
+            // 算法的状态只对原来的机制有效
+            let mech_key = |c: &Config| (serde_json::to_value(&c.mech).unwrap(), c.mech.filter_chain());
+            if mech_key(&config) != mech_key(&snap.config) {
+                snap.mech = None;
+            }
+            snap.config = config;
+        }
+        SimEnv::from_snapshot(snap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::snapshot_path;
+    use crate::{
+        actions::ESActionWrapper, config::Config, request::ReqId, sim_env::SimEnv,
+        sim_timer::register_timer,
+    };
+
+    fn select(conf: &mut std::collections::HashMap<String, Option<String>>, name: &str) {
+        conf.values_mut().for_each(|v| *v = None);
+        conf.insert(name.to_owned(), Some("".to_owned()));
+    }
+
+    fn add_cost(env: &SimEnv, arg: &Value) {
+        *env.help.cost_mut() += arg.as_f64().unwrap() as f32;
+    }
+
+    /// 推进到结束，返回 (完成的请求及其开始、结束帧, score, cost)
+    fn run_to_end(env: &mut SimEnv) -> (Vec<(ReqId, usize, usize)>, f32, f32) {
+        while !env.step_frame(ESActionWrapper::Int(0)).1 {}
+        let done = env.core
+            .done_requests()
+            .iter()
+            .map(|r| (r.req_id, r.begin_frame, r.end_frame))
+            .collect();
+        (done, env.score(), *env.help.cost())
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        register_timer("test_add_cost", add_cost);
+        // 默认的 hpa + careful_down，random 调度不可复现，换成 greedy
+        let mut config = Config::new_test();
+        config.total_frame = 300;
+        select(&mut config.mech.sche, "greedy");
+        let mut env = SimEnv::new(config);
+        // 到这里 careful_down 已经记录了缩容目标
+        for _ in 0..200 {
+            env.step_frame(ESActionWrapper::Int(0));
+        }
+        env.start_timer(5, "test_add_cost", Value::from(1000.0));
+
+        let snap = env.snapshot().unwrap();
+        assert_eq!(snap.timers.values().flatten().count(), 1);
+        let mech_state = serde_json::to_value(snap.mech.as_ref().unwrap()).unwrap();
+        assert!(!mech_state["filters"][0].as_object().unwrap().is_empty());
+        let json = serde_json::to_string(&snap).unwrap();
+        let mut restored = SimEnv::from_snapshot(serde_json::from_str(&json).unwrap()).unwrap();
+
+        assert_eq!(restored.current_frame(), env.current_frame());
+        assert_eq!(restored.core.requests().len(), env.core.requests().len());
+        assert_eq!(*restored.help.cost(), *env.help.cost());
+        assert_eq!(restored.help.next_ids(), env.help.next_ids());
+        assert_eq!(serde_json::to_value(restored.mech.lock().save_state().unwrap()).unwrap(), mech_state);
+        for (a, b) in restored.core.nodes().iter().zip(env.core.nodes().iter()) {
+            let (a, b) = (a.snapshot(), b.snapshot());
+            assert_eq!(a.cache_keys, b.cache_keys);
+            assert_eq!(a.pending_tasks, b.pending_tasks);
+            assert_eq!(
+                a.fn_containers.keys().collect::<std::collections::BTreeSet<_>>(),
+                b.fn_containers.keys().collect::<std::collections::BTreeSet<_>>()
+            );
+        }
+
+        // 原环境和恢复的环境继续运行，结果一致
+        let cost_before = *env.help.cost();
+        let (done, score, cost) = run_to_end(&mut env);
+        assert!(cost >= cost_before + 1000.0);
+        assert!(!done.is_empty());
+        assert_eq!(run_to_end(&mut restored), (done, score, cost));
+
+        // 修改缓存策略的分叉会被拒绝
+        let mut other = env.help.config().clone();
+        other.mech.instance_cache_policy.values_mut().for_each(|v| *v = None);
+        other.mech.instance_cache_policy.insert("no_evict".to_owned(), Some("".to_owned()));
+        assert!(env.fork(Some(other)).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_mech_state() {
+        let cases = [
+            ("scale_sche_separated", "kpa", "data_locality"),
+            ("scale_sche_separated", "forecast", "heft"),
+            ("scale_sche_separated", "temp_scaler", "sparrow"),
+            ("scale_sche_separated", "hpa", "rotate"),
+            ("scale_sche_joint", "hpa", "bp_balance"),
+        ];
+        for (mech_type, scale_num, sche) in cases {
+            let mut config = Config::new_test();
+            config.request_freq = "middle".to_owned();
+            select(&mut config.mech.mech_type, mech_type);
+            select(&mut config.mech.scale_num, scale_num);
+            select(&mut config.mech.sche, sche);
+            for filter in ["stabilize", "budget", "zero_delay"] {
+                config.mech.filter.insert(filter.to_owned(), Some("".to_owned()));
+            }
+            let mut env = SimEnv::new(config);
+            for _ in 0..60 {
+                env.step_frame(ESActionWrapper::Int(0));
+            }
+            let state = serde_json::to_value(env.mech.lock().save_state().unwrap()).unwrap();
+            let json = serde_json::to_string(&env.snapshot().unwrap()).unwrap();
+            let restored = SimEnv::from_snapshot(serde_json::from_str(&json).unwrap()).unwrap();
+            let restored_state = serde_json::to_value(restored.mech.lock().save_state().unwrap()).unwrap();
+            assert_eq!(restored_state, state, "{} {}", scale_num, sche);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_rela() {
+        let mut config = Config::new_test();
+        select(&mut config.mech.scale_num, "rela");
+        let env = SimEnv::new(config);
+        assert!(env.snapshot().is_err());
+    }
+
+    #[test]
+    fn test_snapshot_path() {
+        assert_eq!(snapshot_path("a/b.json").unwrap(), "snapshots/a/b.json");
+        for bad in ["", "/tmp/a.json", "../a.json", "a/../../b.json", "./a.json"] {
+            assert!(snapshot_path(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! gym 风格的观测空间和动作空间
+//!
+//! 观测是定长的 f32 向量，由若干字段顺序拼接，每个字段的名字、偏移和形状在
+//! `ObservationSpace::fields` 中描述，RL 侧按字段切片即可，不需要解析 `State` 的 json。
+//! 字段的组成或含义改变时增加 `SPACES_VERSION`。
+//!
+//! 动作空间由扩缩容算法声明(`ScaleNum::action_space`)，http 传来的 f32 数组
+//! 按动作空间解码为 `ESActionWrapper`
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::ESActionWrapper,
+    fn_dag::DagId,
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+pub const SPACES_VERSION: u32 = 1;
+
+/// 统计最近到达请求数的帧数
+pub const RECENT_ARRIVAL_FRAMES: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum Space {
+    /// 取值 0..n 的整数
+    Discrete { n: usize },
+    /// 第 i 维取值 0..nvec[i] 的整数
+    MultiDiscrete { nvec: Vec<usize> },
+    /// 连续向量，每一维取值 [low, high]
+    Box { shape: Vec<usize>, low: f32, high: f32 },
+}
+
+impl Space {
+    /// 展平后的维数
+    pub fn flat_dim(&self) -> usize {
+        match self {
+            Space::Discrete { .. } => 1,
+            Space::MultiDiscrete { nvec } => nvec.len(),
+            Space::Box { shape, .. } => shape.iter().product(),
+        }
+    }
+
+    /// 把 http 传来的展平动作解码为 ESActionWrapper，越界时返回 Err
+    pub fn decode_action(&self, raw: &[f32]) -> Result<ESActionWrapper, String> {
+        if raw.len() != self.flat_dim() {
+            return Err(format!("action dim {} doesn't match space dim {}", raw.len(), self.flat_dim()));
+        }
+        fn check_int(v: f32, n: usize) -> Result<(), String> {
+            if v.fract() != 0.0 || v < 0.0 || v >= (n as f32) {
+                return Err(format!("action {} is not an integer in 0..{}", v, n));
+            }
+            Ok(())
+        }
+        match self {
+            Space::Discrete { n } => {
+                check_int(raw[0], *n)?;
+                Ok(ESActionWrapper::Int(raw[0] as u32))
+            }
+            Space::MultiDiscrete { nvec } => {
+                for (v, n) in raw.iter().zip(nvec.iter()) {
+                    check_int(*v, *n)?;
+                }
+                Ok(ESActionWrapper::Vec(raw.to_vec()))
+            }
+            Space::Box { low, high, .. } => {
+                if let Some(v) = raw.iter().find(|v| !(**v >= *low && **v <= *high)) {
+                    return Err(format!("action {} out of range [{}, {}]", v, low, high));
+                }
+                if raw.len() == 1 {
+                    Ok(ESActionWrapper::Float(raw[0]))
+                } else {
+                    Ok(ESActionWrapper::Vec(raw.to_vec()))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObsField {
+    pub name: String,
+    /// 在展平的观测向量中的起始位置
+    pub offset: usize,
+    pub shape: Vec<usize>,
+    pub low: f32,
+    pub high: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObservationSpace {
+    pub space: Space,
+    pub fields: Vec<ObsField>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnvSpaces {
+    pub version: u32,
+    pub observation: ObservationSpace,
+    pub action: Space,
+}
+
+/// 观测字段的定义顺序，observation_space 和 observe 共用
+fn obs_fields(node_cnt: usize, fn_cnt: usize) -> Vec<ObsField> {
+    let defs: [(&str, Vec<usize>, f32, f32); 4] = [
+        // 每个节点上一帧的 cpu、mem 利用率
+        ("node_util", vec![node_cnt, 2], 0.0, 1.0),
+        // 每个函数还未被调度的请求数
+        ("fn_queue_len", vec![fn_cnt], 0.0, f32::MAX),
+        // 每个函数最近 RECENT_ARRIVAL_FRAMES 帧内到达的请求数
+        ("fn_recent_arrivals", vec![fn_cnt], 0.0, f32::MAX),
+        // 当前帧 / total_frame
+        ("progress", vec![1], 0.0, 1.0),
+    ];
+    let mut offset = 0;
+    defs.into_iter()
+        .map(|(name, shape, low, high)| {
+            let field = ObsField { name: name.to_owned(), offset, shape, low, high };
+            offset += field.shape.iter().product::<usize>();
+            field
+        })
+        .collect()
+}
+
+pub fn observation_space<E: WithEnvCore + WithEnvHelp>(env: &E) -> ObservationSpace {
+    let fields = obs_fields(env.core().nodes().len(), env.core().fns().len());
+    let dim = fields.last().map(|f| f.offset + f.shape.iter().product::<usize>()).unwrap_or(0);
+    ObservationSpace {
+        space: Space::Box { shape: vec![dim], low: 0.0, high: f32::MAX },
+        fields,
+    }
+}
+
+/// 按 observation_space 的字段顺序生成展平的观测
+pub fn observe<E: WithEnvCore + WithEnvHelp>(env: &E) -> Vec<f32> {
+    let core = env.core();
+    let mut obs = vec![];
+
+    for n in core.nodes().iter() {
+        obs.push((n.last_frame_cpu / n.rsc_limit.cpu).min(1.0));
+        obs.push((n.last_frame_mem / n.rsc_limit.mem).min(1.0));
+    }
+
+    let mech_metric = env.help().mech_metric();
+    for f in core.fns().iter() {
+        obs.push(mech_metric.fn_unsche_req_cnt(f.fn_id) as f32);
+    }
+
+    let cur_frame = core.current_frame();
+    let from = cur_frame.saturating_sub(RECENT_ARRIVAL_FRAMES);
+    let mut dag_arrivals = std::collections::HashMap::<DagId, usize>::new();
+    for (_, req) in core.requests().iter() {
+        if req.begin_frame >= from {
+            *dag_arrivals.entry(req.dag_i).or_default() += 1;
+        }
+    }
+    // done_requests 按完成帧排序，完成得比 from 早的请求一定也到达得更早
+    for req in core.done_requests().iter().rev() {
+        if req.end_frame < from {
+            break;
+        }
+        if req.begin_frame >= from {
+            *dag_arrivals.entry(req.dag_i).or_default() += 1;
+        }
+    }
+    for f in core.fns().iter() {
+        obs.push(*dag_arrivals.get(&f.dag_id).unwrap_or(&0) as f32);
+    }
+
+    let total_frame = env.help().config().total_frames().max(1);
+    obs.push(((cur_frame as f32) / (total_frame as f32)).min(1.0));
+    obs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{obs_fields, Space};
+    use crate::actions::ESActionWrapper;
+
+    #[test]
+    fn test_decode_action() {
+        let discrete = Space::Discrete { n: 3 };
+        assert_eq!(discrete.decode_action(&[2.0]), Ok(ESActionWrapper::Int(2)));
+        assert!(discrete.decode_action(&[3.0]).is_err());
+        assert!(discrete.decode_action(&[1.5]).is_err());
+
+        let multi = Space::MultiDiscrete { nvec: vec![2, 5] };
+        assert_eq!(multi.decode_action(&[1.0, 4.0]), Ok(ESActionWrapper::Vec(vec![1.0, 4.0])));
+        assert!(multi.decode_action(&[2.0, 0.0]).is_err());
+        assert!(multi.decode_action(&[1.0]).is_err());
+
+        let cont = Space::Box { shape: vec![1], low: -1.0, high: 1.0 };
+        assert_eq!(cont.decode_action(&[0.5]), Ok(ESActionWrapper::Float(0.5)));
+        assert!(cont.decode_action(&[f32::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_obs_fields_offset() {
+        let fields = obs_fields(4, 7);
+        let offsets: Vec<usize> = fields.iter().map(|f| f.offset).collect();
+        assert_eq!(offsets, vec![0, 8, 15, 22]);
+    }
+}
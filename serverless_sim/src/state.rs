@@ -91,6 +91,7 @@ struct SerialDoneRequest {
     dag_id: DagId,
     start_frame: usize,
     end_frame: usize,
+    timed_out: bool,
 }
 
 #[derive(Serialize)]
@@ -182,6 +183,7 @@ impl SimEnv {
                 dag_id: req.dag_i,
                 start_frame: req.begin_frame,
                 end_frame: req.end_frame,
+                timed_out: req.timed_out(),
             });
         }
         done_reqs
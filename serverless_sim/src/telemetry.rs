@@ -0,0 +1,159 @@
+//! 仿真运行中逐帧推送的遥测数据
+//!
+//! `Recorder` 只在 flush 时把整段记录写入文件，这里在每帧结束时把当前状态广播给订阅者，
+//! http 侧通过 SSE(`GET /telemetry?env_id=...`) 推送给看板。
+//! 没有订阅者时不收集任何数据，不影响仿真速度。
+//! 订阅者消费太慢时会丢掉最旧的帧，并收到一条 lagged 事件
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::{
+    fn_dag::FnId,
+    mechanism::ScheCmd,
+    node::NodeId,
+    request::ReqId,
+    sim_env::SimEnv,
+};
+
+/// 每个订阅者最多缓存的帧数
+pub const TELEMETRY_CHANNEL_CAP: usize = 256;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NodeTelemetry {
+    pub node: NodeId,
+    pub cpu: f32,
+    pub mem: f32,
+    pub cpu_limit: f32,
+    pub mem_limit: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScheCmdTelemetry {
+    pub reqid: ReqId,
+    pub fnid: FnId,
+    pub nid: NodeId,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FrameTelemetry {
+    pub frame: usize,
+    /// 这条数据覆盖的帧数，事件驱动模式下跳帧时大于 1
+    pub frame_cnt: usize,
+    /// 还未完成的请求数
+    pub running_reqs: usize,
+    pub done_reqs: usize,
+    pub nodes: Vec<NodeTelemetry>,
+    /// 每个函数的容器数
+    pub fn_containers: BTreeMap<FnId, usize>,
+    /// 上一条数据之后下发的调度指令
+    pub sche_cmds: Vec<ScheCmdTelemetry>,
+}
+
+pub struct TelemetryHub {
+    tx: broadcast::Sender<Arc<FrameTelemetry>>,
+    sche_cmds: Mutex<Vec<ScheCmdTelemetry>>,
+}
+
+impl Default for TelemetryHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TelemetryHub {
+    pub fn new() -> Self {
+        Self {
+            tx: broadcast::channel(TELEMETRY_CHANNEL_CAP).0,
+            sche_cmds: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<FrameTelemetry>> {
+        self.tx.subscribe()
+    }
+
+    pub fn watched(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
+    pub fn record_sche(&self, cmd: &ScheCmd) {
+        if !self.watched() {
+            return;
+        }
+        self.sche_cmds.lock().push(ScheCmdTelemetry {
+            reqid: cmd.reqid,
+            fnid: cmd.fnid,
+            nid: cmd.nid,
+        });
+    }
+
+    fn take_sche_cmds(&self) -> Vec<ScheCmdTelemetry> {
+        std::mem::take(&mut *self.sche_cmds.lock())
+    }
+
+    pub fn publish(&self, frame: FrameTelemetry) {
+        // 发送时订阅者可能刚好全部断开，忽略
+        let _ = self.tx.send(Arc::new(frame));
+    }
+}
+
+impl SimEnv {
+    /// 帧结束时调用，有订阅者时广播当前帧的状态
+    pub fn telemetry_publish(&self, frame_cnt: usize) {
+        let hub = self.help.telemetry();
+        if !hub.watched() {
+            return;
+        }
+        let mut fn_containers = BTreeMap::new();
+        let nodes = self
+            .core
+            .nodes()
+            .iter()
+            .map(|n| {
+                for fnid in n.fn_containers.borrow().keys() {
+                    *fn_containers.entry(*fnid).or_insert(0) += 1;
+                }
+                NodeTelemetry {
+                    node: n.node_id(),
+                    cpu: n.cpu,
+                    mem: n.unready_mem(),
+                    cpu_limit: n.rsc_limit.cpu,
+                    mem_limit: n.rsc_limit.mem,
+                }
+            })
+            .collect();
+        hub.publish(FrameTelemetry {
+            frame: self.current_frame(),
+            frame_cnt,
+            running_reqs: self.core.requests().len(),
+            done_reqs: self.core.done_requests().len(),
+            nodes,
+            fn_containers,
+            sche_cmds: hub.take_sche_cmds(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TelemetryHub;
+    use crate::mechanism::ScheCmd;
+
+    #[test]
+    fn test_sche_cmds_only_when_watched() {
+        let hub = TelemetryHub::new();
+        let cmd = ScheCmd { nid: 1, reqid: 2, fnid: 3, memlimit: None };
+        hub.record_sche(&cmd);
+        assert!(hub.take_sche_cmds().is_empty());
+
+        let _rx = hub.subscribe();
+        hub.record_sche(&cmd);
+        let cmds = hub.take_sche_cmds();
+        assert_eq!(cmds.len(), 1);
+        assert_eq!((cmds[0].reqid, cmds[0].fnid, cmds[0].nid), (2, 3, 1));
+    }
+}
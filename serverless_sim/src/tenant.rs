@@ -0,0 +1,313 @@
+//! 多租户
+//!
+//! 租户拥有若干应用(dag_i)，带 DRF 权重和优先级。没有列进任何租户的应用按 dag_i 轮流分给各租户，
+//! 没有配置租户时所有应用属于同一个默认租户。
+//! 租户的资源份额按 DRF 计算：cpu 份额为租户任务在各节点上平分到的 cpu 之和占集群总 cpu 的比例，
+//! mem 份额为租户任务占用的内存占集群总内存的比例，主导份额取两者中较大的一个
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ConfigError,
+    fn_dag::{DagId, EnvFnExt},
+    node::NodeId,
+    sim_env::SimEnv,
+    with_env_sub::{WithEnvCore, WithEnvHelp},
+};
+
+pub type TenantId = usize;
+
+/// 没有配置租户时唯一的租户
+pub const DEFAULT_TENANT: &str = "default";
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub name: String,
+    /// DRF 权重，主导份额除以权重后比较
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// 越大越优先，高优先级的任务先调度，可以抢占低优先级的任务
+    #[serde(default)]
+    pub priority: u32,
+    /// 属于该租户的应用
+    #[serde(default)]
+    pub apps: Vec<DagId>,
+}
+
+/// 应用所属的租户
+pub fn tenant_of(tenants: &[TenantConfig], dag_i: DagId) -> TenantId {
+    if tenants.is_empty() {
+        return 0;
+    }
+    tenants
+        .iter()
+        .position(|t| t.apps.contains(&dag_i))
+        .unwrap_or(dag_i % tenants.len())
+}
+
+pub fn tenant_cnt(tenants: &[TenantConfig]) -> usize {
+    tenants.len().max(1)
+}
+
+pub fn tenant_name(tenants: &[TenantConfig], t: TenantId) -> &str {
+    tenants.get(t).map_or(DEFAULT_TENANT, |t| &t.name)
+}
+
+pub fn tenant_weight(tenants: &[TenantConfig], t: TenantId) -> f32 {
+    tenants.get(t).map_or(1.0, |t| t.weight)
+}
+
+pub fn tenant_priority(tenants: &[TenantConfig], t: TenantId) -> u32 {
+    tenants.get(t).map_or(0, |t| t.priority)
+}
+
+/// 整个租户配置的摘要，用于 Config::str，scripts/records_read.py 中的 tenants_digest 与此一致
+pub fn tenants_digest(tenants: &[TenantConfig]) -> String {
+    let canonical = tenants
+        .iter()
+        .map(|t| {
+            let apps = t.apps
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{}:{}:{}:{}", t.name, t.weight, t.priority, apps)
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+    // FNV-1a，跨版本、跨语言稳定
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in canonical.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", h)
+}
+
+pub fn check_tenants(tenants: &[TenantConfig], errs: &mut Vec<ConfigError>) {
+    for (i, t) in tenants.iter().enumerate() {
+        if t.name.is_empty() {
+            errs.push(ConfigError::new(format!("tenants[{}].name", i), "should not be empty"));
+        } else if tenants[..i].iter().any(|o| o.name == t.name) {
+            errs.push(ConfigError::new(format!("tenants[{}].name", i), format!("duplicated tenant {:?}", t.name)));
+        }
+        if !(t.weight.is_finite() && t.weight > 0.0) {
+            errs.push(
+                ConfigError::new(
+                    format!("tenants[{}].weight", i),
+                    format!("should be a positive number, got {}", t.weight)
+                )
+            );
+        }
+        for app in &t.apps {
+            if let Some(o) = tenants[..i].iter().find(|o| o.apps.contains(app)) {
+                errs.push(
+                    ConfigError::new(
+                        format!("tenants[{}].apps", i),
+                        format!("app {} already belongs to tenant {:?}", app, o.name)
+                    )
+                );
+            }
+        }
+    }
+}
+
+/// 各租户在各节点上的任务数和占用的内存，调度器下发指令后在本轮内同步更新
+#[derive(Clone, Debug)]
+pub struct TenantUsage {
+    /// [tenant][node] 任务数
+    tasks: Vec<Vec<usize>>,
+    /// 各节点上所有租户的任务数
+    node_tasks: Vec<usize>,
+    node_cpu: Vec<f32>,
+    mem: Vec<f32>,
+    total_mem: f32,
+}
+
+impl TenantUsage {
+    pub fn new(tenant_cnt: usize, node_cpu: Vec<f32>, total_mem: f32) -> Self {
+        Self {
+            tasks: vec![vec![0; node_cpu.len()]; tenant_cnt],
+            node_tasks: vec![0; node_cpu.len()],
+            node_cpu,
+            mem: vec![0.0; tenant_cnt],
+            total_mem,
+        }
+    }
+
+    /// 统计节点上已有的任务
+    pub fn from_env(env: &impl EnvFnExt) -> Self {
+        let tenants = &env.help().config().tenants;
+        let nodes = env.core().nodes();
+        let mut usage = Self::new(
+            tenant_cnt(tenants),
+            nodes.iter().map(|n| n.rsc_limit.cpu).collect(),
+            nodes.iter().map(|n| n.rsc_limit.mem).sum(),
+        );
+        let requests = env.core().requests();
+        for node in nodes.iter() {
+            for (reqid, fnid) in node.tasks() {
+                let Some(req) = requests.get(&reqid) else {
+                    continue;
+                };
+                usage.add(tenant_of(tenants, req.dag_i), node.node_id(), env.func(fnid).mem);
+            }
+        }
+        usage
+    }
+
+    pub fn add(&mut self, t: TenantId, nid: NodeId, mem: f32) {
+        self.tasks[t][nid] += 1;
+        self.node_tasks[nid] += 1;
+        self.mem[t] += mem;
+    }
+
+    pub fn remove(&mut self, t: TenantId, nid: NodeId, mem: f32) {
+        self.tasks[t][nid] -= 1;
+        self.node_tasks[nid] -= 1;
+        self.mem[t] = (self.mem[t] - mem).max(0.0);
+    }
+
+    /// (cpu 份额, mem 份额)
+    pub fn shares(&self, t: TenantId) -> (f32, f32) {
+        let total_cpu: f32 = self.node_cpu.iter().sum();
+        let cpu: f32 = self.tasks[t]
+            .iter()
+            .zip(self.node_tasks.iter().zip(self.node_cpu.iter()))
+            .filter(|(_, (&all, _))| all > 0)
+            .map(|(&mine, (&all, &cpu))| cpu * (mine as f32) / (all as f32))
+            .sum();
+        let ratio = |used: f32, total: f32| if total > 0.0 { used / total } else { 0.0 };
+        (ratio(cpu, total_cpu), ratio(self.mem[t], self.total_mem))
+    }
+
+    pub fn dominant_share(&self, t: TenantId) -> f32 {
+        let (cpu, mem) = self.shares(t);
+        cpu.max(mem)
+    }
+}
+
+/// 一个租户在当前帧的指标，记录在每帧的 tenants 项中
+#[derive(Serialize, Clone, Debug)]
+pub struct TenantMetric {
+    pub name: String,
+    pub priority: u32,
+    pub weight: f32,
+    pub running_req_cnt: usize,
+    pub done_req_cnt: usize,
+    pub timed_out_req_cnt: usize,
+    pub req_done_time_avg_ms: f32,
+    pub req_done_time_avg_90p_ms: f32,
+    pub cpu_share: f32,
+    pub mem_share: f32,
+    pub dominant_share: f32,
+}
+
+impl SimEnv {
+    pub fn tenant_metrics(&self) -> Vec<TenantMetric> {
+        let tenants = &self.help().config().tenants;
+        let cnt = tenant_cnt(tenants);
+        let mut running = vec![0; cnt];
+        for req in self.core().requests().values() {
+            running[tenant_of(tenants, req.dag_i)] += 1;
+        }
+        let mut done_ms = vec![vec![]; cnt];
+        let mut timed_out = vec![0; cnt];
+        for req in self.core().done_requests().iter() {
+            if req.timed_out() {
+                timed_out[tenant_of(tenants, req.dag_i)] += 1;
+                continue;
+            }
+            done_ms[tenant_of(tenants, req.dag_i)].push(
+                self.help().config().frames_to_ms((req.end_frame - req.begin_frame) as f32)
+            );
+        }
+        let usage = TenantUsage::from_env(self);
+        (0..cnt)
+            .map(|t| {
+                let (cpu_share, mem_share) = usage.shares(t);
+                let mut ms = std::mem::take(&mut done_ms[t]);
+                ms.sort_by(|a, b| a.total_cmp(b));
+                let avg = |v: &[f32]| if v.is_empty() { 0.0 } else { v.iter().sum::<f32>() / (v.len() as f32) };
+                TenantMetric {
+                    name: tenant_name(tenants, t).to_owned(),
+                    priority: tenant_priority(tenants, t),
+                    weight: tenant_weight(tenants, t),
+                    running_req_cnt: running[t],
+                    done_req_cnt: ms.len(),
+                    timed_out_req_cnt: timed_out[t],
+                    req_done_time_avg_ms: avg(&ms),
+                    req_done_time_avg_90p_ms: avg(&ms[..((ms.len() as f32) * 0.9) as usize]),
+                    cpu_share,
+                    mem_share,
+                    dominant_share: cpu_share.max(mem_share),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_tenants, tenant_of, tenants_digest, TenantConfig, TenantUsage};
+
+    fn tenant(name: &str, weight: f32, apps: Vec<usize>) -> TenantConfig {
+        TenantConfig {
+            name: name.to_owned(),
+            weight,
+            priority: 0,
+            apps,
+        }
+    }
+
+    #[test]
+    fn test_tenant_of_and_check() {
+        assert_eq!(tenant_of(&[], 7), 0);
+        let tenants = vec![tenant("a", 1.0, vec![3]), tenant("b", 2.0, vec![])];
+        assert_eq!(tenant_of(&tenants, 3), 0);
+        // 没列出的应用轮流分
+        assert_eq!(tenant_of(&tenants, 5), 1);
+        assert_eq!(tenant_of(&tenants, 4), 0);
+
+        let mut errs = vec![];
+        check_tenants(&tenants, &mut errs);
+        assert!(errs.is_empty());
+        let bad = vec![tenant("a", 0.0, vec![1]), tenant("a", 1.0, vec![1])];
+        check_tenants(&bad, &mut errs);
+        let fields = errs.into_iter().map(|e| e.field).collect::<Vec<_>>();
+        assert_eq!(fields, vec!["tenants[0].weight", "tenants[1].name", "tenants[1].apps"]);
+    }
+
+    #[test]
+    fn test_tenants_digest() {
+        let tenants = vec![tenant("a", 1.0, vec![3]), tenant("b", 2.0, vec![])];
+        // 与 scripts/records_read.py 中的 tenants_digest 算出的值一致
+        assert_eq!(tenants_digest(&tenants), "5ca20c80a5b2db8b");
+        let mut other = tenants.clone();
+        other[1].priority = 1;
+        assert_ne!(tenants_digest(&other), tenants_digest(&tenants));
+        other[1].priority = 0;
+        other[0].apps.push(4);
+        assert_ne!(tenants_digest(&other), tenants_digest(&tenants));
+    }
+
+    #[test]
+    fn test_drf_shares() {
+        // 两个节点各 10 cpu，总内存 100
+        let mut usage = TenantUsage::new(2, vec![10.0, 10.0], 100.0);
+        usage.add(0, 0, 10.0);
+        usage.add(1, 0, 30.0);
+        usage.add(0, 1, 10.0);
+        // 租户 0 在节点 0 上分到一半 cpu，节点 1 上独占
+        assert_eq!(usage.shares(0), (0.75, 0.2));
+        assert_eq!(usage.shares(1), (0.25, 0.3));
+        assert_eq!(usage.dominant_share(1), 0.3);
+        usage.remove(0, 0, 10.0);
+        assert_eq!(usage.shares(1), (0.5, 0.3));
+    }
+}
@@ -0,0 +1,201 @@
+//! 请求超时和取消
+//!
+//! - request_ms: 请求从到达起超过这个时间还没完成就取消
+//! - fn_ms: 函数任务从调度到节点起超过这个时间还没完成，整个请求取消
+//!
+//! 为 0 表示不限制。取消时把请求还没完成的任务从节点的 pending_tasks 和容器的 req_fn_state 中移除，
+//! 占用的 cpu、内存和传输路径随之释放。请求以 `ReqStatus::TimedOut` 记入 done_requests，
+//! 延迟类指标只统计正常完成的请求，score 把超时请求按已等待的时间加 penalty_ms 计入
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ConfigError,
+    node::EnvNodeExt,
+    request::{ReqId, ReqStatus, Request},
+    sim_env::SimEnv,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// 整个请求(DAG)的超时时间(ms)
+    pub request_ms: f32,
+    /// 单个函数任务的超时时间(ms)
+    pub fn_ms: f32,
+    /// 超时的请求在 score 中额外计入的延迟(ms)
+    pub penalty_ms: f32,
+}
+
+impl TimeoutConfig {
+    pub fn enabled(&self) -> bool {
+        self.request_ms > 0.0 || self.fn_ms > 0.0
+    }
+
+    pub fn check_valid(&self, errs: &mut Vec<ConfigError>) {
+        for (name, v) in [("request_ms", self.request_ms), ("fn_ms", self.fn_ms), ("penalty_ms", self.penalty_ms)] {
+            if !(v.is_finite() && v >= 0.0) {
+                errs.push(ConfigError::new(format!("timeout.{}", name), format!("should be non-negative, got {}", v)));
+            }
+        }
+    }
+}
+
+/// 请求最早超时的帧，request_frames、fn_frames 为 0 表示不限制
+pub fn timeout_deadline(req: &Request, request_frames: usize, fn_frames: usize) -> Option<usize> {
+    let by_req = (request_frames > 0).then(|| req.begin_frame + request_frames);
+    let by_fn = if fn_frames > 0 {
+        req.fn_metric
+            .iter()
+            .filter(|(fnid, _)| !req.done_fns.contains_key(fnid))
+            .filter_map(|(_, m)| m.sche_time)
+            .min()
+            .map(|sche| sche + fn_frames)
+    } else {
+        None
+    };
+    by_req.into_iter().chain(by_fn).min()
+}
+
+impl SimEnv {
+    fn timeout_frames(&self) -> (usize, usize) {
+        let conf = &self.help.config().timeout;
+        let frames = |ms: f32| if ms > 0.0 { self.help.config().ms_to_frames(ms) } else { 0 };
+        (frames(conf.request_ms), frames(conf.fn_ms))
+    }
+
+    /// 所有在途请求中最早超时的帧，事件驱动推进时不能跳过
+    pub fn timeout_next_deadline(&self) -> Option<usize> {
+        if !self.help.config().timeout.enabled() {
+            return None;
+        }
+        let (request_frames, fn_frames) = self.timeout_frames();
+        self.core
+            .requests()
+            .values()
+            .filter_map(|req| timeout_deadline(req, request_frames, fn_frames))
+            .min()
+    }
+
+    /// 帧末取消所有到期的请求
+    pub fn timeout_check(&self) {
+        if !self.help.config().timeout.enabled() {
+            return;
+        }
+        let (request_frames, fn_frames) = self.timeout_frames();
+        let now = self.current_frame();
+        let expired = self.core
+            .requests()
+            .values()
+            .filter(|req| {
+                timeout_deadline(req, request_frames, fn_frames).is_some_and(|d| d <= now)
+            })
+            .map(|req| req.req_id)
+            .collect::<Vec<_>>();
+        for req_id in expired {
+            self.on_request_timeout(req_id);
+        }
+    }
+
+    /// 移除请求还没完成的任务，记为超时
+    pub fn on_request_timeout(&self, req_id: ReqId) {
+        let mut req = self.core.requests_mut().remove(&req_id).unwrap();
+        for (&fnid, &nid) in req.fn_node.iter() {
+            if !req.done_fns.contains_key(&fnid) {
+                self.node(nid).remove_task(req_id, fnid);
+            }
+        }
+        req.end_frame = self.current_frame();
+        req.status = ReqStatus::TimedOut;
+        if let Some(record) = self.help.metric_record().as_ref() {
+            record.log_done_request(&req);
+        }
+        self.core.done_requests_mut().push(req);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actions::ESActionWrapper,
+        config::Config,
+        node::EnvNodeExt,
+        request::ReqStatus,
+        score::EnvMetricExt,
+        sim_env::SimEnv,
+    };
+
+    #[test]
+    fn test_request_timeout() {
+        let mut config = Config::new_test();
+        config.total_frame = 50;
+        config.timeout.request_ms = 2.0;
+        let mut env = SimEnv::new(config);
+        env.step(1);
+
+        {
+            let done = env.core.done_requests();
+            let timed_out = done.iter().filter(|r| r.timed_out()).collect::<Vec<_>>();
+            assert!(!timed_out.is_empty());
+            for req in timed_out {
+                assert_eq!(req.end_frame, req.begin_frame + 2);
+            }
+            // 超时请求的任务都已从节点上移除
+            for n in env.core.nodes().iter() {
+                for (reqid, _) in n.tasks() {
+                    assert!(env.core.requests().contains_key(&reqid));
+                }
+            }
+        }
+        // 延迟指标跳过超时请求，不会因为缺少完成时间而出错
+        assert!(env.req_done_time_avg() <= 2.0);
+        env.req_exe_time_avg();
+        assert!(env.timed_out_req_cnt() > 0);
+        assert!(env.score() <= 0.0);
+    }
+
+    #[test]
+    fn test_request_timeout_mixed() {
+        let mut config = Config::new_test();
+        config.total_frame = 100;
+        config.timeout.request_ms = 6.0;
+        // random 调度不可复现，换成 greedy
+        config.mech.sche.values_mut().for_each(|v| *v = None);
+        config.mech.sche.insert("greedy".to_owned(), Some("".to_owned()));
+        let mut env = SimEnv::new(config);
+
+        let mut mixed_frames = 0;
+        while !env.step_frame(ESActionWrapper::Int(0)).1 {
+            let done = env.core.done_requests();
+            let requests = env.core.requests();
+            let nodes = env.core.nodes();
+            // 取消的请求不再占用节点上的 pending_tasks 和容器的 req_fn_state
+            for req in done.iter().filter(|r| r.timed_out()) {
+                for n in nodes.iter() {
+                    assert!(n.tasks().iter().all(|&(reqid, _)| reqid != req.req_id));
+                    for c in n.fn_containers.borrow().values() {
+                        assert!(!c.req_fn_state.contains_key(&req.req_id));
+                    }
+                }
+            }
+            // 没超时的请求还在，未完成的任务留在调度到的节点上
+            for req in requests.values() {
+                for (&fnid, &nid) in req.fn_node.iter() {
+                    if !req.done_fns.contains_key(&fnid) {
+                        assert!(env.node(nid).tasks().contains(&(req.req_id, fnid)));
+                    }
+                }
+            }
+            let has_timed_out = done.iter().any(|r| r.timed_out());
+            let has_pending = requests.values().any(|r| !r.fn_node.is_empty());
+            if has_timed_out && has_pending {
+                mixed_frames += 1;
+            }
+        }
+
+        let done = env.core.done_requests();
+        assert!(done.iter().any(|r| r.timed_out()));
+        assert!(done.iter().any(|r| r.status == ReqStatus::Done));
+        assert!(mixed_frames > 0);
+    }
+}
@@ -7,6 +7,7 @@ use std::{
 
 use crate::sim_env::SimEnv;
 use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
 use rand::Rng;
 // use windows::Win32::{
 //     Foundation::FILETIME,
@@ -23,7 +24,7 @@ use rand::Rng;
 //     a
 // }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 // 滑动窗口
 pub struct Window {
     // 存储的浮点数
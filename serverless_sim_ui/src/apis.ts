@@ -107,6 +107,7 @@ export class ResetRespSuccess {
 export class ResetRespInvalidConfig {
     constructor(
         public msg:string,
+        public errors:any,
     ){}
 }
 
@@ -243,6 +244,7 @@ export class RlStepResp{
 
 export class RlStepReq {
     constructor(
+        public env_id:string,
         public action:number,
     ){}
 }
@@ -255,3 +257,645 @@ export namespace apis {
 }
 
 
+
+
+export class StepBatchRespSuccess {
+    constructor(
+        public states:string[],
+        public scores:number[],
+        public stops:boolean[],
+        public infos:string[],
+    ){}
+}
+
+export class StepBatchRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class StepBatchRespInvalidReq {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class StepBatchResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| StepBatchRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| StepBatchRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    invalid_req():undefined| StepBatchRespInvalidReq{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class StepBatchReq {
+    constructor(
+        public env_ids:string[],
+        public actions:number[],
+    ){}
+}
+
+export namespace apis {
+    export async function step_batch(req:StepBatchReq):Promise<StepBatchResp>{
+        let res:any = await axios.post("/api/step_batch", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class SnapshotRespSuccess {
+    constructor(
+        public path:string,
+    ){}
+}
+
+export class SnapshotRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class SnapshotRespFailed {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class SnapshotResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| SnapshotRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| SnapshotRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    failed():undefined| SnapshotRespFailed{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class SnapshotReq {
+    constructor(
+        public env_id:string,
+        public path:string,
+    ){}
+}
+
+export namespace apis {
+    export async function snapshot(req:SnapshotReq):Promise<SnapshotResp>{
+        let res:any = await axios.post("/api/snapshot", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class RestoreRespSuccess {
+    constructor(
+        public env_id:string,
+    ){}
+}
+
+export class RestoreRespFailed {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RestoreResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| RestoreRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    failed():undefined| RestoreRespFailed{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class RestoreReq {
+    constructor(
+        public path:string,
+    ){}
+}
+
+export namespace apis {
+    export async function restore(req:RestoreReq):Promise<RestoreResp>{
+        let res:any = await axios.post("/api/restore", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class ForkRespSuccess {
+    constructor(
+        public env_id:string,
+    ){}
+}
+
+export class ForkRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class ForkRespFailed {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class ForkResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| ForkRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| ForkRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    failed():undefined| ForkRespFailed{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class ForkReq {
+    constructor(
+        public env_id:string,
+        public mech:any,
+    ){}
+}
+
+export namespace apis {
+    export async function fork(req:ForkReq):Promise<ForkResp>{
+        let res:any = await axios.post("/api/fork", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class SpacesRespSuccess {
+    constructor(
+        public version:number,
+        public observation:any,
+        public action:any,
+    ){}
+}
+
+export class SpacesRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class SpacesResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| SpacesRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| SpacesRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class SpacesReq {
+    constructor(
+        public env_id:string,
+    ){}
+}
+
+export namespace apis {
+    export async function spaces(req:SpacesReq):Promise<SpacesResp>{
+        let res:any = await axios.post("/api/spaces", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class StepTypedRespSuccess {
+    constructor(
+        public obs:number[],
+        public score:number,
+        public stop:boolean,
+        public info:string,
+    ){}
+}
+
+export class StepTypedRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class StepTypedRespInvalidAction {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class StepTypedResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| StepTypedRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| StepTypedRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    invalid_action():undefined| StepTypedRespInvalidAction{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class StepTypedReq {
+    constructor(
+        public env_id:string,
+        public action:number[],
+    ){}
+}
+
+export namespace apis {
+    export async function step_typed(req:StepTypedReq):Promise<StepTypedResp>{
+        let res:any = await axios.post("/api/step_typed", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class RlAttachRespSuccess {
+    constructor(
+        public points:string[],
+    ){}
+}
+
+export class RlAttachRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlAttachRespInvalidReq {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlAttachResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| RlAttachRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| RlAttachRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    invalid_req():undefined| RlAttachRespInvalidReq{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class RlAttachReq {
+    constructor(
+        public env_id:string,
+        public points:string[],
+    ){}
+}
+
+export namespace apis {
+    export async function rl_attach(req:RlAttachReq):Promise<RlAttachResp>{
+        let res:any = await axios.post("/api/rl_attach", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class RlPollRespPending {
+    constructor(
+        public decision_id:number,
+        public point:string,
+        public fnid:number,
+        public reqid:number,
+        public default_choice:number,
+        public obs:number[],
+        public action_space:any,
+    ){}
+}
+
+export class RlPollRespNoPending {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlPollRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlPollResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    pending():undefined| RlPollRespPending{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    no_pending():undefined| RlPollRespNoPending{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| RlPollRespEnvNotFound{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class RlPollReq {
+    constructor(
+        public env_id:string,
+        public wait_ms:number,
+    ){}
+}
+
+export namespace apis {
+    export async function rl_poll(req:RlPollReq):Promise<RlPollResp>{
+        let res:any = await axios.post("/api/rl_poll", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class RlActRespSuccess {
+    constructor(
+        public decision_id:number,
+    ){}
+}
+
+export class RlActRespInvalidAction {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlActRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class RlActResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| RlActRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    invalid_action():undefined| RlActRespInvalidAction{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| RlActRespEnvNotFound{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class RlActReq {
+    constructor(
+        public env_id:string,
+        public decision_id:number,
+        public action:number[],
+    ){}
+}
+
+export namespace apis {
+    export async function rl_act(req:RlActReq):Promise<RlActResp>{
+        let res:any = await axios.post("/api/rl_act", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
+
+
+export class AuditLogRespSuccess {
+    constructor(
+        public records:any,
+        public next_seq:number,
+    ){}
+}
+
+export class AuditLogRespEnvNotFound {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class AuditLogRespInvalidReq {
+    constructor(
+        public msg:string,
+    ){}
+}
+
+export class AuditLogResp{
+    constructor(
+        private kernel: any,
+        private id: number
+    ) {}
+    
+    success():undefined| AuditLogRespSuccess{
+        if(this.id==1){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    env_not_found():undefined| AuditLogRespEnvNotFound{
+        if(this.id==2){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+    invalid_req():undefined| AuditLogRespInvalidReq{
+        if(this.id==3){
+            return this.kernel
+        }
+        return undefined
+    }
+    
+}
+
+
+export class AuditLogReq {
+    constructor(
+        public env_id:string,
+        public from_seq:number,
+        public limit:number,
+        public kind:string,
+        public fnid:number,
+        public reqid:number,
+    ){}
+}
+
+export namespace apis {
+    export async function audit_log(req:AuditLogReq):Promise<AuditLogResp>{
+        let res:any = await axios.post("/api/audit_log", req)
+        return new GetEnvIdResp(res.data.kernel,res.data.id)
+    }
+}
+
+
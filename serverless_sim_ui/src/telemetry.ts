@@ -0,0 +1,42 @@
+const SIMSERVER_ADDR = "/api/"
+
+export interface NodeTelemetry {
+    node: number
+    cpu: number
+    mem: number
+    cpu_limit: number
+    mem_limit: number
+}
+
+export interface ScheCmdTelemetry {
+    reqid: number
+    fnid: number
+    nid: number
+}
+
+/// 和 serverless_sim/src/telemetry.rs 的 FrameTelemetry 对应
+export interface FrameTelemetry {
+    frame: number
+    frame_cnt: number
+    running_reqs: number
+    done_reqs: number
+    nodes: NodeTelemetry[]
+    fn_containers: { [fnid: string]: number }
+    sche_cmds: ScheCmdTelemetry[]
+}
+
+/// 订阅 env 的逐帧遥测，返回的 EventSource 调用 close() 取消订阅
+export function watch_telemetry(
+    env_id: string,
+    on_frame: (frame: FrameTelemetry) => void,
+    on_lagged?: (skipped: number) => void,
+): EventSource {
+    const source = new EventSource(SIMSERVER_ADDR + "telemetry?env_id=" + encodeURIComponent(env_id))
+    source.addEventListener("frame", (e) => {
+        on_frame(JSON.parse((e as MessageEvent).data))
+    })
+    source.addEventListener("lagged", (e) => {
+        on_lagged?.(Number((e as MessageEvent).data))
+    })
+    return source
+}